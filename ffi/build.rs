@@ -0,0 +1,20 @@
+//! Generates the C header from `src/lib.rs` into `OUT_DIR`.
+//! The checked-in `include/chip8.h` is only rewritten when
+//! `CHIP8_FFI_WRITE_HEADER` is set, so builds never touch the source tree.
+
+use std::{env, path::PathBuf};
+
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=CHIP8_FFI_WRITE_HEADER");
+
+    let header = cbindgen::generate(&crate_dir).expect("Should generate the C header");
+    header.write_to_file(out_dir.join("chip8.h"));
+    if env::var_os("CHIP8_FFI_WRITE_HEADER").is_some() {
+        header.write_to_file(crate_dir.join("include").join("chip8.h"));
+    }
+}
@@ -0,0 +1,168 @@
+/* CHIP-8 emulator C API */
+
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from ffi/src/lib.rs. Do not edit by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Width of the framebuffer in pixels
+ */
+#define CHIP8_DISPLAY_WIDTH 64
+
+/**
+ * Height of the framebuffer in pixels
+ */
+#define CHIP8_DISPLAY_HEIGHT 32
+
+/**
+ * An emulator instance. Opaque to C.
+ */
+typedef struct Chip8 Chip8;
+
+/**
+ * Behavior Configurations for conflicting implementations
+ * (see `BehaviorConfig` on the Rust side)
+ */
+typedef struct Chip8Config {
+  /**
+   * Does it reset V(F) register to 0 for 8xy1, 8xy2 and 8xy3 instructions
+   */
+  bool vf_reset;
+  /**
+   * Does it increment I register on save and load operations
+   */
+  bool increment_i_on_save_load;
+  /**
+   * Do 8xy6 and 8xyE shift V(x) in place, ignoring V(y)
+   */
+  bool shift;
+  /**
+   * Does Bnnn jump to nnn + V(x) instead of nnn + V(0)
+   */
+  bool jump;
+  /**
+   * Do sprites wrap around the edges of the screen instead of being clipped
+   */
+  bool wrap;
+} Chip8Config;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create an emulator with a ROM loaded.
+ * `config` may be NULL to use the defaults.
+ * Returns NULL if `rom` is NULL while `len` isn't 0.
+ *
+ * # Safety
+ * `rom` must point to `len` readable bytes, and `config` must be NULL or
+ * point to a valid `Chip8Config`.
+ */
+struct Chip8 *chip8_new(const uint8_t *rom, size_t len, const struct Chip8Config *config);
+
+/**
+ * Execute one instruction, with the timers following the wall clock.
+ * Returns 1 if the display changed, 0 if not, and -1 if the program crashed.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet.
+ */
+int32_t chip8_step(struct Chip8 *chip);
+
+/**
+ * Execute `instructions` instructions and tick the timers once.
+ * Call it 60 times a second.
+ * Returns 1 if the display changed, 0 if not, and -1 if the program crashed.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet.
+ */
+int32_t chip8_run_frame(struct Chip8 *chip, uint32_t instructions);
+
+/**
+ * Set which keys are held down. Bit N set => key N (0x0 to 0xF) is pressed.
+ * Keys that changed since the last call count as pressed or released.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet.
+ */
+void chip8_set_keys(struct Chip8 *chip, uint16_t keys);
+
+/**
+ * A key (0x0 to 0xF) was pressed. Others are ignored.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet.
+ */
+void chip8_key_down(struct Chip8 *chip, uint8_t key);
+
+/**
+ * A key (0x0 to 0xF) was released. Others are ignored.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet.
+ */
+void chip8_key_up(struct Chip8 *chip, uint8_t key);
+
+/**
+ * The display as `CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT` bytes
+ * (0 = off, 1 = on), row-major.
+ * The pointer stays valid until the next call on this emulator.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet.
+ */
+const uint8_t *chip8_framebuffer(struct Chip8 *chip);
+
+/**
+ * Should the buzzer be playing ?
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet.
+ */
+bool chip8_sound_active(const struct Chip8 *chip);
+
+/**
+ * Size of the buffer needed by `chip8_save_state`
+ */
+size_t chip8_state_size(void);
+
+/**
+ * Write a save state into `out`.
+ * Returns the number of bytes written, or 0 if `len` is too small.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet,
+ * and `out` must point to `len` writable bytes.
+ */
+size_t chip8_save_state(const struct Chip8 *chip, uint8_t *out, size_t len);
+
+/**
+ * Restore a save state made by `chip8_save_state`.
+ * Returns false (and leaves the emulator untouched) if it is invalid.
+ *
+ * # Safety
+ * `chip` must come from `chip8_new` and not be freed yet,
+ * and `data` must point to `len` readable bytes.
+ */
+bool chip8_load_state(struct Chip8 *chip, const uint8_t *data, size_t len);
+
+/**
+ * Destroy an emulator. NULL is ignored.
+ *
+ * # Safety
+ * `chip` must be NULL, or come from `chip8_new` and not be freed yet.
+ */
+void chip8_free(struct Chip8 *chip);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CHIP8_H */
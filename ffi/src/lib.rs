@@ -0,0 +1,236 @@
+//! ### C ABI for the CHIP-8 Emulator
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Builds as `libchip8_ffi.so` / `libchip8_ffi.a`.
+//! The header in `include/chip8.h` is regenerated with
+//! `CHIP8_FFI_WRITE_HEADER=1 cargo build -p chip8-ffi`.
+
+use chip8::{state::STATE_SIZE, structs::BehaviorConfig, Chip, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+/// Width of the framebuffer in pixels
+pub const CHIP8_DISPLAY_WIDTH: usize = 64;
+/// Height of the framebuffer in pixels
+pub const CHIP8_DISPLAY_HEIGHT: usize = 32;
+
+// The header needs plain numbers, so make sure they stay in sync with the core
+const _: () =
+    assert!(CHIP8_DISPLAY_WIDTH == DISPLAY_WIDTH && CHIP8_DISPLAY_HEIGHT == DISPLAY_HEIGHT);
+
+/// Behavior Configurations for conflicting implementations
+/// (see `BehaviorConfig` on the Rust side)
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Chip8Config {
+    /// Does it reset V(F) register to 0 for 8xy1, 8xy2 and 8xy3 instructions
+    pub vf_reset: bool,
+    /// Does it increment I register on save and load operations
+    pub increment_i_on_save_load: bool,
+    /// Do 8xy6 and 8xyE shift V(x) in place, ignoring V(y)
+    pub shift: bool,
+    /// Does Bnnn jump to nnn + V(x) instead of nnn + V(0)
+    pub jump: bool,
+    /// Do sprites wrap around the edges of the screen instead of being clipped
+    pub wrap: bool,
+}
+
+/// An emulator instance. Opaque to C.
+pub struct Chip8 {
+    chip: Chip,
+    /// One byte per pixel (0 or 1), row-major. Filled by `chip8_framebuffer`.
+    framebuffer: [u8; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+    /// Set once the program does something invalid (eg: unknown instruction).
+    /// Nothing runs anymore after that.
+    crashed: bool,
+}
+
+impl Chip8 {
+    /// Run `f` on the chip, turning panics into a crash.
+    /// Returns 1 if the display changed, 0 if not, and -1 if crashed.
+    fn run(&mut self, f: impl FnOnce(&mut Chip) -> (bool, bool)) -> i32 {
+        if self.crashed {
+            return -1;
+        }
+
+        let chip = &mut self.chip;
+        match panic::catch_unwind(AssertUnwindSafe(|| f(chip))) {
+            Ok((display_update, _)) => display_update as i32,
+            Err(_) => {
+                self.crashed = true;
+                -1
+            }
+        }
+    }
+}
+
+/// Create an emulator with a ROM loaded.
+/// `config` may be NULL to use the defaults.
+/// Returns NULL if `rom` is NULL while `len` isn't 0.
+///
+/// # Safety
+/// `rom` must point to `len` readable bytes, and `config` must be NULL or
+/// point to a valid `Chip8Config`.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_new(
+    rom: *const u8,
+    len: usize,
+    config: *const Chip8Config,
+) -> *mut Chip8 {
+    let program = match (rom.is_null(), len) {
+        (_, 0) => &[][..],
+        (true, _) => return ptr::null_mut(),
+        (false, _) => slice::from_raw_parts(rom, len),
+    };
+
+    let mut behavior = BehaviorConfig::default();
+    if let Some(config) = config.as_ref() {
+        behavior.vf_reset = config.vf_reset;
+        behavior.increment_i_on_save_load = config.increment_i_on_save_load;
+        behavior.shift = config.shift;
+        behavior.jump = config.jump;
+        behavior.wrap = config.wrap;
+    }
+
+    Box::into_raw(Box::new(Chip8 {
+        chip: Chip::new(program, behavior),
+        framebuffer: [0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+        crashed: false,
+    }))
+}
+
+/// Execute one instruction, with the timers following the wall clock.
+/// Returns 1 if the display changed, 0 if not, and -1 if the program crashed.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step(chip: *mut Chip8) -> i32 {
+    (*chip).run(|chip| chip.process_instruction())
+}
+
+/// Execute `instructions` instructions and tick the timers once.
+/// Call it 60 times a second.
+/// Returns 1 if the display changed, 0 if not, and -1 if the program crashed.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(chip: *mut Chip8, instructions: u32) -> i32 {
+    (*chip).run(|chip| chip.run_frame(instructions))
+}
+
+/// Set which keys are held down. Bit N set => key N (0x0 to 0xF) is pressed.
+/// Keys that changed since the last call count as pressed or released.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_keys(chip: *mut Chip8, keys: u16) {
+    (*chip).chip.keypad_mut().set_held(keys);
+}
+
+/// A key (0x0 to 0xF) was pressed. Others are ignored.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_key_down(chip: *mut Chip8, key: u8) {
+    (*chip).chip.keypad_mut().key_down(key);
+}
+
+/// A key (0x0 to 0xF) was released. Others are ignored.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_key_up(chip: *mut Chip8, key: u8) {
+    (*chip).chip.keypad_mut().key_up(key);
+}
+
+/// The display as `CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT` bytes
+/// (0 = off, 1 = on), row-major.
+/// The pointer stays valid until the next call on this emulator.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(chip: *mut Chip8) -> *const u8 {
+    let chip = &mut *chip;
+    for (byte, pixel) in chip
+        .framebuffer
+        .iter_mut()
+        .zip(chip.chip.display().iter().flatten())
+    {
+        *byte = *pixel as u8;
+    }
+    chip.framebuffer.as_ptr()
+}
+
+/// Should the buzzer be playing ?
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(chip: *const Chip8) -> bool {
+    (*chip).chip.is_playing_sound()
+}
+
+/// Size of the buffer needed by `chip8_save_state`
+#[no_mangle]
+pub extern "C" fn chip8_state_size() -> usize {
+    STATE_SIZE
+}
+
+/// Write a save state into `out`.
+/// Returns the number of bytes written, or 0 if `len` is too small.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet,
+/// and `out` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(chip: *const Chip8, out: *mut u8, len: usize) -> usize {
+    if out.is_null() || len < STATE_SIZE {
+        return 0;
+    }
+
+    let out = &mut *(out as *mut [u8; STATE_SIZE]);
+    (*chip).chip.save_state(out);
+    STATE_SIZE
+}
+
+/// Restore a save state made by `chip8_save_state`.
+/// Returns false (and leaves the emulator untouched) if it is invalid.
+///
+/// # Safety
+/// `chip` must come from `chip8_new` and not be freed yet,
+/// and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(chip: *mut Chip8, data: *const u8, len: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+
+    let chip = &mut *chip;
+    let loaded = chip
+        .chip
+        .load_state(slice::from_raw_parts(data, len))
+        .is_ok();
+    if loaded {
+        chip.crashed = false;
+    }
+    loaded
+}
+
+/// Destroy an emulator. NULL is ignored.
+///
+/// # Safety
+/// `chip` must be NULL, or come from `chip8_new` and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(chip: *mut Chip8) {
+    if !chip.is_null() {
+        drop(Box::from_raw(chip));
+    }
+}
@@ -0,0 +1,90 @@
+/*
+ * Drives the emulator through the C API.
+ * Usage: ffi <rom>
+ *
+ * Runs the ROM for FRAMES frames and prints the display as rows of '#' and '.',
+ * checking save states along the way. Exits with 1 on any failure.
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "chip8.h"
+
+#define FRAMES 60
+#define INSTRUCTIONS_PER_FRAME 15
+#define PIXELS (CHIP8_DISPLAY_WIDTH * CHIP8_DISPLAY_HEIGHT)
+
+#define CHECK(cond)                                                          \
+    do {                                                                     \
+        if (!(cond)) {                                                       \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, \
+                    #cond);                                                  \
+            exit(1);                                                         \
+        }                                                                    \
+    } while (0)
+
+static size_t read_file(const char *path, uint8_t *buf, size_t cap) {
+    FILE *file = fopen(path, "rb");
+    CHECK(file != NULL);
+    size_t len = fread(buf, 1, cap, file);
+    fclose(file);
+    return len;
+}
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+
+    uint8_t rom[4096];
+    size_t len = read_file(argv[1], rom, sizeof(rom));
+    CHECK(len > 0);
+
+    Chip8Config config = {
+        .vf_reset = true,
+        .increment_i_on_save_load = true,
+        .shift = false,
+        .jump = false,
+        .wrap = false,
+    };
+    Chip8 *chip = chip8_new(rom, len, &config);
+    CHECK(chip != NULL);
+    CHECK(chip8_new(NULL, 10, NULL) == NULL);
+
+    chip8_set_keys(chip, 0);
+    for (int i = 0; i < FRAMES; i++) {
+        CHECK(chip8_run_frame(chip, INSTRUCTIONS_PER_FRAME) >= 0);
+    }
+    CHECK(!chip8_sound_active(chip));
+
+    uint8_t display[PIXELS];
+    memcpy(display, chip8_framebuffer(chip), PIXELS);
+
+    /* Save, clobber the display with 00E0 run from a fresh state, then restore */
+    size_t state_size = chip8_state_size();
+    uint8_t *state = malloc(state_size);
+    CHECK(chip8_save_state(chip, state, state_size - 1) == 0);
+    CHECK(chip8_save_state(chip, state, state_size) == state_size);
+
+    uint8_t clear[] = {0x00, 0xE0};
+    Chip8 *other = chip8_new(clear, sizeof(clear), NULL);
+    CHECK(chip8_step(other) == 1);
+    CHECK(chip8_load_state(other, state, state_size));
+    CHECK(memcmp(chip8_framebuffer(other), display, PIXELS) == 0);
+
+    state[0] ^= 0xFF;
+    CHECK(!chip8_load_state(other, state, state_size));
+
+    for (int y = 0; y < CHIP8_DISPLAY_HEIGHT; y++) {
+        for (int x = 0; x < CHIP8_DISPLAY_WIDTH; x++) {
+            putchar(display[y * CHIP8_DISPLAY_WIDTH + x] ? '#' : '.');
+        }
+        putchar('\n');
+    }
+
+    free(state);
+    chip8_free(other);
+    chip8_free(chip);
+    chip8_free(NULL);
+    return 0;
+}
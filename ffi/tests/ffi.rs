@@ -0,0 +1,73 @@
+//! Compiles `tests/ffi.c` against the shared library and checks that it
+//! sees the same display as the Rust API.
+//!
+//! (The shared library carries its own dependencies, while linking the static
+//! one would need whatever `chip8` features cargo unified in this build.)
+
+#![cfg(unix)]
+
+use chip8::{structs::BehaviorConfig, Chip};
+use std::{env, fs, path::PathBuf, process::Command};
+
+const ROM: &str = "../roms/tests/ibm.ch8";
+
+/// `target/<profile>`, where cargo puts `libchip8_ffi.so`
+fn target_dir() -> PathBuf {
+    let mut dir = env::current_exe().unwrap();
+    dir.pop(); // test binary
+    dir.pop(); // deps/
+    dir
+}
+
+fn expected_display() -> String {
+    let mut chip = Chip::new(fs::read(ROM).unwrap(), BehaviorConfig::default());
+    for _ in 0..60 {
+        chip.run_frame(15);
+    }
+
+    chip.display()
+        .iter()
+        .map(|row| {
+            let mut line: String = row.iter().map(|on| if *on { '#' } else { '.' }).collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+#[test]
+fn test_header_up_to_date() {
+    let generated = fs::read_to_string(PathBuf::from(env!("OUT_DIR")).join("chip8.h")).unwrap();
+    let checked_in = fs::read_to_string("include/chip8.h").unwrap();
+    assert!(
+        generated == checked_in,
+        "include/chip8.h is out of date, regenerate it with CHIP8_FFI_WRITE_HEADER=1"
+    );
+}
+
+#[test]
+fn test_c_program() {
+    let dir = target_dir();
+    let exe = dir.join("chip8_ffi_test");
+
+    let status = Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_owned()))
+        .args(["-std=c99", "-Wall", "-Werror", "-Iinclude", "tests/ffi.c"])
+        .arg(format!("-L{}", dir.display()))
+        .arg(format!("-Wl,-rpath,{}", dir.display()))
+        .args(["-lchip8_ffi", "-o"])
+        .arg(&exe)
+        .status()
+        .expect("Should run the C compiler");
+    assert!(status.success());
+
+    let output = Command::new(&exe).arg(ROM).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let display = String::from_utf8(output.stdout).unwrap();
+    assert!(display.contains('#'));
+    assert_eq!(display, expected_display());
+}
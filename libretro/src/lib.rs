@@ -0,0 +1,410 @@
+//! ### libretro core for the CHIP-8 Emulator
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Builds as `chip8_libretro.so` (`.dll`/`.dylib`), loadable by RetroArch
+//! or any other libretro frontend.
+//! The hex keypad is mapped onto the RetroPad (see `KEYMAP`), and the buzzer
+//! is a square wave played while the sound timer is running. Quirks follow
+//! the platform of the ROM's extension, as in the CLI.
+
+mod sys;
+
+use chip8::{
+    palette::Palette,
+    rom::{self, Rom},
+    state::STATE_SIZE,
+    Chip, DISPLAY_HEIGHT, DISPLAY_WIDTH,
+};
+use std::{
+    ffi::{c_char, c_uint, c_void, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    ptr, slice,
+    sync::{Mutex, OnceLock},
+};
+use sys::*;
+
+/// Instructions executed per 60Hz frame (~660 per second)
+const INSTRUCTIONS_PER_FRAME: u32 = 11;
+
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: u32 = 44_100;
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / 60) as usize;
+/// Frequency of the buzzer
+const TONE_HZ: u32 = 440;
+const VOLUME: i16 = 4_000;
+
+/// RetroPad button => hex key, with the description shown by the frontend.
+/// The d-pad is on 2/4/6/8 and A on 5, which is what most games use.
+const KEYMAP: [(c_uint, u8, &CStr); 16] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x2, c"2 (Up)"),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8, c"8 (Down)"),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x4, c"4 (Left)"),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x6, c"6 (Right)"),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x5, c"5"),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x0, c"0"),
+    (RETRO_DEVICE_ID_JOYPAD_Y, 0x1, c"1"),
+    (RETRO_DEVICE_ID_JOYPAD_X, 0x3, c"3"),
+    (RETRO_DEVICE_ID_JOYPAD_L, 0x7, c"7"),
+    (RETRO_DEVICE_ID_JOYPAD_R, 0x9, c"9"),
+    (RETRO_DEVICE_ID_JOYPAD_L2, 0xA, c"A"),
+    (RETRO_DEVICE_ID_JOYPAD_R2, 0xB, c"B"),
+    (RETRO_DEVICE_ID_JOYPAD_L3, 0xC, c"C"),
+    (RETRO_DEVICE_ID_JOYPAD_R3, 0xD, c"D"),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, 0xE, c"E"),
+    (RETRO_DEVICE_ID_JOYPAD_START, 0xF, c"F"),
+];
+
+/// Callbacks handed over by the frontend
+struct Callbacks {
+    environment: Option<retro_environment_t>,
+    video_refresh: Option<retro_video_refresh_t>,
+    audio_sample_batch: Option<retro_audio_sample_batch_t>,
+    input_poll: Option<retro_input_poll_t>,
+    input_state: Option<retro_input_state_t>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+/// Everything about the loaded game
+struct Core {
+    chip: Chip,
+    /// Kept around for `retro_reset`
+    rom: Rom,
+    /// XRGB8888 pixels
+    video: [u32; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+    /// Interleaved stereo samples for one frame
+    audio: [i16; SAMPLES_PER_FRAME * 2],
+    /// Position in the square wave, in samples
+    phase: u32,
+    /// Set once the program does something invalid (eg: unknown instruction).
+    /// Nothing runs anymore after that, until a reset or a state is loaded.
+    crashed: bool,
+}
+
+impl Core {
+    fn new(rom: Rom) -> Self {
+        Core {
+            chip: Chip::new(rom.bytes(), rom.behavior()),
+            rom,
+            video: [0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            audio: [0; SAMPLES_PER_FRAME * 2],
+            phase: 0,
+            crashed: false,
+        }
+    }
+
+    /// Run a frame, turning a panic into a crash rather than unwinding
+    /// into the frontend
+    fn run_frame(&mut self) {
+        if self.crashed {
+            return;
+        }
+        let chip = &mut self.chip;
+        let frame = panic::catch_unwind(AssertUnwindSafe(|| {
+            chip.run_frame(INSTRUCTIONS_PER_FRAME);
+        }));
+        self.crashed = frame.is_err();
+    }
+
+    fn render_video(&mut self) {
+        Palette::default().render(&self.chip.display(), &mut self.video);
+    }
+
+    fn render_audio(&mut self) {
+        let playing = !self.crashed && self.chip.is_playing_sound();
+        let half_period = SAMPLE_RATE / TONE_HZ / 2;
+
+        for frame in self.audio.chunks_mut(2) {
+            let sample = match (playing, (self.phase / half_period) % 2) {
+                (false, _) => 0,
+                (true, 0) => VOLUME,
+                (true, _) => -VOLUME,
+            };
+            frame.fill(sample);
+            self.phase = self.phase.wrapping_add(1);
+        }
+    }
+}
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+/// `rom::EXTENSIONS`, as `ch8|c8|...`
+static EXTENSIONS: OnceLock<CString> = OnceLock::new();
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(cb: retro_environment_t) {
+    CALLBACKS.lock().unwrap().environment = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(cb: retro_video_refresh_t) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(cb);
+}
+
+/// Unused, audio is sent a frame at a time through the batch callback
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_cb: retro_audio_sample_t) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(cb: retro_audio_sample_batch_t) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(cb: retro_input_poll_t) {
+    CALLBACKS.lock().unwrap().input_poll = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(cb: retro_input_state_t) {
+    CALLBACKS.lock().unwrap().input_state = Some(cb);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut retro_system_info) {
+    *info = retro_system_info {
+        library_name: c"CHIP-8".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: EXTENSIONS
+            .get_or_init(|| {
+                let extensions: Vec<_> = rom::EXTENSIONS.iter().map(|(ext, _)| *ext).collect();
+                CString::new(extensions.join("|")).unwrap()
+            })
+            .as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut retro_system_av_info) {
+    *info = retro_system_av_info {
+        geometry: retro_game_geometry {
+            base_width: DISPLAY_WIDTH as c_uint,
+            base_height: DISPLAY_HEIGHT as c_uint,
+            max_width: DISPLAY_WIDTH as c_uint,
+            max_height: DISPLAY_HEIGHT as c_uint,
+            aspect_ratio: DISPLAY_WIDTH as f32 / DISPLAY_HEIGHT as f32,
+        },
+        timing: retro_system_timing {
+            fps: FPS,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        *core = Core::new(core.rom.clone());
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let (video_refresh, audio_sample_batch, input_poll, input_state) = {
+        let callbacks = CALLBACKS.lock().unwrap();
+        (
+            callbacks.video_refresh,
+            callbacks.audio_sample_batch,
+            callbacks.input_poll,
+            callbacks.input_state,
+        )
+    };
+
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+
+    // Gather the held keys
+    let mut held = 0;
+    if let (Some(input_poll), Some(input_state)) = (input_poll, input_state) {
+        input_poll();
+        for (id, key, _) in KEYMAP {
+            if input_state(0, RETRO_DEVICE_JOYPAD, 0, id) != 0 {
+                held |= 1 << key;
+            }
+        }
+    }
+    core.chip.keypad_mut().set_held(held);
+
+    core.run_frame();
+
+    core.render_video();
+    if let Some(video_refresh) = video_refresh {
+        video_refresh(
+            core.video.as_ptr() as *const c_void,
+            DISPLAY_WIDTH as c_uint,
+            DISPLAY_HEIGHT as c_uint,
+            DISPLAY_WIDTH * 4,
+        );
+    }
+
+    core.render_audio();
+    if let Some(audio_sample_batch) = audio_sample_batch {
+        audio_sample_batch(core.audio.as_ptr(), SAMPLES_PER_FRAME);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    STATE_SIZE
+}
+
+/// # Safety
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = CORE.lock().unwrap();
+    match core.as_ref() {
+        Some(core) if !data.is_null() && size >= STATE_SIZE => {
+            core.chip.save_state(&mut *(data as *mut [u8; STATE_SIZE]));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// # Safety
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = CORE.lock().unwrap();
+    match core.as_mut() {
+        Some(core) if !data.is_null() => {
+            let loaded = core
+                .chip
+                .load_state(slice::from_raw_parts(data as *const u8, size))
+                .is_ok();
+            if loaded {
+                core.crashed = false;
+            }
+            loaded
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+/// `game` must be NULL or point to a valid `retro_game_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const retro_game_info) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    if game.data.is_null() {
+        return false;
+    }
+
+    // The path is only there to pick the platform, the data is what's loaded
+    let platform = match game.path.is_null() {
+        true => None,
+        false => CStr::from_ptr(game.path)
+            .to_str()
+            .ok()
+            .and_then(|path| rom::platform_for(Path::new(path))),
+    };
+    let bytes = slice::from_raw_parts(game.data as *const u8, game.size).to_vec();
+    let Ok(rom) = Rom::new(bytes, platform.unwrap_or(rom::DEFAULT_PLATFORM)) else {
+        return false;
+    };
+
+    if let Some(environment) = CALLBACKS.lock().unwrap().environment {
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !environment(
+            RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+            &mut format as *mut c_uint as *mut c_void,
+        ) {
+            return false;
+        }
+
+        let mut descriptors: Vec<retro_input_descriptor> = KEYMAP
+            .iter()
+            .map(|(id, _, description)| retro_input_descriptor {
+                port: 0,
+                device: RETRO_DEVICE_JOYPAD,
+                index: 0,
+                id: *id,
+                description: description.as_ptr(),
+            })
+            .collect();
+        // Terminated by an empty descriptor
+        descriptors.push(retro_input_descriptor {
+            port: 0,
+            device: 0,
+            index: 0,
+            id: 0,
+            description: ptr::null(),
+        });
+        environment(
+            RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+            descriptors.as_mut_ptr() as *mut c_void,
+        );
+    }
+
+    *CORE.lock().unwrap() = Some(Core::new(rom));
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const retro_game_info,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap() = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -0,0 +1,265 @@
+//! A tiny libretro frontend: loads the built core, feeds it `ibm.ch8`
+//! and checks what comes out of the callbacks.
+
+use chip8::{palette::Palette, rom, structs::BehaviorConfig, Chip};
+use libloading::{Library, Symbol};
+use std::{
+    env,
+    ffi::{c_char, c_uint, c_void, CStr},
+    fs, ptr, slice,
+    sync::Mutex,
+};
+
+const ROM: &str = "../roms/tests/ibm.ch8";
+
+/// Same as the core's
+const INSTRUCTIONS_PER_FRAME: u32 = 11;
+
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS: c_uint = 11;
+
+#[repr(C)]
+struct GameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+#[repr(C)]
+struct SystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+struct InputDescriptor {
+    port: c_uint,
+    device: c_uint,
+    index: c_uint,
+    id: c_uint,
+    description: *const c_char,
+}
+
+/// What the callbacks have seen
+struct Seen {
+    pixel_format: Option<c_uint>,
+    input_descriptors: usize,
+    frame: Vec<u32>,
+    frame_size: (c_uint, c_uint, usize),
+    audio_frames: usize,
+}
+
+static SEEN: Mutex<Seen> = Mutex::new(Seen {
+    pixel_format: None,
+    input_descriptors: 0,
+    frame: vec![],
+    frame_size: (0, 0, 0),
+    audio_frames: 0,
+});
+
+extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    let mut seen = SEEN.lock().unwrap();
+    match cmd {
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => {
+            seen.pixel_format = Some(unsafe { *(data as *const c_uint) });
+            true
+        }
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS => {
+            let mut descriptor = data as *const InputDescriptor;
+            unsafe {
+                while !(*descriptor).description.is_null() {
+                    seen.input_descriptors += 1;
+                    descriptor = descriptor.add(1);
+                }
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+extern "C" fn video_refresh(data: *const c_void, width: c_uint, height: c_uint, pitch: usize) {
+    let mut seen = SEEN.lock().unwrap();
+    let pixels = unsafe { slice::from_raw_parts(data as *const u32, pitch / 4 * height as usize) };
+    seen.frame = pixels.to_vec();
+    seen.frame_size = (width, height, pitch);
+}
+
+extern "C" fn audio_sample_batch(_data: *const i16, frames: usize) -> usize {
+    SEEN.lock().unwrap().audio_frames += frames;
+    frames
+}
+
+extern "C" fn input_poll() {}
+
+extern "C" fn input_state(_port: c_uint, _device: c_uint, _index: c_uint, _id: c_uint) -> i16 {
+    0
+}
+
+/// Path to the core built next to this test
+fn core_path() -> std::path::PathBuf {
+    let mut dir = env::current_exe().unwrap();
+    dir.pop(); // test binary
+    dir.pop(); // deps/
+    dir.join(libloading::library_filename("chip8_libretro"))
+}
+
+fn expected_frame(rom: &[u8], behavior: BehaviorConfig, frames: u32) -> Vec<u32> {
+    let mut chip = Chip::new(rom, behavior);
+    for _ in 0..frames {
+        chip.run_frame(INSTRUCTIONS_PER_FRAME);
+    }
+
+    let mut frame = vec![0; 64 * 32];
+    Palette::default().render(&chip.display(), &mut frame);
+    frame
+}
+
+#[test]
+fn test_stub_frontend() {
+    unsafe {
+        let core = Library::new(core_path()).unwrap();
+        macro_rules! sym {
+            ($name:ident: $ty:ty) => {
+                let $name: Symbol<$ty> = core.get(stringify!($name).as_bytes()).unwrap();
+            };
+        }
+
+        sym!(retro_api_version: extern "C" fn() -> c_uint);
+        sym!(retro_set_environment: extern "C" fn(extern "C" fn(c_uint, *mut c_void) -> bool));
+        sym!(retro_set_video_refresh: extern "C" fn(extern "C" fn(*const c_void, c_uint, c_uint, usize)));
+        sym!(retro_set_audio_sample_batch: extern "C" fn(extern "C" fn(*const i16, usize) -> usize));
+        sym!(retro_set_input_poll: extern "C" fn(extern "C" fn()));
+        sym!(retro_set_input_state: extern "C" fn(extern "C" fn(c_uint, c_uint, c_uint, c_uint) -> i16));
+        sym!(retro_get_system_info: unsafe extern "C" fn(*mut SystemInfo));
+        sym!(retro_init: extern "C" fn());
+        sym!(retro_load_game: unsafe extern "C" fn(*const GameInfo) -> bool);
+        sym!(retro_run: extern "C" fn());
+        sym!(retro_serialize_size: extern "C" fn() -> usize);
+        sym!(retro_serialize: unsafe extern "C" fn(*mut c_void, usize) -> bool);
+        sym!(retro_unserialize: unsafe extern "C" fn(*const c_void, usize) -> bool);
+        sym!(retro_unload_game: extern "C" fn());
+        sym!(retro_deinit: extern "C" fn());
+
+        assert_eq!(retro_api_version(), 1);
+
+        retro_set_environment(environment);
+        retro_set_video_refresh(video_refresh);
+        retro_set_audio_sample_batch(audio_sample_batch);
+        retro_set_input_poll(input_poll);
+        retro_set_input_state(input_state);
+        retro_init();
+
+        let mut info = SystemInfo {
+            library_name: ptr::null(),
+            library_version: ptr::null(),
+            valid_extensions: ptr::null(),
+            need_fullpath: true,
+            block_extract: true,
+        };
+        retro_get_system_info(&mut info);
+        let extensions = CStr::from_ptr(info.valid_extensions).to_str().unwrap();
+        assert_eq!(extensions, "ch8|c8|hc8|sc8|xo8");
+        // Frontends go by the .info file before loading the core
+        let info_file = fs::read_to_string("chip8_libretro.info").unwrap();
+        let supported = format!("supported_extensions = \"{}\"", extensions);
+        assert!(info_file.lines().any(|line| line == supported));
+        assert!(!info.need_fullpath);
+
+        let rom = fs::read(ROM).unwrap();
+        let game = GameInfo {
+            path: ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: ptr::null(),
+        };
+        assert!(retro_load_game(&game));
+
+        for _ in 0..30 {
+            retro_run();
+        }
+
+        // Save half-way through, and check that restoring gets back there
+        let mut state = vec![0u8; retro_serialize_size()];
+        assert!(retro_serialize(
+            state.as_mut_ptr() as *mut c_void,
+            state.len()
+        ));
+        for _ in 0..30 {
+            retro_run();
+        }
+        assert!(retro_unserialize(
+            state.as_ptr() as *const c_void,
+            state.len()
+        ));
+        retro_run();
+
+        {
+            let seen = SEEN.lock().unwrap();
+            assert_eq!(seen.pixel_format, Some(1)); // XRGB8888
+            assert_eq!(seen.input_descriptors, 16);
+            assert_eq!(seen.frame_size, (64, 32, 64 * 4));
+            assert_eq!(
+                seen.frame,
+                expected_frame(&rom, BehaviorConfig::default(), 31)
+            );
+            assert!(seen.frame.contains(&0xFFFFFFFF));
+            assert_eq!(seen.audio_frames, 61 * 735);
+        }
+
+        retro_unload_game();
+
+        // An unknown instruction stops the core, rather than unwinding
+        // through the frontend
+        let rom = [0x01, 0x23];
+        let game = GameInfo {
+            path: ptr::null(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: ptr::null(),
+        };
+        assert!(retro_load_game(&game));
+        for _ in 0..3 {
+            retro_run();
+        }
+        assert_eq!(SEEN.lock().unwrap().audio_frames, 64 * 735);
+        retro_unload_game();
+
+        // Empty and oversize ROMs aren't loaded
+        let too_large = vec![0; rom::max_size(rom::DEFAULT_PLATFORM) + 1];
+        for rom in [&[][..], &too_large] {
+            let game = GameInfo {
+                path: ptr::null(),
+                data: rom.as_ptr() as *const c_void,
+                size: rom.len(),
+                meta: ptr::null(),
+            };
+            assert!(!retro_load_game(&game));
+        }
+
+        // V0 = 3, V1 = 8, 8016, then draw the digit in V0: 4 with the VIP's
+        // shift, 1 with SUPER-CHIP's
+        let rom = [
+            0x60, 0x03, 0x61, 0x08, 0x80, 0x16, 0xF0, 0x29, 0x62, 0x00, 0xD2, 0x25, 0x12, 0x0C,
+        ];
+        let game = GameInfo {
+            path: c"game.sc8".as_ptr(),
+            data: rom.as_ptr() as *const c_void,
+            size: rom.len(),
+            meta: ptr::null(),
+        };
+        assert!(retro_load_game(&game));
+        retro_run();
+        let superchip = BehaviorConfig::for_platform("superchip").unwrap();
+        let frame = SEEN.lock().unwrap().frame.clone();
+        assert_eq!(frame, expected_frame(&rom, superchip, 1));
+        assert_ne!(frame, expected_frame(&rom, BehaviorConfig::default(), 1));
+
+        retro_unload_game();
+        retro_deinit();
+    }
+}
@@ -0,0 +1,168 @@
+//! Lockstep comparison
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Runs the same ROM on two chips with different `BehaviorConfig`s, feeding
+//! both the same keys and random numbers, and finds the first instruction
+//! after which their states differ. That instruction usually points straight
+//! at the quirk a game needs.
+
+use crate::{
+    platform::{Clock, DefaultClock, RandomSource, XorShift},
+    structs::BehaviorConfig,
+    Chip, MEMORY_SIZE,
+};
+use core::fmt;
+
+/// Which part of the state differs first (checked in this order)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference {
+    Pc,
+    IRegister,
+    Registers,
+    Stack,
+    Timers,
+    Memory,
+    Display,
+}
+
+/// Where the two chips went their separate ways
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Divergence {
+    /// Frame it happened in, starting at 0
+    pub frame: u64,
+    /// Instructions executed before it, starting at 0
+    pub instruction: u64,
+    /// Address and opcode of the instruction that made them differ
+    pub pc: u16,
+    pub opcode: u16,
+    pub difference: Difference,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frame {}, instruction {} ({:#05X}: {:04X}), {:?} differs",
+            self.frame, self.instruction, self.pc, self.opcode, self.difference
+        )
+    }
+}
+
+/// Two chips running in lockstep
+pub struct Lockstep<C: Clock = DefaultClock, R: RandomSource = XorShift> {
+    left: Chip<C, R>,
+    right: Chip<C, R>,
+    frame: u64,
+    instructions: u64,
+    divergence: Option<Divergence>,
+}
+
+impl Lockstep {
+    /// Load a program twice, with a random seed shared by both chips
+    pub fn new(program: impl AsRef<[u8]>, left: BehaviorConfig, right: BehaviorConfig) -> Self {
+        #[cfg(feature = "std")]
+        let rng = XorShift::from_entropy();
+        #[cfg(not(feature = "std"))]
+        let rng = XorShift::default();
+
+        let program = program.as_ref();
+        Self::with_chips(
+            Chip::with_platform(program, left, DefaultClock::default(), rng),
+            Chip::with_platform(program, right, DefaultClock::default(), rng),
+        )
+    }
+}
+
+impl<C: Clock, R: RandomSource> Lockstep<C, R> {
+    /// Compare two chips. They should only differ by their `BehaviorConfig`,
+    /// random numbers included.
+    pub fn with_chips(left: Chip<C, R>, right: Chip<C, R>) -> Self {
+        Lockstep {
+            left,
+            right,
+            frame: 0,
+            instructions: 0,
+            divergence: None,
+        }
+    }
+
+    pub fn left(&self) -> &Chip<C, R> {
+        &self.left
+    }
+
+    pub fn right(&self) -> &Chip<C, R> {
+        &self.right
+    }
+
+    /// Frames run so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// The first divergence, once there is one
+    pub fn divergence(&self) -> Option<Divergence> {
+        self.divergence
+    }
+
+    /// Run one 60Hz frame on both chips with the same keys held
+    /// (bit N set => key N held). Like `Chip::run_frame`, the clock isn't used.
+    /// Outputs whether either display was updated.
+    pub fn run_frame(&mut self, instructions: u32, held_keys: u16) -> bool {
+        self.left.keypad_mut().set_held(held_keys);
+        self.right.keypad_mut().set_held(held_keys);
+
+        let mut display_update = false;
+        for _ in 0..instructions {
+            let pc = self.left.pc();
+            let opcode = self
+                .left
+                .memory(pc as usize..pc as usize + 2)
+                .map_or(0, |bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+
+            display_update |= self.left.step().0;
+            display_update |= self.right.step().0;
+
+            if self.divergence.is_none() {
+                self.divergence =
+                    first_difference(&self.left, &self.right).map(|difference| Divergence {
+                        frame: self.frame,
+                        instruction: self.instructions,
+                        pc,
+                        opcode,
+                        difference,
+                    });
+            }
+            self.instructions += 1;
+        }
+
+        self.left.tick_timers();
+        self.right.tick_timers();
+        self.frame += 1;
+        display_update
+    }
+}
+
+fn first_difference<C: Clock, R: RandomSource>(
+    left: &Chip<C, R>,
+    right: &Chip<C, R>,
+) -> Option<Difference> {
+    let timers = |chip: &Chip<C, R>| (chip.delay_timer(), chip.sound_timer());
+
+    if left.pc() != right.pc() {
+        Some(Difference::Pc)
+    } else if left.i_reg() != right.i_reg() {
+        Some(Difference::IRegister)
+    } else if left.registers() != right.registers() {
+        Some(Difference::Registers)
+    } else if left.stack() != right.stack() {
+        Some(Difference::Stack)
+    } else if timers(left) != timers(right) {
+        Some(Difference::Timers)
+    } else if left.memory(0..MEMORY_SIZE) != right.memory(0..MEMORY_SIZE) {
+        Some(Difference::Memory)
+    } else if left.display() != right.display() {
+        Some(Difference::Display)
+    } else {
+        None
+    }
+}
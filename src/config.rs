@@ -0,0 +1,226 @@
+//! Configuration file
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Defaults for the command line options, in `$XDG_CONFIG_HOME/chip8/config.toml`
+//! (`~/.config/chip8/config.toml`, or `%APPDATA%\chip8\config.toml` on Windows).
+//! Sections under `[roms]`, keyed by the SHA-1 of the ROM, override them for
+//! one game. Command line options override both.
+//!
+//! ```toml
+//! ipf = 15
+//! palette = "amber"
+//! keymap = "azerty"
+//!
+//! # roms/games/tetris.ch8
+//! [roms.5f518084744bf3cb8733f6e5454dfd1634320563]
+//! ipf = 30
+//! vf_reset = false
+//! ```
+
+use crate::{
+    frontend::{keymap::Keymap, viewport::Grid},
+    palette::Palette,
+    phosphor::Persistence,
+    rom,
+};
+use serde::{Deserialize, Deserializer};
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// One layer of settings. `None` means "not set here".
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Instructions per frame
+    pub ipf: Option<u32>,
+    pub vf_reset: Option<bool>,
+    pub increment_i: Option<bool>,
+    #[serde(deserialize_with = "parsed_palette")]
+    pub palette: Option<Palette>,
+    #[serde(deserialize_with = "parsed_persistence")]
+    pub persistence: Option<Persistence>,
+    /// A preset name, or the path to a keymap file (relative to the
+    /// configuration file)
+    #[serde(deserialize_with = "parsed_keymap")]
+    pub keymap: Option<Keymap>,
+    /// Window scale (1 to 32)
+    pub scale: Option<u32>,
+    /// `off`, `scanlines` or `grid`
+    #[serde(deserialize_with = "parsed_grid")]
+    pub grid: Option<Grid>,
+    pub fullscreen: Option<bool>,
+    pub shift: Option<bool>,
+    pub jump: Option<bool>,
+    pub wrap: Option<bool>,
+}
+
+impl Settings {
+    /// `other`'s settings where set, and these otherwise
+    pub fn overridden_by(self, other: Settings) -> Settings {
+        Settings {
+            ipf: other.ipf.or(self.ipf),
+            vf_reset: other.vf_reset.or(self.vf_reset),
+            increment_i: other.increment_i.or(self.increment_i),
+            palette: other.palette.or(self.palette),
+            persistence: other.persistence.or(self.persistence),
+            keymap: other.keymap.or(self.keymap),
+            scale: other.scale.or(self.scale),
+            grid: other.grid.or(self.grid),
+            fullscreen: other.fullscreen.or(self.fullscreen),
+            shift: other.shift.or(self.shift),
+            jump: other.jump.or(self.jump),
+            wrap: other.wrap.or(self.wrap),
+        }
+    }
+}
+
+/// The whole configuration file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub defaults: Settings,
+    /// Per-ROM settings, keyed by the lowercase hex SHA-1 of the ROM
+    pub roms: HashMap<String, Settings>,
+}
+
+impl Config {
+    /// Keymap files are relative to the working directory
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        Self::parse_in(text, Path::new(""))
+    }
+
+    /// Keymap files are relative to `dir` (eg: the one the file is in)
+    pub fn parse_in(text: &str, dir: &Path) -> Result<Self, ConfigError> {
+        let error = |reason: String| ConfigError { path: None, reason };
+
+        // `[roms]` is split off by hand, since `#[serde(flatten)]` would
+        // silently accept unknown settings
+        let mut table: toml::Table = toml::from_str(text).map_err(|err| error(err.to_string()))?;
+        resolve_keymap(&mut table, dir);
+        let roms = match table.remove("roms") {
+            Some(mut roms) => {
+                if let Some(roms) = roms.as_table_mut() {
+                    for (_, settings) in roms.iter_mut() {
+                        if let Some(settings) = settings.as_table_mut() {
+                            resolve_keymap(settings, dir);
+                        }
+                    }
+                }
+                roms.try_into::<HashMap<String, Settings>>()
+                    .map_err(|err| error(format!("in [roms]: {}", err)))?
+                    .into_iter()
+                    .map(|(hash, settings)| (hash.to_ascii_lowercase(), settings))
+                    .collect()
+            }
+            None => HashMap::new(),
+        };
+        let defaults = table
+            .try_into()
+            .map_err(|err: toml::de::Error| error(err.to_string()))?;
+
+        Ok(Config { defaults, roms })
+    }
+
+    /// Read a configuration file. A missing file is an empty configuration.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(ConfigError {
+                    path: Some(path.to_owned()),
+                    reason: err.to_string(),
+                })
+            }
+        };
+
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse_in(&text, dir).map_err(|err| ConfigError {
+            path: Some(path.to_owned()),
+            ..err
+        })
+    }
+
+    /// Where the configuration file lives, if there's a home to put it in
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("chip8").join("config.toml"))
+    }
+
+    /// The defaults, overridden by the ROM's section if there is one
+    pub fn settings_for(&self, program: &[u8]) -> Settings {
+        self.defaults
+            .clone()
+            .overridden_by(self.rom_settings(program))
+    }
+
+    /// Only the ROM's section (empty if there is none)
+    pub fn rom_settings(&self, program: &[u8]) -> Settings {
+        self.roms
+            .get(&rom::sha1(program))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Make a keymap file path relative to `dir`, leaving presets as they are
+fn resolve_keymap(table: &mut toml::Table, dir: &Path) {
+    if let Some(toml::Value::String(keymap)) = table.get_mut("keymap") {
+        if Keymap::preset(keymap).is_none() {
+            *keymap = dir.join(&*keymap).display().to_string();
+        }
+    }
+}
+
+fn parsed_palette<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Palette>, D::Error> {
+    let s = String::deserialize(d)?;
+    Palette::parse(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn parsed_persistence<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Persistence>, D::Error> {
+    let s = String::deserialize(d)?;
+    Persistence::parse(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn parsed_grid<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Grid>, D::Error> {
+    let s = String::deserialize(d)?;
+    Grid::parse(&s).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn parsed_keymap<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Keymap>, D::Error> {
+    let s = String::deserialize(d)?;
+    Keymap::from_preset_or_file(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// A configuration file that couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub path: Option<PathBuf>,
+    pub reason: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}: {}", path.display(), self.reason),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+impl Error for ConfigError {}
@@ -0,0 +1,504 @@
+//! Frontend abstraction
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! The emulator only talks to the outside world through the three traits here,
+//! so any video/audio/input library can be plugged in by implementing them.
+//! The bundled `minifb` and `cpal` backends are behind the `frontend-minifb`
+//! and `audio-cpal` features.
+
+#[cfg(feature = "audio-cpal")]
+pub mod beeper;
+pub mod browser;
+pub mod keymap;
+pub mod overlay;
+#[cfg(feature = "frontend-minifb")]
+pub mod screen;
+pub mod screenshot;
+pub mod text;
+#[cfg(feature = "frontend-tty")]
+pub mod tty;
+pub mod viewport;
+pub mod virtual_keypad;
+#[cfg(feature = "frontend-minifb")]
+pub mod window;
+
+use crate::{
+    compare::{Divergence, Lockstep},
+    platform::{Clock, RandomSource},
+    quirks::Quirk,
+    state::STATE_SIZE,
+    structs::BehaviorConfig,
+    Chip, Display,
+};
+use std::{io, path::PathBuf};
+
+/// Instructions per frame if not told otherwise (~660 per second)
+pub const DEFAULT_INSTRUCTIONS_PER_FRAME: u32 = 11;
+
+/// Frames run per frame shown while fast-forwarding
+pub const FAST_FORWARD_FRAMES: u32 = 4;
+
+/// Frames shown per frame run while in slow motion
+pub const SLOW_MOTION_FRAMES: u32 = 4;
+
+/// Speeds `Action::SpeedUp` and `Action::SpeedDown` step through,
+/// in percent of the instructions per frame `run` was given
+pub const SPEEDS: [u32; 9] = [10, 25, 50, 75, 100, 150, 200, 400, 800];
+
+/// Volume change of `Action::VolumeUp` and `Action::VolumeDown`
+pub const VOLUME_STEP: f32 = 0.1;
+
+/// Frames a message from `Renderer::message` stays on screen (2 seconds)
+pub const MESSAGE_FRAMES: u32 = 120;
+
+/// Something that can show the CHIP-8 display.
+/// `draw` or `update` is called exactly once per frame, and is expected to
+/// block until it is time for the next one (eg: vsync, or a 60Hz rate limit).
+pub trait Renderer {
+    /// Show a new frame. Only called when the display has changed.
+    fn draw(&mut self, display: &Display);
+
+    /// Called instead of `draw` when nothing changed,
+    /// so the backend can still process events.
+    fn update(&mut self) {}
+
+    /// The run loop stops once this returns false (eg: the window was closed)
+    fn is_open(&self) -> bool {
+        true
+    }
+
+    /// Show a short message over the display for `MESSAGE_FRAMES` frames
+    /// (eg: to confirm a hotkey), replacing the previous one
+    fn message(&mut self, _text: &str) {}
+
+    /// Save what is on screen, and return where. `None` if the backend can't.
+    fn screenshot(&mut self) -> Option<io::Result<PathBuf>> {
+        None
+    }
+
+    /// What the run loop is doing, given before each `draw` or `update`
+    fn status(&mut self, _status: &Status) {}
+
+    /// Show or hide the status overlay, and return whether it is now shown.
+    /// `None` if the backend has none.
+    fn toggle_overlay(&mut self) -> Option<bool> {
+        None
+    }
+
+    /// Show or hide the virtual keypad, and return whether it is now shown.
+    /// `None` if the backend has none.
+    fn toggle_keypad(&mut self) -> Option<bool> {
+        None
+    }
+
+    /// Cover the screen, or go back to a window, and return whether it now
+    /// covers the screen. `None` if the backend can't.
+    fn toggle_fullscreen(&mut self) -> Option<bool> {
+        None
+    }
+}
+
+/// What `run` is doing, for on-screen displays (see `Renderer::status`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// Frames run since the last one shown: 0 while paused (or between
+    /// slow motion frames), more while fast-forwarding
+    pub frames_run: u32,
+    /// At the current speed
+    pub instructions_per_frame: u32,
+    /// In percent, one of `SPEEDS`
+    pub speed: u32,
+    pub paused: bool,
+    pub behavior: BehaviorConfig,
+    /// Keys the chip sees as held (bit N set => key N held)
+    pub held_keys: u16,
+    /// Register `Fx0A` will store the key into, while it waits for one
+    pub waiting_for_key: Option<u8>,
+}
+
+/// Something that can show two displays side by side, for `run_compare`.
+/// `draw_pair` is called exactly once per frame, and sets the pace.
+pub trait PairRenderer {
+    fn draw_pair(&mut self, left: &Display, right: &Display);
+
+    /// Called once, when the two chips first differ
+    fn diverged(&mut self, _divergence: &Divergence) {}
+
+    /// The run loop stops once this returns false (eg: the window was closed)
+    fn is_open(&self) -> bool {
+        true
+    }
+}
+
+/// Something that can play the buzzer
+pub trait AudioSink {
+    /// Start or stop the buzzer. Called once per frame.
+    fn set_playing(&mut self, playing: bool);
+
+    /// Called once the run loop exits
+    fn stop(&mut self) {}
+
+    /// From 0.0 (silent) to 1.0 (full volume)
+    fn set_volume(&mut self, _volume: f32) {}
+}
+
+/// Emulator controls that aren't on the hex keypad
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+    /// Run `FAST_FORWARD_FRAMES` frames per frame shown
+    FastForward,
+    /// Run a frame every `SLOW_MOTION_FRAMES` frames shown
+    SlowMotion,
+}
+
+/// Emulator controls triggered once per key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Stop or resume running frames
+    Pause,
+    /// Run a single frame, pausing first if running
+    StepFrame,
+    /// Go back to the state the chip was in when `run` was called,
+    /// which is a freshly loaded ROM for the CLI
+    Reset,
+    /// Move up or down `SPEEDS`
+    SpeedUp,
+    SpeedDown,
+    /// Flip a quirk while running
+    ToggleQuirk(Quirk),
+    VolumeUp,
+    VolumeDown,
+    /// Silence the buzzer, or bring it back
+    Mute,
+    /// See `Renderer::screenshot`
+    Screenshot,
+    /// See `Renderer::toggle_overlay`
+    ToggleOverlay,
+    /// See `Renderer::toggle_keypad`
+    ToggleKeypad,
+    /// See `Renderer::toggle_fullscreen`
+    ToggleFullscreen,
+}
+
+/// What function key `n` does in the bundled frontends: F1 to F5 flip the
+/// quirks (in `Quirk::ALL` order), F6 pauses, F7 steps a frame, F8 resets,
+/// F9 and F10 slow down and speed up, F11 switches to fullscreen and back, and
+/// F12 saves a screenshot
+pub fn function_key_action(n: u8) -> Option<Action> {
+    match n {
+        1..=5 => Some(Action::ToggleQuirk(Quirk::ALL[n as usize - 1])),
+        6 => Some(Action::Pause),
+        7 => Some(Action::StepFrame),
+        8 => Some(Action::Reset),
+        9 => Some(Action::SpeedDown),
+        10 => Some(Action::SpeedUp),
+        11 => Some(Action::ToggleFullscreen),
+        12 => Some(Action::Screenshot),
+        _ => None,
+    }
+}
+
+/// Something that knows which keys of the hex keypad are held down
+pub trait InputSource {
+    /// The keys currently being pressed. Bit N set => key N (0x0 to 0xF) held.
+    fn held_keys(&mut self) -> u16;
+
+    /// The run loop stops once this returns true (eg: Escape was pressed)
+    fn quit_requested(&mut self) -> bool {
+        false
+    }
+
+    /// Is the key for this hotkey held down ?
+    fn hotkey_held(&mut self, _hotkey: Hotkey) -> bool {
+        false
+    }
+
+    /// The next action requested since the last call, if any.
+    /// Called until it returns `None`, once per frame.
+    fn next_action(&mut self) -> Option<Action> {
+        None
+    }
+}
+
+/// Run the emulator until the renderer closes or the input asks to quit.
+/// Each frame runs `instructions_per_frame` instructions and ticks the timers
+/// once, and the renderer sets the pace. Actions from the input are applied
+/// as they come, and confirmed with a message.
+pub fn run<C, G, R, A, I>(
+    chip: &mut Chip<C, G>,
+    instructions_per_frame: u32,
+    renderer: &mut R,
+    audio: &mut A,
+    input: &mut I,
+) where
+    C: Clock,
+    G: RandomSource,
+    R: Renderer,
+    A: AudioSink,
+    I: InputSource,
+{
+    // Frames shown since the last one run, for slow motion
+    let mut skipped = 0;
+    let mut controls = Controls::new(chip, instructions_per_frame);
+
+    while renderer.is_open() && !input.quit_requested() {
+        let mut frames = frames_to_run(input, &mut skipped);
+
+        let mut display_update = false;
+        while let Some(action) = input.next_action() {
+            display_update |= action == Action::Reset;
+            let message = controls.apply(action, chip, renderer, audio);
+            renderer.message(&message);
+        }
+        if controls.paused {
+            frames = std::mem::take(&mut controls.step) as u32;
+        }
+
+        chip.keypad_mut().set_held(input.held_keys());
+        for _ in 0..frames {
+            display_update |= chip.run_frame(controls.instructions_per_frame()).0;
+        }
+        audio.set_playing(!controls.paused && chip.is_playing_sound());
+
+        renderer.status(&Status {
+            frames_run: frames,
+            instructions_per_frame: controls.instructions_per_frame(),
+            speed: SPEEDS[controls.speed],
+            paused: controls.paused,
+            behavior: chip.behavior(),
+            held_keys: chip.keypad().held(),
+            waiting_for_key: chip.waiting_for_key(),
+        });
+        if display_update {
+            renderer.draw(&chip.display());
+        } else {
+            renderer.update();
+        }
+    }
+
+    audio.stop();
+}
+
+/// What the actions of `run` change
+struct Controls {
+    /// The chip as `run` got it, for `Action::Reset`
+    initial: Box<[u8; STATE_SIZE]>,
+    /// The quirks as `run` got them, as save states don't hold them
+    behavior: BehaviorConfig,
+    instructions_per_frame: u32,
+    /// Index in `SPEEDS`
+    speed: usize,
+    paused: bool,
+    /// Run one frame while paused
+    step: bool,
+    volume: f32,
+    muted: bool,
+}
+
+impl Controls {
+    fn new<C: Clock, G: RandomSource>(chip: &Chip<C, G>, instructions_per_frame: u32) -> Self {
+        let mut initial = Box::new([0; STATE_SIZE]);
+        chip.save_state(&mut initial);
+        Controls {
+            initial,
+            behavior: chip.behavior(),
+            instructions_per_frame,
+            speed: SPEEDS.iter().position(|speed| *speed == 100).unwrap_or(0),
+            paused: false,
+            step: false,
+            volume: 1.0,
+            muted: false,
+        }
+    }
+
+    /// At the current speed, and at least one
+    fn instructions_per_frame(&self) -> u32 {
+        let scaled = u64::from(self.instructions_per_frame) * u64::from(SPEEDS[self.speed]) / 100;
+        u32::try_from(scaled).unwrap_or(u32::MAX).max(1)
+    }
+
+    /// Apply an action, and say what it did
+    fn apply<C, G, R, A>(
+        &mut self,
+        action: Action,
+        chip: &mut Chip<C, G>,
+        renderer: &mut R,
+        audio: &mut A,
+    ) -> String
+    where
+        C: Clock,
+        G: RandomSource,
+        R: Renderer,
+        A: AudioSink,
+    {
+        match action {
+            Action::Pause => {
+                self.paused = !self.paused;
+                match self.paused {
+                    true => "Paused".to_owned(),
+                    false => "Resumed".to_owned(),
+                }
+            }
+            Action::StepFrame if !self.paused => {
+                self.paused = true;
+                "Paused".to_owned()
+            }
+            Action::StepFrame => {
+                self.step = true;
+                "Next frame".to_owned()
+            }
+            Action::Reset => {
+                chip.load_state(&self.initial[..])
+                    .expect("The state saved by `run` should load");
+                *chip.behavior_mut() = self.behavior;
+                // What was recorded before belongs to the run that was reset
+                if chip.quirk_usage().is_some() {
+                    chip.detect_quirks();
+                }
+                "Reset".to_owned()
+            }
+            Action::SpeedUp | Action::SpeedDown => {
+                self.speed = match action {
+                    Action::SpeedUp => (self.speed + 1).min(SPEEDS.len() - 1),
+                    _ => self.speed.saturating_sub(1),
+                };
+                format!(
+                    "Speed {}% ({} instructions per frame)",
+                    SPEEDS[self.speed],
+                    self.instructions_per_frame()
+                )
+            }
+            Action::ToggleQuirk(quirk) => {
+                let flag = quirk.flag(chip.behavior_mut());
+                *flag = !*flag;
+                format!("{} {}", quirk.field(), if *flag { "on" } else { "off" })
+            }
+            Action::VolumeUp | Action::VolumeDown => {
+                let step = match action {
+                    Action::VolumeUp => VOLUME_STEP,
+                    _ => -VOLUME_STEP,
+                };
+                // Rounded, so steps land back on whole percents
+                self.volume = ((self.volume + step).clamp(0.0, 1.0) * 100.0).round() / 100.0;
+                self.muted = false;
+                audio.set_volume(self.volume);
+                format!("Volume {}%", (self.volume * 100.0) as u32)
+            }
+            Action::Mute => {
+                self.muted = !self.muted;
+                match self.muted {
+                    true => {
+                        audio.set_volume(0.0);
+                        "Muted".to_owned()
+                    }
+                    false => {
+                        audio.set_volume(self.volume);
+                        format!("Volume {}%", (self.volume * 100.0) as u32)
+                    }
+                }
+            }
+            Action::Screenshot => match renderer.screenshot() {
+                Some(Ok(path)) => format!("Saved {}", path.display()),
+                Some(Err(err)) => format!("Can't save a screenshot: {}", err),
+                None => "Screenshots aren't supported here".to_owned(),
+            },
+            Action::ToggleOverlay => match renderer.toggle_overlay() {
+                Some(true) => "Overlay on".to_owned(),
+                Some(false) => "Overlay off".to_owned(),
+                None => "No overlay here".to_owned(),
+            },
+            Action::ToggleKeypad => match renderer.toggle_keypad() {
+                Some(true) => "Keypad on".to_owned(),
+                Some(false) => "Keypad off".to_owned(),
+                None => "No keypad here".to_owned(),
+            },
+            Action::ToggleFullscreen => match renderer.toggle_fullscreen() {
+                Some(true) => "Fullscreen".to_owned(),
+                Some(false) => "Windowed".to_owned(),
+                None => "No fullscreen here".to_owned(),
+            },
+        }
+    }
+}
+
+/// How many frames to run before showing the next one, given the speed hotkeys
+fn frames_to_run(input: &mut impl InputSource, skipped: &mut u32) -> u32 {
+    if input.hotkey_held(Hotkey::FastForward) {
+        FAST_FORWARD_FRAMES
+    } else if input.hotkey_held(Hotkey::SlowMotion) {
+        *skipped = (*skipped + 1) % SLOW_MOTION_FRAMES;
+        (*skipped == 0) as u32
+    } else {
+        1
+    }
+}
+
+/// `run`, but for two chips in lockstep shown side by side.
+/// The buzzer follows the left chip.
+pub fn run_compare<C, G, R, A, I>(
+    lockstep: &mut Lockstep<C, G>,
+    instructions_per_frame: u32,
+    renderer: &mut R,
+    audio: &mut A,
+    input: &mut I,
+) where
+    C: Clock,
+    G: RandomSource,
+    R: PairRenderer,
+    A: AudioSink,
+    I: InputSource,
+{
+    let mut skipped = 0;
+
+    while renderer.is_open() && !input.quit_requested() {
+        let frames = frames_to_run(input, &mut skipped);
+
+        // Actions only apply to `run`
+        while input.next_action().is_some() {}
+
+        let held_keys = input.held_keys();
+        for _ in 0..frames {
+            let diverged = lockstep.divergence().is_some();
+            lockstep.run_frame(instructions_per_frame, held_keys);
+
+            if let (false, Some(divergence)) = (diverged, lockstep.divergence()) {
+                renderer.diverged(&divergence);
+            }
+        }
+        audio.set_playing(lockstep.left().is_playing_sound());
+
+        renderer.draw_pair(&lockstep.left().display(), &lockstep.right().display());
+    }
+
+    audio.stop();
+}
+
+/// A renderer that doesn't show anything (and never closes)
+#[derive(Debug, Default)]
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn draw(&mut self, _display: &Display) {}
+}
+
+impl PairRenderer for NullRenderer {
+    fn draw_pair(&mut self, _left: &Display, _right: &Display) {}
+}
+
+/// An audio sink that stays silent
+#[derive(Debug, Default)]
+pub struct NullAudio;
+
+impl AudioSink for NullAudio {
+    fn set_playing(&mut self, _playing: bool) {}
+}
+
+/// An input source with no keys pressed (and never quits)
+#[derive(Debug, Default)]
+pub struct NullInput;
+
+impl InputSource for NullInput {
+    fn held_keys(&mut self) -> u16 {
+        0
+    }
+}
@@ -0,0 +1,183 @@
+//! Buzzer backend using the `cpal` crate
+//! @AryaveerSR <me.aryaveer@gmail.com>
+
+use crate::frontend::AudioSink;
+use cpal::{
+    self,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    BuildStreamError, FromSample, Sample, SizedSample, Stream,
+};
+use std::{
+    f32::consts,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    thread,
+};
+
+/// Messages to send to the beeper thread
+enum BeeperMessage {
+    Play,
+    Pause,
+    Stop,
+}
+
+/// A simple Beeper implementation using `cpal` crate
+#[derive(Debug)]
+pub struct Beeper {
+    tx: Sender<BeeperMessage>,
+    is_on: bool,
+    /// Bits of the `f32` volume, read by the stream
+    volume: Arc<AtomicU32>,
+}
+
+impl Beeper {
+    pub fn new() -> Self {
+        let host = cpal::default_host();
+        let device = host.default_output_device().unwrap();
+        let config = device.default_output_config().unwrap();
+
+        let (tx, rx) = channel::<BeeperMessage>();
+        let volume = Arc::new(AtomicU32::new(1f32.to_bits()));
+        let stream_volume = volume.clone();
+
+        // A thread dedicated to making noise
+        thread::spawn(move || {
+            let volume = stream_volume;
+            let stream = match config.sample_format() {
+                cpal::SampleFormat::F32 => {
+                    Self::create_stream::<f32>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::I16 => {
+                    Self::create_stream::<i16>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::U16 => {
+                    Self::create_stream::<u16>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::I8 => {
+                    Self::create_stream::<i8>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::I32 => {
+                    Self::create_stream::<i32>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::I64 => {
+                    Self::create_stream::<i64>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::U8 => {
+                    Self::create_stream::<u8>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::U64 => {
+                    Self::create_stream::<u64>(&device, &config.into(), volume)
+                }
+                cpal::SampleFormat::F64 => {
+                    Self::create_stream::<f64>(&device, &config.into(), volume)
+                }
+                _ => panic!(),
+            };
+
+            match stream {
+                Ok(stream) => {
+                    stream.pause().unwrap();
+                    loop {
+                        match rx.recv() {
+                            Ok(BeeperMessage::Play) => stream.play().unwrap(),
+                            Ok(BeeperMessage::Pause) => stream.pause().unwrap(),
+                            Ok(BeeperMessage::Stop) => {
+                                stream.pause().unwrap();
+                                return;
+                            }
+                            Err(err) => panic!("{}", err),
+                        }
+                    }
+                }
+                Err(err) => panic!("{}", err),
+            }
+        });
+
+        Beeper {
+            tx,
+            is_on: false,
+            volume,
+        }
+    }
+
+    pub fn update(&mut self, state: bool) {
+        if state != self.is_on {
+            if state {
+                self.tx.send(BeeperMessage::Play).unwrap();
+            } else {
+                self.tx.send(BeeperMessage::Pause).unwrap();
+            }
+            self.is_on = state;
+        }
+    }
+
+    pub fn stop(&self) {
+        self.tx.send(BeeperMessage::Stop).unwrap();
+    }
+
+    fn create_stream<T>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        volume: Arc<AtomicU32>,
+    ) -> Result<Stream, BuildStreamError>
+    where
+        T: SizedSample + FromSample<f32>,
+    {
+        let sample_rate = config.sample_rate.0 as f32;
+        let channels = config.channels as usize;
+
+        let mut sample_clock = 0f32;
+        let mut next_value = move || {
+            sample_clock = (sample_clock + 1.0) % sample_rate;
+            let volume = f32::from_bits(volume.load(Ordering::Relaxed));
+            (sample_clock * 440.0 * 2.0 * consts::PI / sample_rate).sin() * volume
+        };
+
+        let err_fn = |err| panic!("{}", err);
+
+        device.build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                Self::write_data(data, channels, &mut next_value)
+            },
+            err_fn,
+            None,
+        )
+    }
+
+    fn write_data<T>(output: &mut [T], channels: usize, next_sample: &mut dyn FnMut() -> f32)
+    where
+        T: Sample + FromSample<f32>,
+    {
+        for frame in output.chunks_mut(channels) {
+            let value: T = T::from_sample(next_sample());
+            for sample in frame.iter_mut() {
+                *sample = value;
+            }
+        }
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioSink for Beeper {
+    fn set_playing(&mut self, playing: bool) {
+        self.update(playing);
+    }
+
+    fn stop(&mut self) {
+        Beeper::stop(self);
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volume
+            .store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+}
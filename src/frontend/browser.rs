@@ -0,0 +1,272 @@
+//! ROM browser
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! A list of the ROMs in a directory, recently played ones first, drawn with
+//! the bitmap font so any framebuffer frontend can show it (see
+//! `window::browse`).
+
+use crate::{
+    frontend::text::{Canvas, LINE_HEIGHT},
+    palette::Palette,
+    rom,
+};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Most ROMs remembered as recently played
+pub const RECENT_LIMIT: usize = 10;
+
+/// Size of a font pixel, in screen pixels
+const TEXT_SCALE: usize = 2;
+
+/// Space around the list, in screen pixels
+const MARGIN: usize = 8;
+
+/// Longest name shown, in characters
+const NAME_WIDTH: usize = 48;
+
+/// A ROM file found by `scan`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomFile {
+    pub path: PathBuf,
+    /// Path relative to the directory it was found in
+    pub name: String,
+    pub size: u64,
+    /// Lowercase hex SHA-1, as used by the ROM database and config file
+    pub sha1: String,
+}
+
+impl RomFile {
+    pub fn read(path: impl AsRef<Path>, name: String) -> io::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        Ok(RomFile {
+            path: path.to_owned(),
+            name,
+            size: bytes.len() as u64,
+            sha1: rom::sha1(&bytes),
+        })
+    }
+}
+
+/// Every file with a ROM extension (see `rom::EXTENSIONS`) in a directory
+/// and its subdirectories, sorted by name. A directory that doesn't exist
+/// has none, and files or subdirectories that can't be read are left out.
+pub fn scan(dir: impl AsRef<Path>) -> io::Result<Vec<RomFile>> {
+    let dir = dir.as_ref();
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_owned()];
+    while let Some(current) = pending.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(err) if current == dir && err.kind() != io::ErrorKind::NotFound => return Err(err),
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if rom::platform_for(&path).is_some() {
+                let name = path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string();
+                if let Ok(file) = RomFile::read(&path, name) {
+                    files.push(file);
+                }
+            }
+        }
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+/// Recently played ROMs, most recent first, one path per line in a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recent {
+    pub roms: Vec<PathBuf>,
+}
+
+impl Recent {
+    /// An empty list if the file doesn't exist yet
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Recent {
+                roms: text
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(PathBuf::from)
+                    .take(RECENT_LIMIT)
+                    .collect(),
+            }),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Recent::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let lines: Vec<_> = self
+            .roms
+            .iter()
+            .map(|rom| rom.display().to_string())
+            .collect();
+        fs::write(path, lines.join("\n") + "\n")
+    }
+
+    /// Move a ROM to the front, forgetting the oldest past `RECENT_LIMIT`
+    pub fn push(&mut self, rom: impl AsRef<Path>) {
+        let rom = absolute(rom.as_ref());
+        self.roms.retain(|known| *known != rom);
+        self.roms.insert(0, rom);
+        self.roms.truncate(RECENT_LIMIT);
+    }
+
+    /// Where the list lives, if there's a home to put it in
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_STATE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ if cfg!(windows) => PathBuf::from(env::var_os("LOCALAPPDATA")?),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+        };
+        Some(dir.join("chip8").join("recent"))
+    }
+}
+
+/// The path itself if it can't be resolved (eg: it no longer exists)
+fn absolute(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+/// A list of ROMs to pick from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Browser {
+    /// Shown in the header
+    title: String,
+    files: Vec<RomFile>,
+    /// How many of `files` (at the start) are recently played
+    recent: usize,
+    selected: usize,
+}
+
+impl Browser {
+    /// Recently played ROMs that still exist go first (even if they are
+    /// elsewhere), then the others
+    pub fn new(title: String, mut files: Vec<RomFile>, recent: &Recent) -> Self {
+        let mut first = Vec::new();
+        for path in &recent.roms {
+            match files.iter().position(|file| absolute(&file.path) == *path) {
+                Some(index) => first.push(files.remove(index)),
+                None if path.is_file() => {
+                    let name = path.display().to_string();
+                    if let Ok(file) = RomFile::read(path, name) {
+                        first.push(file);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        let recent = first.len();
+        first.append(&mut files);
+        Browser {
+            title,
+            files: first,
+            recent,
+            selected: 0,
+        }
+    }
+
+    pub fn files(&self) -> &[RomFile] {
+        &self.files
+    }
+
+    pub fn is_recent(&self, index: usize) -> bool {
+        index < self.recent
+    }
+
+    pub fn selected(&self) -> Option<&RomFile> {
+        self.files.get(self.selected)
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Move the selection by `offset` rows, stopping at either end
+    pub fn move_by(&mut self, offset: isize) {
+        let last = self.files.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(offset).min(last);
+    }
+
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    pub fn select_last(&mut self) {
+        self.selected = self.files.len().saturating_sub(1);
+    }
+
+    /// Rows of the list that fit on a canvas
+    pub fn visible_rows(height: usize) -> usize {
+        // The title and the help line take one row each, plus a blank one after the title
+        (height.saturating_sub(MARGIN * 2) / (LINE_HEIGHT * TEXT_SCALE)).saturating_sub(3)
+    }
+
+    /// Draw the list, scrolled to show the selection
+    pub fn render(&self, canvas: &mut Canvas, palette: &Palette) {
+        let (background, foreground) = (palette.background(), palette.foreground());
+        let line = LINE_HEIGHT * TEXT_SCALE;
+        canvas.buffer.fill(background);
+
+        canvas.draw_text(MARGIN, MARGIN, TEXT_SCALE, &self.title, foreground);
+
+        let rows = Self::visible_rows(canvas.height()).max(1);
+        let first = self.selected.saturating_sub(rows - 1);
+        if self.files.is_empty() {
+            let y = MARGIN + line * 2;
+            canvas.draw_text(MARGIN, y, TEXT_SCALE, "No ROMs found", foreground);
+        }
+        for (row, index) in (first..self.files.len()).take(rows).enumerate() {
+            let file = &self.files[index];
+            let y = MARGIN + line * (row + 2);
+            let marker = if self.is_recent(index) { '*' } else { ' ' };
+            let name = match file.name.chars().count() > NAME_WIDTH {
+                true => {
+                    let tail: String = file.name.chars().rev().take(NAME_WIDTH - 3).collect();
+                    format!("...{}", tail.chars().rev().collect::<String>())
+                }
+                false => file.name.clone(),
+            };
+            let text = format!(
+                "{} {:<width$} {:>5} B  {}",
+                marker,
+                name,
+                file.size,
+                &file.sha1[..8],
+                width = NAME_WIDTH
+            );
+
+            // The selected row is drawn inverted
+            let color = match index == self.selected {
+                true => {
+                    let height = line - TEXT_SCALE;
+                    canvas.fill_rect(0, y - TEXT_SCALE, canvas.width, height, foreground);
+                    background
+                }
+                false => foreground,
+            };
+            canvas.draw_text(MARGIN, y, TEXT_SCALE, &text, color);
+        }
+
+        let help = "Up/Down: select   Enter: play   Esc: quit   * recently played";
+        let y = canvas.height().saturating_sub(MARGIN + line - TEXT_SCALE);
+        canvas.draw_text(MARGIN, y, TEXT_SCALE, help, foreground);
+    }
+}
@@ -0,0 +1,308 @@
+//! Keyboard mappings
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Which host keys press which keys of the hex keypad. A host key can press
+//! several hex keys and several host keys can press the same hex key.
+//!
+//! Keymap files have one `KEY = HEX` binding per line, optionally starting
+//! from a preset:
+//!
+//! ```text
+//! # Pong only uses 1 and 4 (left paddle) and C and D (right paddle)
+//! preset = azerty
+//! up = 1
+//! down = 4
+//! ```
+
+use std::{error::Error, fmt, fs, path::Path};
+
+/// A key on the host keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostKey {
+    /// A letter, digit or punctuation key, lowercase (eg: `'q'`, `'7'`, `';'`)
+    Char(char),
+    /// A digit (0 to 9) on the numeric keypad
+    Numpad(u8),
+    NumpadDivide,
+    NumpadMultiply,
+    NumpadSubtract,
+    NumpadAdd,
+    NumpadEnter,
+    NumpadDecimal,
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Enter,
+    Backspace,
+}
+
+/// Names of the keys that aren't a single character
+const NAMED_KEYS: [(&str, HostKey); 13] = [
+    ("numpad/", HostKey::NumpadDivide),
+    ("numpad*", HostKey::NumpadMultiply),
+    ("numpad-", HostKey::NumpadSubtract),
+    ("numpad+", HostKey::NumpadAdd),
+    ("numpadenter", HostKey::NumpadEnter),
+    ("numpad.", HostKey::NumpadDecimal),
+    ("up", HostKey::Up),
+    ("down", HostKey::Down),
+    ("left", HostKey::Left),
+    ("right", HostKey::Right),
+    ("space", HostKey::Space),
+    ("enter", HostKey::Enter),
+    ("backspace", HostKey::Backspace),
+];
+
+impl HostKey {
+    /// `q`, `7`, `;`, `numpad7`, `numpad+`, `up`, `space`... (ignoring case)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return (!c.is_whitespace()).then_some(HostKey::Char(c));
+        }
+
+        if let Some(digit) = name.strip_prefix("numpad").and_then(|d| d.parse().ok()) {
+            return (digit <= 9).then_some(HostKey::Numpad(digit));
+        }
+
+        NAMED_KEYS
+            .iter()
+            .find(|(key_name, _)| *key_name == name)
+            .map(|(_, key)| *key)
+    }
+}
+
+impl fmt::Display for HostKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostKey::Char(c) => write!(f, "{}", c),
+            HostKey::Numpad(digit) => write!(f, "numpad{}", digit),
+            key => {
+                let (name, _) = NAMED_KEYS.iter().find(|(_, k)| k == key).unwrap();
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+/// Rows of the hex keypad, as laid out on the original hardware
+const KEYPAD_ROWS: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// The 4x4 block of keys on the left of each keyboard layout,
+/// mapped position by position onto `KEYPAD_ROWS`
+const BLOCK_PRESETS: [(&str, [&str; 4]); 4] = [
+    ("qwerty", ["1234", "qwer", "asdf", "zxcv"]),
+    ("azerty", ["1234", "azer", "qsdf", "wxcv"]),
+    ("qwertz", ["1234", "qwer", "asdf", "yxcv"]),
+    ("dvorak", ["1234", "',.p", "aoeu", ";qjk"]),
+];
+
+/// Bindings from host keys to the hex keypad
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    bindings: Vec<(HostKey, u8)>,
+}
+
+impl Keymap {
+    /// Names of the presets, for `Keymap::preset` and help messages
+    pub const PRESETS: [&'static str; 5] = ["qwerty", "azerty", "qwertz", "dvorak", "numpad"];
+
+    /// A keymap without any binding
+    pub fn empty() -> Self {
+        Keymap { bindings: vec![] }
+    }
+
+    /// The 4x4 block under 1234 (in the given layout), or the digits of the
+    /// numeric keypad (with / * - + Enter . as A to F)
+    pub fn preset(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        let mut keymap = Keymap::empty();
+
+        if name == "numpad" {
+            for digit in 0..10 {
+                keymap.bind(HostKey::Numpad(digit), digit);
+            }
+            let extra = [
+                HostKey::NumpadDivide,
+                HostKey::NumpadMultiply,
+                HostKey::NumpadSubtract,
+                HostKey::NumpadAdd,
+                HostKey::NumpadEnter,
+                HostKey::NumpadDecimal,
+            ];
+            for (key, hex) in extra.into_iter().zip(0xA..) {
+                keymap.bind(key, hex);
+            }
+            return Some(keymap);
+        }
+
+        let (_, rows) = BLOCK_PRESETS.iter().find(|(preset, _)| *preset == name)?;
+        for (row, hex_row) in rows.iter().zip(KEYPAD_ROWS) {
+            for (c, hex) in row.chars().zip(hex_row) {
+                keymap.bind(HostKey::Char(c), hex);
+            }
+        }
+        Some(keymap)
+    }
+
+    /// Add a binding. Bindings already on the key or hex key are kept.
+    pub fn bind(&mut self, key: HostKey, hex: u8) {
+        if !self.bindings.contains(&(key, hex)) {
+            self.bindings.push((key, hex));
+        }
+    }
+
+    /// Remove all bindings of a host key
+    pub fn unbind(&mut self, key: HostKey) {
+        self.bindings.retain(|(bound, _)| *bound != key);
+    }
+
+    pub fn bindings(&self) -> &[(HostKey, u8)] {
+        &self.bindings
+    }
+
+    /// The hex keys pressed by a host key
+    pub fn keypad_keys(&self, key: HostKey) -> impl Iterator<Item = u8> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(bound, _)| *bound == key)
+            .map(|(_, hex)| *hex)
+    }
+
+    /// The hex keys pressed by a set of held host keys (bit N set => key N held)
+    pub fn held(&self, keys: impl IntoIterator<Item = HostKey>) -> u16 {
+        keys.into_iter()
+            .flat_map(|key| self.keypad_keys(key))
+            .fold(0, |held, hex| held | 1 << hex)
+    }
+
+    /// Read a keymap file, starting from the QWERTY preset
+    /// unless it has a `preset = NAME` line.
+    pub fn parse(text: &str) -> Result<Self, ParseKeymapError> {
+        let mut keymap = Keymap::default();
+        keymap.apply(text)?;
+        Ok(keymap)
+    }
+
+    /// `Keymap::parse` on a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ParseKeymapError> {
+        let text = fs::read_to_string(path.as_ref()).map_err(|err| ParseKeymapError {
+            line: 0,
+            reason: format!("can't read {}: {}", path.as_ref().display(), err),
+        })?;
+        Self::parse(&text)
+    }
+
+    /// Apply the lines of a keymap file on top of this one (eg: per-ROM overrides).
+    /// A `KEY = HEX` line replaces what that key was bound to, and a
+    /// `preset = NAME` line starts over from the preset.
+    pub fn apply(&mut self, text: &str) -> Result<(), ParseKeymapError> {
+        let mut overridden = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |reason: String| ParseKeymapError {
+                line: index + 1,
+                reason,
+            };
+
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // The last `=`, so that `=` can itself be bound
+            let (key, value) = line
+                .rsplit_once('=')
+                .ok_or_else(|| error("expected KEY = HEX".to_owned()))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            if key.eq_ignore_ascii_case("preset") {
+                *self = Keymap::preset(value)
+                    .ok_or_else(|| error(format!("unknown preset `{}`", value)))?;
+                overridden.clear();
+                continue;
+            }
+
+            let key =
+                HostKey::from_name(key).ok_or_else(|| error(format!("unknown key `{}`", key)))?;
+            let hex = match u8::from_str_radix(value.trim_start_matches("0x"), 16) {
+                Ok(hex) if hex <= 0xF => hex,
+                _ => return Err(error(format!("`{}` isn't a hex key (0 to F)", value))),
+            };
+
+            // The first time a key shows up, forget what it was bound to before
+            if !overridden.contains(&key) {
+                self.unbind(key);
+                overridden.push(key);
+            }
+            self.bind(key, hex);
+        }
+
+        Ok(())
+    }
+
+    /// Preset names are tried first, then paths to keymap files
+    pub fn from_preset_or_file(name: &str) -> Result<Self, ParseKeymapError> {
+        match Keymap::preset(name) {
+            Some(keymap) => Ok(keymap),
+            None => Keymap::load(name),
+        }
+    }
+
+    /// Per-ROM overrides live next to the ROM, with a `.keymap` extension
+    /// (eg: `pong.keymap` for `pong.ch8`)
+    pub fn apply_rom_overrides(&mut self, rom: impl AsRef<Path>) -> Result<(), ParseKeymapError> {
+        let path = rom.as_ref().with_extension("keymap");
+        match fs::read_to_string(&path) {
+            Ok(text) => self.apply(&text).map_err(|mut err| {
+                err.reason = format!("{}: {}", path.display(), err.reason);
+                err
+            }),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// QWERTY (see `KEYPAD_ROWS` for the layout):
+///
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// Q W E R  =>  4 5 6 D
+/// A S D F      7 8 9 E
+/// Z X C V      A 0 B F
+/// ```
+///
+/// https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#keypad
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset("qwerty").unwrap()
+    }
+}
+
+/// A keymap that couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeymapError {
+    /// Line of the error, starting at 1 (0 if it isn't about a line)
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseKeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "{}", self.reason),
+            line => write!(f, "line {}: {}", line, self.reason),
+        }
+    }
+}
+
+impl Error for ParseKeymapError {}
@@ -0,0 +1,227 @@
+//! Status overlay
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Frame rate, speed and quirks of the running emulator, plus short messages,
+//! drawn over the display with the bitmap font. Renderers feed it the
+//! `Status` of every frame, and draw it on top of the upscaled display.
+
+use crate::{
+    frontend::{
+        text::{self, Canvas, LINE_HEIGHT},
+        Status, MESSAGE_FRAMES,
+    },
+    palette::Palette,
+    platform::TIMER_PERIOD_MICROS,
+    quirks::Quirk,
+    structs::BehaviorConfig,
+};
+use std::time::{Duration, Instant};
+
+/// How often the statistics are refreshed
+pub const MEASURE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Space around each box of text, in font pixels
+const PADDING: usize = 2;
+
+/// Rates measured over a `MEASURE_PERIOD`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    /// Frames shown per second
+    pub fps: f32,
+    pub instructions_per_second: u64,
+    /// Frames run per second, in percent of the CHIP-8's 60Hz
+    pub speed: u32,
+}
+
+impl Stats {
+    pub fn measure(shown: u32, run: u32, instructions: u64, elapsed: Duration) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        if seconds <= 0.0 {
+            return Stats::default();
+        }
+        let frame = Duration::from_micros(TIMER_PERIOD_MICROS).as_secs_f64();
+        Stats {
+            fps: (shown as f64 / seconds) as f32,
+            instructions_per_second: (instructions as f64 / seconds).round() as u64,
+            speed: (run as f64 * frame / seconds * 100.0).round() as u32,
+        }
+    }
+}
+
+/// A name for a set of quirks: its platform, or the quirks that are on
+pub fn profile_name(behavior: &BehaviorConfig) -> String {
+    if let Some(platform) = behavior.platform() {
+        return platform.to_owned();
+    }
+    let mut behavior = *behavior;
+    let on: Vec<_> = Quirk::ALL
+        .into_iter()
+        .filter(|quirk| *quirk.flag(&mut behavior))
+        .map(|quirk| quirk.field())
+        .collect();
+    match on.is_empty() {
+        true => "custom: no quirks".to_owned(),
+        false => format!("custom: {}", on.join(", ")),
+    }
+}
+
+/// What's shown over the display
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    /// Whether the statistics are shown (messages and pausing always are)
+    visible: bool,
+    /// Text, and for how many more frames
+    message: Option<(String, u32)>,
+    status: Option<Status>,
+    stats: Stats,
+    /// Something disappeared, so the display needs drawing again
+    dirty: bool,
+    /// Counted since `since`
+    shown: u32,
+    run: u32,
+    instructions: u64,
+    since: Instant,
+}
+
+impl Overlay {
+    pub fn new(visible: bool) -> Self {
+        Overlay {
+            visible,
+            message: None,
+            status: None,
+            stats: Stats::default(),
+            dirty: false,
+            shown: 0,
+            run: 0,
+            instructions: 0,
+            since: Instant::now(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the statistics, and return whether they are now shown
+    pub fn toggle(&mut self) -> bool {
+        self.visible = !self.visible;
+        self.dirty = true;
+        self.visible
+    }
+
+    /// Show a message for `MESSAGE_FRAMES` frames, replacing the previous one
+    pub fn message(&mut self, text: &str) {
+        self.message = Some((text.to_owned(), MESSAGE_FRAMES));
+    }
+
+    pub fn current_message(&self) -> Option<&str> {
+        self.message.as_ref().map(|(text, _)| text.as_str())
+    }
+
+    /// Record a frame shown. Also counts down the message.
+    pub fn status(&mut self, status: &Status) {
+        if self.status.is_some_and(|last| last.paused != status.paused) {
+            self.dirty = true;
+        }
+        self.status = Some(*status);
+
+        match &mut self.message {
+            Some((_, 0)) => {
+                self.message = None;
+                self.dirty = true;
+            }
+            Some((_, frames)) => *frames -= 1,
+            None => {}
+        }
+
+        self.shown += 1;
+        self.run += status.frames_run;
+        self.instructions += status.frames_run as u64 * status.instructions_per_frame as u64;
+        let elapsed = self.since.elapsed();
+        if elapsed >= MEASURE_PERIOD {
+            self.stats = Stats::measure(self.shown, self.run, self.instructions, elapsed);
+            (self.shown, self.run, self.instructions) = (0, 0, 0);
+            self.since = Instant::now();
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.status.is_some_and(|status| status.paused)
+    }
+
+    /// Whether the overlay changes from frame to frame, so the display has
+    /// to be drawn again even if the CHIP-8 didn't change it
+    pub fn needs_redraw(&mut self) -> bool {
+        let dirty = std::mem::take(&mut self.dirty);
+        dirty || self.visible || self.message.is_some() || self.is_paused()
+    }
+
+    /// The statistics, one line each (empty when hidden)
+    pub fn lines(&self) -> Vec<String> {
+        if !self.visible {
+            return vec![];
+        }
+        let mut lines = vec![format!(
+            "{:.0} FPS  {} IPS  {}%",
+            self.stats.fps, self.stats.instructions_per_second, self.stats.speed
+        )];
+        if let Some(status) = &self.status {
+            lines.push(profile_name(&status.behavior));
+            if status.speed != 100 {
+                lines.push(format!(
+                    "Speed {}% ({} per frame)",
+                    status.speed, status.instructions_per_frame
+                ));
+            }
+        }
+        lines
+    }
+
+    /// Draw the message in the top left corner, the statistics in the top
+    /// right one, and `PAUSED` in the middle, each font pixel `scale` pixels wide
+    pub fn render(&self, canvas: &mut Canvas, palette: &Palette, scale: usize) {
+        let (background, foreground) = (palette.background(), palette.foreground());
+        let padding = PADDING * scale;
+        let line = LINE_HEIGHT * scale;
+
+        if let Some(text) = self.current_message() {
+            draw_box(canvas, 0, 0, text, scale, background, foreground);
+        }
+
+        for (n, text) in self.lines().iter().enumerate() {
+            let width = text::text_width(text, scale) + padding * 2;
+            let x = canvas.width.saturating_sub(width);
+            draw_box(canvas, x, n * line, text, scale, background, foreground);
+        }
+
+        if self.is_paused() {
+            let big = scale * 2;
+            let width = text::text_width("PAUSED", big) + PADDING * big * 2;
+            let height = LINE_HEIGHT * big;
+            let x = canvas.width.saturating_sub(width) / 2;
+            let y = canvas.height().saturating_sub(height) / 2;
+            draw_box(canvas, x, y, "PAUSED", big, background, foreground);
+        }
+    }
+}
+
+/// Text on a filled box, with the box's top left corner at (x, y)
+fn draw_box(
+    canvas: &mut Canvas,
+    x: usize,
+    y: usize,
+    text: &str,
+    scale: usize,
+    background: u32,
+    foreground: u32,
+) {
+    let padding = PADDING * scale;
+    let width = text::text_width(text, scale) + padding * 2;
+    let height = LINE_HEIGHT * scale;
+    canvas.fill_rect(x, y, width, height, background);
+    canvas.draw_text(x + padding, y + scale, scale, text, foreground);
+}
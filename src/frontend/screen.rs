@@ -0,0 +1,121 @@
+//! Monitors
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! `minifb` has no fullscreen mode, and can't tell how big the screen is.
+//! A fullscreen window is a borderless one covering a monitor, so the
+//! display server is asked where the monitors are: X11 (with Xinerama when
+//! there are several, which also covers XWayland) and Windows. Elsewhere
+//! (eg: macOS) there's no answer, so no fullscreen.
+
+/// A monitor's area of the desktop, in screen pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Monitor {
+    pub x: isize,
+    pub y: isize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Monitor {
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        (self.x..self.x + self.width as isize).contains(&x)
+            && (self.y..self.y + self.height as isize).contains(&y)
+    }
+}
+
+/// The monitor a point of the desktop is on (eg: a window's corner), or the
+/// first one if it's on none. `None` if the display server can't tell.
+#[cfg(not(any(target_os = "macos", target_os = "redox", windows)))]
+pub fn monitor_at(x: isize, y: isize) -> Option<Monitor> {
+    let monitors = x11_monitors();
+    monitors
+        .iter()
+        .find(|monitor| monitor.contains(x, y))
+        .or(monitors.first())
+        .copied()
+}
+
+/// Every monitor, or the whole screen when Xinerama can't tell them apart
+#[cfg(not(any(target_os = "macos", target_os = "redox", windows)))]
+fn x11_monitors() -> Vec<Monitor> {
+    use std::{ptr, slice};
+    use x11_dl::{xinerama, xlib};
+
+    let Ok(xlib) = xlib::Xlib::open() else {
+        return vec![];
+    };
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            return vec![];
+        }
+
+        let mut monitors = Vec::new();
+        if let Ok(xinerama) = xinerama::Xlib::open() {
+            if (xinerama.XineramaIsActive)(display) != 0 {
+                let mut count = 0;
+                let screens = (xinerama.XineramaQueryScreens)(display, &mut count);
+                if !screens.is_null() {
+                    let screens = slice::from_raw_parts(screens, count.max(0) as usize);
+                    monitors.extend(screens.iter().map(|screen| Monitor {
+                        x: screen.x_org as isize,
+                        y: screen.y_org as isize,
+                        width: screen.width as u16 as usize,
+                        height: screen.height as u16 as usize,
+                    }));
+                    (xlib.XFree)(screens.as_ptr() as *mut _);
+                }
+            }
+        }
+        if monitors.is_empty() {
+            let screen = (xlib.XDefaultScreen)(display);
+            monitors.push(Monitor {
+                x: 0,
+                y: 0,
+                width: (xlib.XDisplayWidth)(display, screen).max(0) as usize,
+                height: (xlib.XDisplayHeight)(display, screen).max(0) as usize,
+            });
+        }
+
+        (xlib.XCloseDisplay)(display);
+        monitors
+    }
+}
+
+/// The monitor a point of the desktop is on (eg: a window's corner), or the
+/// nearest one. `None` if Windows can't tell.
+#[cfg(windows)]
+pub fn monitor_at(x: isize, y: isize) -> Option<Monitor> {
+    use std::mem;
+    use winapi::{
+        shared::windef::POINT,
+        um::winuser::{GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST},
+    };
+
+    unsafe {
+        let point = POINT {
+            x: x as i32,
+            y: y as i32,
+        };
+        let monitor = MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST);
+        let mut info: MONITORINFO = mem::zeroed();
+        info.cbSize = mem::size_of::<MONITORINFO>() as u32;
+        if monitor.is_null() || GetMonitorInfoW(monitor, &mut info) == 0 {
+            return None;
+        }
+
+        let area = info.rcMonitor;
+        Some(Monitor {
+            x: area.left as isize,
+            y: area.top as isize,
+            width: (area.right - area.left).max(0) as usize,
+            height: (area.bottom - area.top).max(0) as usize,
+        })
+    }
+}
+
+/// No way to ask here
+#[cfg(any(target_os = "macos", target_os = "redox"))]
+pub fn monitor_at(_x: isize, _y: isize) -> Option<Monitor> {
+    None
+}
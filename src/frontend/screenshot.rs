@@ -0,0 +1,97 @@
+//! Screenshots
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Saves a framebuffer as a PNG. The image data is stored without
+//! compression, which keeps this free of dependencies and is plenty for
+//! screenshots of a 64x32 display.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Largest block of a stored (uncompressed) deflate stream
+const STORED_BLOCK: usize = 0xFFFF;
+
+/// Encode 0xRRGGBB pixels (row by row) as an RGB PNG
+pub fn encode_png(pixels: &[u32], width: usize, height: usize) -> Vec<u8> {
+    // Each row starts with its filter type, 0 (none)
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in pixels.chunks(width).take(height) {
+        raw.push(0);
+        for pixel in row {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            raw.extend_from_slice(&[r, g, b]);
+        }
+    }
+
+    // A zlib stream of stored deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let blocks = raw.chunks(STORED_BLOCK).collect::<Vec<_>>();
+    for (n, block) in blocks.iter().enumerate() {
+        let last = n + 1 == blocks.len();
+        let len = block.len() as u16;
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, default compression, filtering and no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Save a PNG in `dir`, named after the current time (eg: `chip8-1700000000123.png`)
+pub fn save(pixels: &[u32], width: usize, height: usize, dir: &Path) -> io::Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    let path = dir.join(format!("chip8-{}.png", millis));
+    fs::write(&path, encode_png(pixels, width, height))?;
+    Ok(path)
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,504 @@
+//! Terminal backend using the `crossterm` crate
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Draws the display with Unicode characters and ANSI colours, so ROMs can
+//! run over SSH or on machines without a display server.
+//! The terminal needs to fit the display and the line under it (see
+//! `TtyStyle::size`), or it isn't drawn.
+
+use crate::{
+    frontend::{
+        self,
+        keymap::{HostKey, Keymap},
+        overlay::Overlay,
+        Action, AudioSink, Hotkey, InputSource, Renderer, Status,
+    },
+    palette::Palette,
+    phosphor::{Persistence, Phosphor},
+    platform::TIMER_PERIOD_MICROS,
+    Display, DISPLAY_HEIGHT, DISPLAY_WIDTH,
+};
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    io::{self, Stdout, Write},
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Most terminals only report presses (repeated while held), never releases.
+/// There, a key counts as held for this long after it was last reported,
+/// which bridges the gap before key repeat kicks in.
+const HOLD_TIME: Duration = Duration::from_millis(250);
+
+/// How pixels are turned into characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TtyStyle {
+    /// `▀` with the top pixel as foreground and the bottom one as background.
+    /// Full colour, 64x16 characters.
+    #[default]
+    HalfBlock,
+    /// Braille patterns, 2x4 pixels per character (32x8).
+    /// Only the background and foreground colours.
+    Braille,
+}
+
+impl TtyStyle {
+    /// Columns and rows taken by the display, plus the line under it
+    pub fn size(self) -> (u16, u16) {
+        let (width, height) = match self {
+            TtyStyle::HalfBlock => (DISPLAY_WIDTH, DISPLAY_HEIGHT / 2),
+            TtyStyle::Braille => (DISPLAY_WIDTH / 2, DISPLAY_HEIGHT / 4),
+        };
+        (width as u16, height as u16 + 1)
+    }
+
+    /// Does a terminal of this size fit everything ?
+    fn fits(self, (columns, rows): (u16, u16)) -> bool {
+        let (width, height) = self.size();
+        columns >= width && rows >= height
+    }
+}
+
+/// Why the display can't be drawn in a terminal of this size
+fn too_small(style: TtyStyle, (columns, rows): (u16, u16)) -> String {
+    let (width, height) = style.size();
+    format!(
+        "The terminal is {}x{}, it needs to be at least {}x{}",
+        columns, rows, width, height
+    )
+}
+
+/// How the terminal looks and behaves
+#[derive(Debug, Clone)]
+pub struct TtyConfig {
+    /// Time between two frames (60Hz by default). `None` doesn't wait at all.
+    pub rate: Option<Duration>,
+    pub palette: Palette,
+    pub persistence: Persistence,
+    pub style: TtyStyle,
+    pub keymap: Keymap,
+    /// Show the statistics under the display from the start
+    pub overlay: bool,
+}
+
+impl Default for TtyConfig {
+    fn default() -> Self {
+        TtyConfig {
+            rate: Some(Duration::from_micros(TIMER_PERIOD_MICROS)),
+            palette: Palette::default(),
+            persistence: Persistence::default(),
+            style: TtyStyle::default(),
+            keymap: Keymap::default(),
+            overlay: false,
+        }
+    }
+}
+
+/// Keyboard state, shared between the renderer (which polls for events) and the input
+#[derive(Debug, Default)]
+struct KeyState {
+    /// When each key was last reported as pressed
+    held: HashMap<KeyCode, Instant>,
+    /// Pressed since the input last looked, oldest first (not repeats)
+    pressed: VecDeque<KeyCode>,
+    /// Does the terminal report key releases ?
+    reports_release: bool,
+    /// Escape or Ctrl+C, or the terminal can't be written to anymore
+    quit: bool,
+}
+
+impl KeyState {
+    fn handle(&mut self, event: KeyEvent) {
+        let is_ctrl_c =
+            event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL);
+        if event.kind != KeyEventKind::Release && (event.code == KeyCode::Esc || is_ctrl_c) {
+            self.quit = true;
+        }
+
+        if event.kind == KeyEventKind::Press {
+            self.pressed.push_back(event.code);
+        }
+        match event.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                self.held.insert(event.code, Instant::now());
+            }
+            KeyEventKind::Release => {
+                self.held.remove(&event.code);
+            }
+        }
+    }
+
+    fn is_held(&self, code: KeyCode) -> bool {
+        match self.held.get(&code) {
+            Some(pressed) => self.reports_release || pressed.elapsed() < HOLD_TIME,
+            None => false,
+        }
+    }
+}
+
+type SharedKeys = Rc<RefCell<KeyState>>;
+
+/// Switch the terminal to raw mode and the alternate screen, and return its two halves.
+/// Everything is restored once the renderer is dropped.
+/// Fails if the terminal is too small for the style.
+pub fn open(config: TtyConfig) -> io::Result<(TtyRenderer, TtyInput)> {
+    let size = terminal::size()?;
+    if !config.style.fits(size) {
+        return Err(io::Error::other(too_small(config.style, size)));
+    }
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        cursor::Hide,
+        terminal::Clear(ClearType::All)
+    )?;
+
+    let reports_release = matches!(terminal::supports_keyboard_enhancement(), Ok(true));
+    if reports_release {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+    }
+
+    let keys = SharedKeys::new(RefCell::new(KeyState {
+        reports_release,
+        ..KeyState::default()
+    }));
+
+    Ok((
+        TtyRenderer {
+            stdout,
+            keys: keys.clone(),
+            rate: config.rate,
+            palette: config.palette,
+            style: config.style,
+            phosphor: Phosphor::new(config.persistence),
+            latest: [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            buffer: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            next_frame: Instant::now(),
+            overlay: Overlay::new(config.overlay),
+            size,
+        },
+        TtyInput {
+            keys,
+            keymap: config.keymap,
+        },
+    ))
+}
+
+/// The drawing half of the terminal
+pub struct TtyRenderer {
+    stdout: Stdout,
+    keys: SharedKeys,
+    rate: Option<Duration>,
+    palette: Palette,
+    style: TtyStyle,
+    phosphor: Phosphor,
+    /// Last display drawn, kept to redraw it after a resize
+    latest: Display,
+    /// 0xAARRGGBB pixels
+    buffer: Vec<u32>,
+    next_frame: Instant,
+    /// Shown on the line under the display
+    overlay: Overlay,
+    /// Of the terminal, in columns and rows
+    size: (u16, u16),
+}
+
+impl TtyRenderer {
+    /// Read all pending events.
+    /// Returns true if the terminal was resized (and cleared), so needs a redraw.
+    fn poll_events(&mut self) -> bool {
+        let mut resized = false;
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            match event::read() {
+                Ok(Event::Key(key)) => self.keys.borrow_mut().handle(key),
+                Ok(Event::Resize(columns, rows)) => {
+                    self.size = (columns, rows);
+                    resized = true;
+                }
+                _ => {}
+            }
+        }
+
+        if resized {
+            let _ = execute!(self.stdout, terminal::Clear(ClearType::All));
+        }
+        resized
+    }
+
+    /// Wait until it is time for the next frame
+    fn wait(&mut self) {
+        let rate = match self.rate {
+            Some(rate) => rate,
+            None => return,
+        };
+
+        self.next_frame += rate;
+        let now = Instant::now();
+        match self.next_frame.checked_duration_since(now) {
+            Some(remaining) => thread::sleep(remaining),
+            // Running behind, don't try to catch up
+            None => self.next_frame = now,
+        }
+    }
+
+    /// Blend in the latest display, and show it. A terminal shrunk below
+    /// the display only gets told so, as the picture would wrap around.
+    fn present(&mut self) -> io::Result<()> {
+        self.phosphor.push(&self.latest);
+        self.palette
+            .render_intensity(self.phosphor.intensity(), &mut self.buffer);
+
+        if !self.style.fits(self.size) {
+            queue!(
+                self.stdout,
+                ResetColor,
+                cursor::MoveTo(0, 0),
+                Print(too_small(self.style, self.size))
+            )?;
+            return self.stdout.flush();
+        }
+
+        match self.style {
+            TtyStyle::HalfBlock => self.draw_half_blocks()?,
+            TtyStyle::Braille => self.draw_braille()?,
+        }
+        queue!(self.stdout, ResetColor)?;
+        self.draw_message()?;
+        self.stdout.flush()
+    }
+
+    /// `present`, ending the session if the terminal went away (eg: the SSH
+    /// connection dropped), as there's nothing left to draw to
+    fn present_or_quit(&mut self) {
+        if self.present().is_err() {
+            self.keys.borrow_mut().quit = true;
+        }
+    }
+
+    /// Print the message on the line under the display, or else the
+    /// statistics of the overlay (blank when there is neither)
+    fn draw_message(&mut self) -> io::Result<()> {
+        let text = match self.overlay.current_message() {
+            Some(text) => text.to_owned(),
+            None if self.overlay.is_paused() => "PAUSED".to_owned(),
+            None => self.overlay.lines().join(" | "),
+        };
+        let (width, height) = self.style.size();
+        let width = width as usize;
+        let text: String = text.chars().take(width).collect();
+
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, height - 1),
+            Print(format!("{:<width$}", text, width = width))
+        )
+    }
+
+    fn draw_half_blocks(&mut self) -> io::Result<()> {
+        let mut colors = None;
+        for row in 0..DISPLAY_HEIGHT / 2 {
+            queue!(self.stdout, cursor::MoveTo(0, row as u16))?;
+            for x in 0..DISPLAY_WIDTH {
+                let top = self.buffer[row * 2 * DISPLAY_WIDTH + x];
+                let bottom = self.buffer[(row * 2 + 1) * DISPLAY_WIDTH + x];
+
+                // Only send colours when they change, it adds up quickly
+                if colors != Some((top, bottom)) {
+                    queue!(
+                        self.stdout,
+                        SetForegroundColor(to_color(top)),
+                        SetBackgroundColor(to_color(bottom))
+                    )?;
+                    colors = Some((top, bottom));
+                }
+                queue!(self.stdout, Print('▀'))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_braille(&mut self) -> io::Result<()> {
+        // Bit for each dot of a braille pattern, indexed by [y][x]
+        const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+        queue!(
+            self.stdout,
+            SetForegroundColor(to_color(self.palette.foreground())),
+            SetBackgroundColor(to_color(self.palette.background()))
+        )?;
+
+        let intensity = self.phosphor.intensity();
+        for row in 0..DISPLAY_HEIGHT / 4 {
+            queue!(self.stdout, cursor::MoveTo(0, row as u16))?;
+            for column in 0..DISPLAY_WIDTH / 2 {
+                let mut pattern = 0;
+                for (dy, bits) in DOTS.iter().enumerate() {
+                    for (dx, bit) in bits.iter().enumerate() {
+                        if intensity[row * 4 + dy][column * 2 + dx] != 0 {
+                            pattern |= bit;
+                        }
+                    }
+                }
+                let c = char::from_u32(0x2800 + pattern).unwrap_or(' ');
+                queue!(self.stdout, Print(c))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Renderer for TtyRenderer {
+    fn draw(&mut self, display: &Display) {
+        self.latest = *display;
+        self.poll_events();
+        self.present_or_quit();
+        self.wait();
+    }
+
+    fn update(&mut self) {
+        // Faded pixels keep fading even if nothing changed, and the overlay changes
+        let fading = self.phosphor.mode() != Persistence::Off || self.overlay.needs_redraw();
+        if self.poll_events() || fading {
+            self.present_or_quit();
+        }
+        self.wait();
+    }
+
+    fn message(&mut self, text: &str) {
+        self.overlay.message(text);
+    }
+
+    fn status(&mut self, status: &Status) {
+        self.overlay.status(status);
+    }
+
+    fn toggle_overlay(&mut self) -> Option<bool> {
+        Some(self.overlay.toggle())
+    }
+}
+
+impl Drop for TtyRenderer {
+    fn drop(&mut self) {
+        if self.keys.borrow().reports_release {
+            let _ = execute!(self.stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.stdout, ResetColor, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// The keyboard half of the terminal
+pub struct TtyInput {
+    keys: SharedKeys,
+    keymap: Keymap,
+}
+
+impl InputSource for TtyInput {
+    /// Terminals can't tell the numeric keypad apart,
+    /// so digits press both the digit and the numpad bindings.
+    fn held_keys(&mut self) -> u16 {
+        let keys = self.keys.borrow();
+        let host_keys = keys
+            .held
+            .keys()
+            .filter(|code| keys.is_held(**code))
+            .flat_map(|code| match code {
+                KeyCode::Char(' ') => [Some(HostKey::Space), None],
+                KeyCode::Char(c) => {
+                    let c = c.to_ascii_lowercase();
+                    let numpad = c.to_digit(10).map(|digit| HostKey::Numpad(digit as u8));
+                    [Some(HostKey::Char(c)), numpad]
+                }
+                KeyCode::Up => [Some(HostKey::Up), None],
+                KeyCode::Down => [Some(HostKey::Down), None],
+                KeyCode::Left => [Some(HostKey::Left), None],
+                KeyCode::Right => [Some(HostKey::Right), None],
+                KeyCode::Enter => [Some(HostKey::Enter), None],
+                KeyCode::Backspace => [Some(HostKey::Backspace), None],
+                _ => [None, None],
+            })
+            .flatten();
+        self.keymap.held(host_keys)
+    }
+
+    /// Escape or Ctrl+C (raw mode swallows the signal)
+    fn quit_requested(&mut self) -> bool {
+        self.keys.borrow().quit
+    }
+
+    /// Tab fast-forwards, ` (backquote) slows down
+    fn hotkey_held(&mut self, hotkey: Hotkey) -> bool {
+        let code = match hotkey {
+            Hotkey::FastForward => KeyCode::Tab,
+            Hotkey::SlowMotion => KeyCode::Char('`'),
+        };
+        self.keys.borrow().is_held(code)
+    }
+
+    /// Function keys (see `frontend::function_key_action`), Pause, Home for
+    /// the overlay, Insert for the keypad (which terminals don't have), and
+    /// Page Up, Page Down and End for the volume
+    fn next_action(&mut self) -> Option<Action> {
+        let mut keys = self.keys.borrow_mut();
+        while let Some(code) = keys.pressed.pop_front() {
+            let action = match code {
+                KeyCode::F(n) => frontend::function_key_action(n),
+                KeyCode::Pause => Some(Action::Pause),
+                KeyCode::Home => Some(Action::ToggleOverlay),
+                KeyCode::Insert => Some(Action::ToggleKeypad),
+                KeyCode::PageUp => Some(Action::VolumeUp),
+                KeyCode::PageDown => Some(Action::VolumeDown),
+                KeyCode::End => Some(Action::Mute),
+                _ => None,
+            };
+            if action.is_some() {
+                return action;
+            }
+        }
+        None
+    }
+}
+
+/// Rings the terminal bell when the buzzer starts.
+/// Terminals can't hold a note, so that's as close as it gets.
+#[derive(Debug, Default)]
+pub struct TtyBell {
+    playing: bool,
+}
+
+impl AudioSink for TtyBell {
+    fn set_playing(&mut self, playing: bool) {
+        if playing && !self.playing {
+            let mut stdout = io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+        }
+        self.playing = playing;
+    }
+}
+
+/// 0xAARRGGBB to a terminal colour
+fn to_color(argb: u32) -> Color {
+    let [_, r, g, b] = argb.to_be_bytes();
+    Color::Rgb { r, g, b }
+}
@@ -0,0 +1,134 @@
+//! Scaling the display into a window
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! The display is scaled up by a whole number, so every CHIP-8 pixel is the
+//! same size, and centered with bars around it when the window doesn't have
+//! its aspect ratio. Scanlines or a grid can be drawn between the pixels.
+//! Everything goes by the size of the display it is given, so a display of
+//! another resolution is laid out the same way.
+
+use std::fmt;
+
+/// Largest size of a CHIP-8 pixel, in screen pixels
+pub const MAX_SCALE: usize = 32;
+
+/// Smallest scale lines are drawn at, as they'd cover too much below it
+pub const MIN_GRID_SCALE: usize = 3;
+
+/// Colour of the bars around the display
+pub const LETTERBOX: u32 = 0xFF00_0000;
+
+/// What is drawn between the pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Grid {
+    #[default]
+    Off,
+    /// Darker lines between rows, like a CRT
+    Scanlines,
+    /// Darker lines between rows and columns, like an LCD
+    Grid,
+}
+
+impl Grid {
+    /// `off`, `scanlines` or `grid`
+    pub fn parse(s: &str) -> Result<Self, ParseGridError> {
+        match s.trim() {
+            "off" => Ok(Grid::Off),
+            "scanlines" => Ok(Grid::Scanlines),
+            "grid" => Ok(Grid::Grid),
+            _ => Err(ParseGridError),
+        }
+    }
+}
+
+/// Returned by `Grid::parse` for unknown styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseGridError;
+
+impl fmt::Display for ParseGridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected off, scanlines or grid")
+    }
+}
+
+impl std::error::Error for ParseGridError {}
+
+/// Where the display goes in a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// Top left corner of the display, in screen pixels
+    pub x: usize,
+    pub y: usize,
+    /// Size of a CHIP-8 pixel, in screen pixels
+    pub scale: usize,
+    /// Size of the window
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Viewport {
+    /// The display at the largest whole scale that fits the window (and at
+    /// least 1, cut off if it doesn't fit), centered
+    pub fn fit(display: (usize, usize), width: usize, height: usize) -> Self {
+        let (display_width, display_height) = display;
+        let scale = (width / display_width.max(1))
+            .min(height / display_height.max(1))
+            .clamp(1, MAX_SCALE);
+        Viewport {
+            x: width.saturating_sub(display_width * scale) / 2,
+            y: height.saturating_sub(display_height * scale) / 2,
+            scale,
+            width,
+            height,
+        }
+    }
+
+    /// The display at a given scale, filling the window exactly
+    pub fn exact(display: (usize, usize), scale: usize) -> Self {
+        Viewport {
+            x: 0,
+            y: 0,
+            scale,
+            width: display.0 * scale,
+            height: display.1 * scale,
+        }
+    }
+
+    /// Scale `source` (a display-sized buffer, row by row) into `output` (a
+    /// window-sized one), with the bars around it
+    pub fn render(&self, source: &[u32], display: (usize, usize), grid: Grid, output: &mut [u32]) {
+        let (display_width, display_height) = display;
+        let scale = self.scale;
+        let lines = scale >= MIN_GRID_SCALE && grid != Grid::Off;
+
+        for (y, row) in output.chunks_mut(self.width).take(self.height).enumerate() {
+            let source_y = match y.checked_sub(self.y).map(|y| y / scale) {
+                Some(source_y) if source_y < display_height => source_y,
+                _ => {
+                    row.fill(LETTERBOX);
+                    continue;
+                }
+            };
+            let scanline = lines && (y - self.y) % scale == scale - 1;
+            let source_row = &source[source_y * display_width..][..display_width];
+
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = match x.checked_sub(self.x).map(|x| (x / scale, x % scale)) {
+                    Some((source_x, column)) if source_x < display_width => {
+                        let gap = grid == Grid::Grid && column == scale - 1;
+                        match scanline || (lines && gap) {
+                            true => dim(source_row[source_x]),
+                            false => source_row[source_x],
+                        }
+                    }
+                    _ => LETTERBOX,
+                };
+            }
+        }
+    }
+}
+
+/// Half as bright, keeping the alpha
+fn dim(pixel: u32) -> u32 {
+    (pixel & 0xFF00_0000) | ((pixel >> 1) & 0x007F_7F7F)
+}
@@ -0,0 +1,186 @@
+//! Virtual keypad
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! The 4x4 hex keypad drawn over a corner of the display, with the keys the
+//! ROM sees as held lit up, and a caption while `Fx0A` waits for a key.
+//! Renderers feed it the `Status` of every frame, and ask it which key is
+//! under the mouse so clicks can press keys too.
+
+use crate::frontend::{
+    text::{self, Canvas, GLYPH_HEIGHT, LINE_HEIGHT},
+    Status,
+};
+use crate::palette::Palette;
+
+/// The keys as laid out on the COSMAC VIP, row by row
+pub const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Size of a key, in font pixels
+const KEY_SIZE: usize = 11;
+
+/// Space between two keys, in font pixels
+const GAP: usize = 1;
+
+/// Space between the keypad and the edges, in font pixels
+const MARGIN: usize = 2;
+
+/// Size of the whole keypad, in font pixels
+const PAD_SIZE: usize = KEY_SIZE * 4 + GAP * 3;
+
+/// A rectangle on the canvas, in screen pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// What's shown of the keypad
+#[derive(Debug, Clone, Default)]
+pub struct VirtualKeypad {
+    visible: bool,
+    /// Keys held down (bit N set => key N held)
+    held: u16,
+    /// Register `Fx0A` stores the key into, while it waits
+    waiting: Option<u8>,
+    /// Something changed, so the display needs drawing again
+    dirty: bool,
+}
+
+impl VirtualKeypad {
+    pub fn new(visible: bool) -> Self {
+        VirtualKeypad {
+            visible,
+            ..VirtualKeypad::default()
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the keypad, and return whether it is now shown
+    pub fn toggle(&mut self) -> bool {
+        self.visible = !self.visible;
+        self.dirty = true;
+        self.visible
+    }
+
+    /// Record the keys of a frame
+    pub fn status(&mut self, status: &Status) {
+        let changed = (self.held, self.waiting) != (status.held_keys, status.waiting_for_key);
+        if self.visible && changed {
+            self.dirty = true;
+        }
+        self.held = status.held_keys;
+        self.waiting = status.waiting_for_key;
+    }
+
+    pub fn is_held(&self, key: u8) -> bool {
+        key <= 0xF && self.held & (1 << key) != 0
+    }
+
+    /// Whether the keypad changed since the last call while shown (or was
+    /// shown or hidden), so the display has to be drawn again
+    pub fn needs_redraw(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// What `Fx0A` is waiting for, if it is
+    pub fn caption(&self) -> Option<String> {
+        let register = self.waiting?;
+        // A key held down now is the one whose release ends the wait
+        let held = (0..=0xF).find(|key| self.is_held(*key));
+        Some(match held {
+            Some(key) => format!("Fx0A: release {:X} -> V{:X}", key, register),
+            None => format!("Fx0A: any key -> V{:X}", register),
+        })
+    }
+
+    /// Where a key is drawn on a canvas of this size, with the keypad in the
+    /// bottom right corner and each font pixel `scale` pixels wide
+    pub fn key_rect(key: u8, width: usize, height: usize, scale: usize) -> Option<Rect> {
+        let (row, column) = LAYOUT.iter().enumerate().find_map(|(row, keys)| {
+            let column = keys.iter().position(|k| *k == key)?;
+            Some((row, column))
+        })?;
+        let left = width.saturating_sub((PAD_SIZE + MARGIN) * scale);
+        let top = height.saturating_sub((PAD_SIZE + MARGIN) * scale);
+        Some(Rect {
+            x: left + column * (KEY_SIZE + GAP) * scale,
+            y: top + row * (KEY_SIZE + GAP) * scale,
+            width: KEY_SIZE * scale,
+            height: KEY_SIZE * scale,
+        })
+    }
+
+    /// The key under a point of the canvas, if the keypad is shown
+    pub fn key_at(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        scale: usize,
+    ) -> Option<u8> {
+        if !self.visible {
+            return None;
+        }
+        (0..=0xF).find(|key| {
+            Self::key_rect(*key, width, height, scale).is_some_and(|rect| rect.contains(x, y))
+        })
+    }
+
+    /// Draw the keypad and the caption above it. Held keys are drawn inverted.
+    pub fn render(&self, canvas: &mut Canvas, palette: &Palette, scale: usize) {
+        if !self.visible {
+            return;
+        }
+        let (background, foreground) = (palette.background(), palette.foreground());
+        let (width, height) = (canvas.width, canvas.height());
+
+        for key in 0..=0xF {
+            let rect = match Self::key_rect(key, width, height, scale) {
+                Some(rect) => rect,
+                None => continue,
+            };
+            // An outline, then the inside filled for keys that aren't held
+            canvas.fill_rect(rect.x, rect.y, rect.width, rect.height, foreground);
+            let color = match self.is_held(key) {
+                true => background,
+                false => {
+                    let inner = (rect.width - scale * 2, rect.height - scale * 2);
+                    canvas.fill_rect(rect.x + scale, rect.y + scale, inner.0, inner.1, background);
+                    foreground
+                }
+            };
+
+            let label = format!("{:X}", key);
+            let x = rect.x + (rect.width - text::text_width(&label, scale)) / 2;
+            let y = rect.y + (rect.height - GLYPH_HEIGHT * scale) / 2;
+            canvas.draw_text(x, y, scale, &label, color);
+        }
+
+        if let Some(caption) = self.caption() {
+            let padding = MARGIN * scale;
+            let box_width = text::text_width(&caption, scale) + padding * 2;
+            let box_height = LINE_HEIGHT * scale;
+            let x = width.saturating_sub(box_width);
+            let y = height.saturating_sub((PAD_SIZE + MARGIN * 2) * scale + box_height);
+            canvas.fill_rect(x, y, box_width, box_height, background);
+            canvas.draw_text(x + padding, y + scale, scale, &caption, foreground);
+        }
+    }
+}
@@ -0,0 +1,549 @@
+//! Window and keyboard backend using the `minifb` crate
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Windows can be resized, and the display is scaled by the largest whole
+//! number that fits (see `viewport.rs`). `minifb` has no fullscreen mode, so
+//! fullscreen is a borderless window covering the monitor, where its size
+//! can be found (see `screen.rs`).
+
+use crate::{
+    compare::Divergence,
+    frontend::{
+        self,
+        browser::Browser,
+        keymap::Keymap,
+        overlay::Overlay,
+        screen::{self, Monitor},
+        screenshot,
+        text::Canvas,
+        viewport::{Grid, Viewport},
+        virtual_keypad::VirtualKeypad,
+        Action, Hotkey, InputSource, PairRenderer, Renderer, Status,
+    },
+    helpers,
+    palette::Palette,
+    phosphor::{Persistence, Phosphor},
+    platform::TIMER_PERIOD_MICROS,
+    Display, DISPLAY_HEIGHT, DISPLAY_WIDTH,
+};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Scale, Window, WindowOptions};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+/// Keys of the window, as of its last update
+#[derive(Debug, Default)]
+struct Keys {
+    held: Vec<Key>,
+    /// Pressed since the input last looked, oldest first
+    pressed: VecDeque<Key>,
+    /// Key of the virtual keypad held down with the mouse
+    clicked: Option<u8>,
+}
+
+/// Shared between the renderer (which owns the window) and the input
+type SharedKeys = Rc<RefCell<Keys>>;
+
+/// How the window looks and behaves
+#[derive(Debug, Clone)]
+pub struct WindowConfig {
+    /// Time between two frames (60Hz by default). `None` doesn't wait at all.
+    pub rate: Option<Duration>,
+    pub palette: Palette,
+    /// Blend each frame with the previous ones
+    pub persistence: Persistence,
+    pub keymap: Keymap,
+    /// Size of a CHIP-8 pixel when the window opens, in screen pixels
+    /// (1 to `viewport::MAX_SCALE`). Resizing the window changes it.
+    pub scale: usize,
+    /// Lines drawn between the pixels
+    pub grid: Grid,
+    /// Cover the monitor from the start
+    pub fullscreen: bool,
+    /// Show the statistics overlay from the start (see `overlay.rs`)
+    pub overlay: bool,
+    /// Show the virtual keypad from the start (see `virtual_keypad.rs`)
+    pub keypad: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowConfig {
+            rate: Some(Duration::from_micros(TIMER_PERIOD_MICROS)),
+            palette: Palette::default(),
+            persistence: Persistence::default(),
+            keymap: Keymap::default(),
+            scale: 16,
+            grid: Grid::Off,
+            fullscreen: false,
+            overlay: false,
+            keypad: false,
+        }
+    }
+}
+
+/// Columns between the two displays of a side by side window
+const GAP: usize = 2;
+
+/// Pixels that differ between the two sides
+const DIFFERENT: u32 = 0xFFFF_0000;
+
+/// Colour of the gap between the two sides
+const SEPARATOR: u32 = 0xFF80_8080;
+
+/// Size of the ROM browser, as big as the display at 16x
+const BROWSER_WIDTH: usize = DISPLAY_WIDTH * 16;
+const BROWSER_HEIGHT: usize = DISPLAY_HEIGHT * 16;
+
+/// A resizable window, which `minifb` doesn't scale (the buffer given to
+/// it is always the window's size). With a monitor, it is a borderless
+/// window covering it instead.
+fn new_window(
+    title: &str,
+    size: (usize, usize),
+    fullscreen: Option<Monitor>,
+    rate: Option<Duration>,
+) -> Result<Window, minifb::Error> {
+    let opts = WindowOptions {
+        borderless: fullscreen.is_some(),
+        topmost: fullscreen.is_some(),
+        resize: true,
+        scale: Scale::X1,
+        ..WindowOptions::default()
+    };
+
+    let (width, height) = fullscreen.map_or(size, |monitor| (monitor.width, monitor.height));
+    let mut window = Window::new(title, width, height, opts)?;
+    window.limit_update_rate(rate);
+    if let Some(monitor) = fullscreen {
+        window.set_position(monitor.x, monitor.y);
+    }
+    Ok(window)
+}
+
+/// The monitor to cover when opening fullscreen, if it can be found
+fn startup_monitor(fullscreen: bool) -> Option<Monitor> {
+    if !fullscreen {
+        return None;
+    }
+    let monitor = screen::monitor_at(0, 0);
+    if monitor.is_none() {
+        eprintln!("Can't tell how big the screen is here, so the window isn't fullscreen");
+    }
+    monitor
+}
+
+/// Open a window and return its two halves.
+/// The display is scaled up here rather than by `minifb`, so messages can
+/// be drawn over it at the window's resolution (see `overlay.rs`).
+pub fn open(
+    title: &str,
+    config: WindowConfig,
+) -> Result<(WindowRenderer, WindowInput), minifb::Error> {
+    let viewport = Viewport::exact((DISPLAY_WIDTH, DISPLAY_HEIGHT), config.scale);
+    let size = (viewport.width, viewport.height);
+    let fullscreen = startup_monitor(config.fullscreen);
+    let window = new_window(title, size, fullscreen, config.rate)?;
+    let keys = SharedKeys::default();
+
+    Ok((
+        WindowRenderer {
+            window,
+            keys: keys.clone(),
+            title: title.to_owned(),
+            rate: config.rate,
+            windowed: fullscreen.map(|monitor| ((monitor.x, monitor.y), size)),
+            palette: config.palette,
+            phosphor: Phosphor::new(config.persistence),
+            latest: [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            buffer: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            grid: config.grid,
+            viewport,
+            output: vec![0; viewport.width * viewport.height],
+            overlay: Overlay::new(config.overlay),
+            keypad: VirtualKeypad::new(config.keypad),
+        },
+        WindowInput {
+            keys,
+            keymap: config.keymap,
+        },
+    ))
+}
+
+/// Fit a display into the window as it is now, making room in `output`
+/// if the window was resized. Returns the new viewport.
+fn fit_window(window: &Window, display: (usize, usize), output: &mut Vec<u32>) -> Viewport {
+    let (width, height) = window.get_size();
+    let viewport = Viewport::fit(display, width.max(1), height.max(1));
+    output.resize(viewport.width * viewport.height, 0);
+    viewport
+}
+
+/// Record the keys after a window update.
+/// minifb only polls for events on update, so this goes after each one.
+fn refresh_keys(window: &Window, keys: &SharedKeys) {
+    let mut keys = keys.borrow_mut();
+    keys.held = window.get_keys();
+    keys.pressed.extend(window.get_keys_pressed(KeyRepeat::No));
+}
+
+/// Open a window showing two displays side by side (see `frontend::run_compare`).
+/// Persistence isn't applied, so flicker can't hide differences.
+pub fn open_side_by_side(
+    title: &str,
+    config: WindowConfig,
+) -> Result<(SideBySideRenderer, WindowInput), minifb::Error> {
+    let width = DISPLAY_WIDTH * 2 + GAP;
+    let viewport = Viewport::exact((width, DISPLAY_HEIGHT), config.scale);
+    let size = (viewport.width, viewport.height);
+    let window = new_window(title, size, startup_monitor(config.fullscreen), config.rate)?;
+    let keys = SharedKeys::default();
+
+    Ok((
+        SideBySideRenderer {
+            window,
+            keys: keys.clone(),
+            title: title.to_owned(),
+            palette: config.palette,
+            grid: config.grid,
+            half: vec![0; DISPLAY_WIDTH * DISPLAY_HEIGHT],
+            buffer: vec![SEPARATOR; width * DISPLAY_HEIGHT],
+            output: vec![0; viewport.width * viewport.height],
+        },
+        WindowInput {
+            keys,
+            keymap: config.keymap,
+        },
+    ))
+}
+
+/// Show a ROM browser until a ROM is picked with Enter. `None` if Escape
+/// is pressed or the window is closed instead.
+pub fn browse(
+    title: &str,
+    browser: &mut Browser,
+    palette: &Palette,
+) -> Result<Option<PathBuf>, minifb::Error> {
+    let mut window = Window::new(
+        title,
+        BROWSER_WIDTH,
+        BROWSER_HEIGHT,
+        WindowOptions::default(),
+    )?;
+    window.limit_update_rate(Some(Duration::from_micros(TIMER_PERIOD_MICROS)));
+
+    let mut buffer = vec![0; BROWSER_WIDTH * BROWSER_HEIGHT];
+    let page = Browser::visible_rows(BROWSER_HEIGHT) as isize;
+    while window.is_open() {
+        let mut canvas = Canvas {
+            buffer: &mut buffer,
+            width: BROWSER_WIDTH,
+        };
+        browser.render(&mut canvas, palette);
+        window.update_with_buffer(&buffer, BROWSER_WIDTH, BROWSER_HEIGHT)?;
+
+        for key in window.get_keys_pressed(KeyRepeat::Yes) {
+            match key {
+                Key::Up => browser.move_by(-1),
+                Key::Down => browser.move_by(1),
+                Key::PageUp => browser.move_by(-page),
+                Key::PageDown => browser.move_by(page),
+                Key::Home => browser.select_first(),
+                Key::End => browser.select_last(),
+                Key::Enter | Key::NumPadEnter => {
+                    if let Some(file) = browser.selected() {
+                        return Ok(Some(file.path.clone()));
+                    }
+                }
+                Key::Escape => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The drawing half of a `minifb` window
+pub struct WindowRenderer {
+    window: Window,
+    keys: SharedKeys,
+    /// To open the window again, fullscreen or not
+    title: String,
+    rate: Option<Duration>,
+    /// Position and size of the window to go back to, while fullscreen
+    windowed: Option<((isize, isize), (usize, usize))>,
+    palette: Palette,
+    phosphor: Phosphor,
+    /// Last display drawn, kept to fade it out with persistence
+    latest: Display,
+    /// The display, one pixel per CHIP-8 pixel
+    buffer: Vec<u32>,
+    grid: Grid,
+    /// Where the display goes in `output`
+    viewport: Viewport,
+    /// What the window shows, at its size
+    output: Vec<u32>,
+    overlay: Overlay,
+    keypad: VirtualKeypad,
+}
+
+impl WindowRenderer {
+    /// Blend in the latest display, and show it
+    fn present(&mut self) {
+        self.phosphor.push(&self.latest);
+        self.palette
+            .render_intensity(self.phosphor.intensity(), &mut self.buffer);
+        let display = (DISPLAY_WIDTH, DISPLAY_HEIGHT);
+        self.viewport = fit_window(&self.window, display, &mut self.output);
+        self.viewport
+            .render(&self.buffer, display, self.grid, &mut self.output);
+
+        let text_scale = self.text_scale();
+        let mut canvas = Canvas {
+            buffer: &mut self.output,
+            width: self.viewport.width,
+        };
+        self.keypad.render(&mut canvas, &self.palette, text_scale);
+        self.overlay.render(&mut canvas, &self.palette, text_scale);
+
+        let (width, height) = (self.viewport.width, self.viewport.height);
+        self.window
+            .update_with_buffer(&self.output, width, height)
+            .unwrap();
+        self.refresh_input();
+    }
+
+    /// Size of a font pixel of the overlays.
+    /// Crisp at the usual 16x, and still readable at small sizes.
+    fn text_scale(&self) -> usize {
+        (self.viewport.scale / 8).max(1)
+    }
+
+    /// Record the keys, and the virtual keypad key clicked, after an update
+    fn refresh_input(&mut self) {
+        refresh_keys(&self.window, &self.keys);
+
+        let (width, height) = (self.viewport.width, self.viewport.height);
+        let clicked = match self.window.get_mouse_down(MouseButton::Left) {
+            true => self
+                .window
+                .get_mouse_pos(MouseMode::Discard)
+                .and_then(|(x, y)| {
+                    let (x, y) = (x as usize, y as usize);
+                    self.keypad.key_at(x, y, width, height, self.text_scale())
+                }),
+            false => None,
+        };
+        self.keys.borrow_mut().clicked = clicked;
+    }
+}
+
+impl Renderer for WindowRenderer {
+    fn draw(&mut self, display: &Display) {
+        self.latest = *display;
+        self.present();
+    }
+
+    fn update(&mut self) {
+        // Faded pixels keep fading even if nothing changed, the overlays
+        // change, and a resized window needs filling
+        let overlays = self.overlay.needs_redraw() | self.keypad.needs_redraw();
+        let resized = self.window.get_size() != (self.viewport.width, self.viewport.height);
+        if self.phosphor.mode() != Persistence::Off || overlays || resized {
+            return self.present();
+        }
+
+        self.window.update();
+        self.refresh_input();
+    }
+
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn message(&mut self, text: &str) {
+        self.overlay.message(text);
+    }
+
+    fn status(&mut self, status: &Status) {
+        self.overlay.status(status);
+        self.keypad.status(status);
+    }
+
+    fn toggle_overlay(&mut self) -> Option<bool> {
+        Some(self.overlay.toggle())
+    }
+
+    /// Keys of the virtual keypad can be clicked while it is shown
+    fn toggle_keypad(&mut self) -> Option<bool> {
+        Some(self.keypad.toggle())
+    }
+
+    /// Open the window again, covering the monitor it is on, or back where
+    /// it was. `None` where the monitor can't be found (see `screen.rs`).
+    fn toggle_fullscreen(&mut self) -> Option<bool> {
+        let reopened = match self.windowed {
+            Some(((x, y), size)) => {
+                new_window(&self.title, size, None, self.rate).map(|mut window| {
+                    window.set_position(x, y);
+                    (window, None)
+                })
+            }
+            None => {
+                let (x, y) = self.window.get_position();
+                let monitor = screen::monitor_at(x, y)?;
+                let size = self.window.get_size();
+                new_window(&self.title, size, Some(monitor), self.rate)
+                    .map(|window| (window, Some(((x, y), size))))
+            }
+        };
+
+        match reopened {
+            Ok((window, windowed)) => {
+                self.window = window;
+                self.windowed = windowed;
+                self.present();
+            }
+            Err(err) => eprintln!("Can't open the window again: {}", err),
+        }
+        Some(self.windowed.is_some())
+    }
+
+    /// Saved in the current directory, at the display's scale in the window
+    /// and with its lines, but without the bars or the overlays
+    fn screenshot(&mut self) -> Option<io::Result<PathBuf>> {
+        let display = (DISPLAY_WIDTH, DISPLAY_HEIGHT);
+        let viewport = Viewport::exact(display, self.viewport.scale);
+        let mut pixels = vec![0; viewport.width * viewport.height];
+        viewport.render(&self.buffer, display, self.grid, &mut pixels);
+        let (width, height) = (viewport.width, viewport.height);
+        Some(screenshot::save(&pixels, width, height, Path::new(".")))
+    }
+}
+
+/// The drawing half of a side by side window.
+/// Pixels that differ between the two displays are drawn in red.
+pub struct SideBySideRenderer {
+    window: Window,
+    keys: SharedKeys,
+    title: String,
+    palette: Palette,
+    grid: Grid,
+    /// One display rendered on its own
+    half: Vec<u32>,
+    /// Both displays, one pixel per CHIP-8 pixel
+    buffer: Vec<u32>,
+    /// What the window shows, at its size
+    output: Vec<u32>,
+}
+
+impl SideBySideRenderer {
+    /// Render a display into the left or right half of the buffer
+    fn render_half(&mut self, display: &Display, other: &Display, offset: usize) {
+        self.palette.render(display, &mut self.half);
+
+        let width = DISPLAY_WIDTH * 2 + GAP;
+        for y in 0..DISPLAY_HEIGHT {
+            for x in 0..DISPLAY_WIDTH {
+                let pixel = match display[y][x] != other[y][x] {
+                    true => DIFFERENT,
+                    false => self.half[y * DISPLAY_WIDTH + x],
+                };
+                self.buffer[y * width + offset + x] = pixel;
+            }
+        }
+    }
+}
+
+impl PairRenderer for SideBySideRenderer {
+    fn draw_pair(&mut self, left: &Display, right: &Display) {
+        self.render_half(left, right, 0);
+        self.render_half(right, left, DISPLAY_WIDTH + GAP);
+
+        let both = (DISPLAY_WIDTH * 2 + GAP, DISPLAY_HEIGHT);
+        let viewport = fit_window(&self.window, both, &mut self.output);
+        viewport.render(&self.buffer, both, self.grid, &mut self.output);
+        self.window
+            .update_with_buffer(&self.output, viewport.width, viewport.height)
+            .unwrap();
+        refresh_keys(&self.window, &self.keys);
+    }
+
+    fn diverged(&mut self, divergence: &Divergence) {
+        self.window
+            .set_title(&format!("{} - diverged at {}", self.title, divergence));
+    }
+
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+}
+
+/// The keyboard half of a `minifb` window
+pub struct WindowInput {
+    keys: SharedKeys,
+    keymap: Keymap,
+}
+
+impl InputSource for WindowInput {
+    /// The mapped keys, and the key of the virtual keypad under the mouse
+    fn held_keys(&mut self) -> u16 {
+        let keys = self.keys.borrow();
+        let clicked = keys.clicked.map_or(0, |key| 1 << key);
+        self.keymap
+            .held(keys.held.iter().filter_map(|key| helpers::host_key(*key)))
+            | clicked
+    }
+
+    fn quit_requested(&mut self) -> bool {
+        self.keys.borrow().held.contains(&Key::Escape)
+    }
+
+    /// Tab fast-forwards, ` (backquote) slows down
+    fn hotkey_held(&mut self, hotkey: Hotkey) -> bool {
+        let key = match hotkey {
+            Hotkey::FastForward => Key::Tab,
+            Hotkey::SlowMotion => Key::Backquote,
+        };
+        self.keys.borrow().held.contains(&key)
+    }
+
+    /// Function keys (see `frontend::function_key_action`), Pause, Home for
+    /// the overlay, Insert for the keypad, and Page Up, Page Down and End for
+    /// the volume
+    fn next_action(&mut self) -> Option<Action> {
+        let mut keys = self.keys.borrow_mut();
+        while let Some(key) = keys.pressed.pop_front() {
+            let action = match key {
+                Key::F1 => frontend::function_key_action(1),
+                Key::F2 => frontend::function_key_action(2),
+                Key::F3 => frontend::function_key_action(3),
+                Key::F4 => frontend::function_key_action(4),
+                Key::F5 => frontend::function_key_action(5),
+                Key::F6 => frontend::function_key_action(6),
+                Key::F7 => frontend::function_key_action(7),
+                Key::F8 => frontend::function_key_action(8),
+                Key::F9 => frontend::function_key_action(9),
+                Key::F10 => frontend::function_key_action(10),
+                Key::F11 => frontend::function_key_action(11),
+                Key::F12 => frontend::function_key_action(12),
+                Key::Pause => Some(Action::Pause),
+                Key::Home => Some(Action::ToggleOverlay),
+                Key::Insert => Some(Action::ToggleKeypad),
+                Key::PageUp => Some(Action::VolumeUp),
+                Key::PageDown => Some(Action::VolumeDown),
+                Key::End => Some(Action::Mute),
+                _ => None,
+            };
+            if action.is_some() {
+                return action;
+            }
+        }
+        None
+    }
+}
@@ -1,38 +1,84 @@
 //! Helper functions
 //! @AryaveerSR <me.aryaveer@gmail.com>
 
+use crate::frontend::keymap::HostKey;
 use minifb::Key;
 
 /// ## Key mappings
-/// The key mappings are described here:
-///
-/// https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#keypad
-pub fn keys_to_u8(keys: Vec<Key>) -> Vec<u8> {
-    let mut key_vec: Vec<u8> = vec![];
+/// The host key for a `minifb` key, if it can be bound
+pub fn host_key(key: Key) -> Option<HostKey> {
+    let c = match key {
+        Key::Key0 => '0',
+        Key::Key1 => '1',
+        Key::Key2 => '2',
+        Key::Key3 => '3',
+        Key::Key4 => '4',
+        Key::Key5 => '5',
+        Key::Key6 => '6',
+        Key::Key7 => '7',
+        Key::Key8 => '8',
+        Key::Key9 => '9',
+        Key::A => 'a',
+        Key::B => 'b',
+        Key::C => 'c',
+        Key::D => 'd',
+        Key::E => 'e',
+        Key::F => 'f',
+        Key::G => 'g',
+        Key::H => 'h',
+        Key::I => 'i',
+        Key::J => 'j',
+        Key::K => 'k',
+        Key::L => 'l',
+        Key::M => 'm',
+        Key::N => 'n',
+        Key::O => 'o',
+        Key::P => 'p',
+        Key::Q => 'q',
+        Key::R => 'r',
+        Key::S => 's',
+        Key::T => 't',
+        Key::U => 'u',
+        Key::V => 'v',
+        Key::W => 'w',
+        Key::X => 'x',
+        Key::Y => 'y',
+        Key::Z => 'z',
+        Key::Apostrophe => '\'',
+        Key::Comma => ',',
+        Key::Period => '.',
+        Key::Semicolon => ';',
+        Key::Slash => '/',
+        Key::Backslash => '\\',
+        Key::Minus => '-',
+        Key::Equal => '=',
+        Key::LeftBracket => '[',
+        Key::RightBracket => ']',
 
-    for key in keys {
-        key_vec.push(match key {
-            Key::Key1 => 0x01,
-            Key::Key2 => 0x02,
-            Key::Key3 => 0x03,
-            Key::Key4 => 0x0C,
-            Key::Q => 0x04,
-            Key::W => 0x05,
-            Key::E => 0x06,
-            Key::R => 0x0D,
-            Key::A => 0x07,
-            Key::S => 0x08,
-            Key::D => 0x09,
-            Key::F => 0x0E,
-            Key::Z => 0x0A,
-            Key::X => 0x00,
-            Key::C => 0x0B,
-            Key::V => 0x0F,
-            _ => {
-                continue;
-            }
-        });
-    }
-
-    key_vec
+        Key::NumPad0 => return Some(HostKey::Numpad(0)),
+        Key::NumPad1 => return Some(HostKey::Numpad(1)),
+        Key::NumPad2 => return Some(HostKey::Numpad(2)),
+        Key::NumPad3 => return Some(HostKey::Numpad(3)),
+        Key::NumPad4 => return Some(HostKey::Numpad(4)),
+        Key::NumPad5 => return Some(HostKey::Numpad(5)),
+        Key::NumPad6 => return Some(HostKey::Numpad(6)),
+        Key::NumPad7 => return Some(HostKey::Numpad(7)),
+        Key::NumPad8 => return Some(HostKey::Numpad(8)),
+        Key::NumPad9 => return Some(HostKey::Numpad(9)),
+        Key::NumPadSlash => return Some(HostKey::NumpadDivide),
+        Key::NumPadAsterisk => return Some(HostKey::NumpadMultiply),
+        Key::NumPadMinus => return Some(HostKey::NumpadSubtract),
+        Key::NumPadPlus => return Some(HostKey::NumpadAdd),
+        Key::NumPadEnter => return Some(HostKey::NumpadEnter),
+        Key::NumPadDot => return Some(HostKey::NumpadDecimal),
+        Key::Up => return Some(HostKey::Up),
+        Key::Down => return Some(HostKey::Down),
+        Key::Left => return Some(HostKey::Left),
+        Key::Right => return Some(HostKey::Right),
+        Key::Space => return Some(HostKey::Space),
+        Key::Enter => return Some(HostKey::Enter),
+        Key::Backspace => return Some(HostKey::Backspace),
+        _ => return None,
+    };
+    Some(HostKey::Char(c))
 }
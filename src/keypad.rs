@@ -0,0 +1,89 @@
+//! The hex keypad
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Keeps which of the 16 keys are held down, and latches presses and
+//! releases in between, so `Fx0A` can wait for a whole press and release
+//! like the original COSMAC VIP did.
+
+/// State of the 16 keys (0x0 to 0xF). Bit N is key N.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Keypad {
+    /// Keys currently held down
+    held: u16,
+    /// Keys that went down since the last `clear_edges`
+    pressed: u16,
+    /// Keys that went up since the last `clear_edges`
+    released: u16,
+}
+
+impl Keypad {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A key went down. Keys above 0xF are ignored.
+    pub fn key_down(&mut self, key: u8) {
+        if let Some(bit) = bit(key) {
+            if self.held & bit == 0 {
+                // A release before this press doesn't count any more
+                self.pressed |= bit;
+                self.released &= !bit;
+            }
+            self.held |= bit;
+        }
+    }
+
+    /// A key went up. Keys above 0xF are ignored.
+    pub fn key_up(&mut self, key: u8) {
+        if let Some(bit) = bit(key) {
+            if self.held & bit != 0 {
+                self.released |= bit;
+            }
+            self.held &= !bit;
+        }
+    }
+
+    /// Replace the held keys all at once (bit N set => key N held),
+    /// generating presses and releases for the keys that changed.
+    /// For frontends that poll the keyboard instead of getting events.
+    pub fn set_held(&mut self, held: u16) {
+        let changed = self.held ^ held;
+        self.pressed |= changed & held;
+        self.released = (self.released & !(changed & held)) | (changed & self.held);
+        self.held = held;
+    }
+
+    /// Keys held down (bit N set => key N held)
+    pub fn held(&self) -> u16 {
+        self.held
+    }
+
+    pub fn is_held(&self, key: u8) -> bool {
+        bit(key).is_some_and(|bit| self.held & bit != 0)
+    }
+
+    /// Forget the presses and releases seen so far
+    pub fn clear_edges(&mut self) {
+        self.pressed = 0;
+        self.released = 0;
+    }
+
+    /// The lowest key that was pressed and then released since the last
+    /// `clear_edges`, if any (a key still down after pressing it again
+    /// doesn't count). Its edges are cleared so it is only returned once.
+    pub fn take_press_and_release(&mut self) -> Option<u8> {
+        let done = self.pressed & self.released;
+        if done == 0 {
+            return None;
+        }
+
+        let key = done.trailing_zeros() as u8;
+        self.pressed &= !(1 << key);
+        self.released &= !(1 << key);
+        Some(key)
+    }
+}
+
+fn bit(key: u8) -> Option<u16> {
+    (key <= 0xF).then(|| 1 << key)
+}
@@ -1,12 +1,61 @@
 //! ### Main Implementation for CHIP-8 Emulator
 //! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! The core is `no_std` and doesn't allocate. Everything else needs the `std` feature.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod compare;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod frontend;
+#[cfg(feature = "frontend-minifb")]
 pub mod helpers;
+pub mod keypad;
+#[cfg(feature = "std")]
+pub mod lint;
+#[cfg(feature = "octo")]
+pub mod octo;
+#[cfg(feature = "pack")]
+pub mod pack;
+pub mod palette;
+pub mod phosphor;
+pub mod platform;
+pub mod quirks;
+#[cfg(feature = "std")]
+pub mod rom;
+#[cfg(feature = "romdb")]
+pub mod romdb;
+pub mod state;
 pub mod structs;
 
-use crate::structs::{BehaviorConfig, Instruction, VariableRegisters};
-use rand::Rng;
-use std::{num::Wrapping, time::Instant};
+use crate::{
+    keypad::Keypad,
+    platform::{Clock, DefaultClock, RandomSource, XorShift},
+    quirks::QuirkUsage,
+    structs::{BehaviorConfig, Instruction, OutOfBounds, VariableRegisters},
+};
+use core::{num::Wrapping, ops::Range};
+
+/// Width of the display in pixels
+pub const DISPLAY_WIDTH: usize = 64;
+/// Height of the display in pixels
+pub const DISPLAY_HEIGHT: usize = 32;
+
+/// Rows of monochromatic pixels, as returned by `Chip::display()`
+pub type Display = [[bool; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+
+/// Size of the addressable memory, in bytes.
+pub const MEMORY_SIZE: usize = 4096;
+
+/// The memory location at which programs should be loaded.
+/// Memory 0x000 to 0x1FF is (almost) empty as it used to contain the
+/// interpreter itself. It's only use is fonts.
+pub const PROGRAM_START: u16 = 0x200;
+
+/// How many return addresses fit on the stack
+pub const STACK_SIZE: usize = 16;
 
 const FONT_0: [u8; 5] = [0xF0, 0x90, 0x90, 0x90, 0xF0];
 const FONT_1: [u8; 5] = [0x20, 0x60, 0x20, 0x20, 0x70];
@@ -25,16 +74,28 @@ const FONT_D: [u8; 5] = [0xE0, 0x90, 0x90, 0x90, 0xE0];
 const FONT_E: [u8; 5] = [0xF0, 0x80, 0xF0, 0x80, 0xF0];
 const FONT_F: [u8; 5] = [0xF0, 0x80, 0xF0, 0x80, 0x80];
 
+/// Sprites of the hex digits, loaded at the start of memory
+pub(crate) const FONTS: [[u8; 5]; 16] = [
+    FONT_0, FONT_1, FONT_2, FONT_3, FONT_4, FONT_5, FONT_6, FONT_7, FONT_8, FONT_9, FONT_A, FONT_B,
+    FONT_C, FONT_D, FONT_E, FONT_F,
+];
+
+/// The interpreter itself.
+/// `C` and `R` default to the wall clock and a randomly seeded xorshift
+/// with `std`, and can be replaced using `Chip::with_platform`.
 #[derive(Debug)]
-pub struct Chip {
+pub struct Chip<C: Clock = DefaultClock, R: RandomSource = XorShift> {
     /// 4KB Memory (4096 Bytes)
-    memory: Vec<u8>,
+    memory: [u8; MEMORY_SIZE],
     /// 2D Array of bools each representing a monochromatic pixel.
     /// 1 => White;
     /// 0 => Black
-    display: [[bool; 64]; 32],
+    display: Display,
     /// Last in, First out stack.
-    stack: Vec<u16>,
+    stack: [u16; STACK_SIZE],
+    /// Stack Pointer.
+    /// Number of addresses currently on the stack
+    sp: usize,
     /// Decremented by 60 every second (60Hz)
     delay_timer: u8,
     /// Decremented by 60 every second (60Hz)
@@ -51,50 +112,209 @@ pub struct Chip {
     /// If the program is halted and waiting for a keypress
     /// The u8 is the register to put the keycode into.
     is_waiting_for_press: Option<u8>,
-    /// Time elapsed since last timer update
-    last_update: Instant,
+    /// The hex keypad, updated by the frontend
+    keypad: Keypad,
+    /// Clock time (in microseconds) of the last timer update
+    last_update: u64,
+    /// Source of time for the timers
+    clock: C,
+    /// Source of random numbers for Cxnn
+    rng: R,
     /// Behavior Configurations for conflicting implementations (Check `structs.rs`)
     behavior: BehaviorConfig,
     /// Should be playing sound ?
     is_playing_sound: bool,
+    /// When execution depended on a quirk, if recording (see `quirks.rs`)
+    quirk_usage: Option<QuirkUsage>,
 }
 
 impl Chip {
-    /// The memory location at which programs should be loaded.
-    /// Memory 0x000 to 0x1FF is (almost) empty as it used to contain the
-    /// interpreter itself. It's only use is fonts.
-    const PROGRAM_START: u16 = 0x200;
+    /// Load a program, using the default clock and random number generator
+    pub fn new(program: impl AsRef<[u8]>, behavior: BehaviorConfig) -> Self {
+        #[cfg(feature = "std")]
+        let rng = XorShift::from_entropy();
+        #[cfg(not(feature = "std"))]
+        let rng = XorShift::default();
+
+        Self::with_platform(program, behavior, DefaultClock::default(), rng)
+    }
+}
 
-    pub fn display(&self) -> [[bool; 64]; 32] {
+impl<C: Clock, R: RandomSource> Chip<C, R> {
+    pub fn display(&self) -> Display {
         self.display
     }
 
+    /// Address of the next instruction to be executed
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Point the Program Counter somewhere else.
+    /// Fails if there isn't a full instruction at that address.
+    pub fn set_pc(&mut self, pc: u16) -> Result<(), OutOfBounds> {
+        if pc as usize + 1 >= MEMORY_SIZE {
+            return Err(OutOfBounds);
+        }
+        self.pc = pc;
+        Ok(())
+    }
+
+    /// Current value of the Index register
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
+    /// Set the Index register.
+    /// Fails if the address is outside of memory.
+    pub fn set_i_reg(&mut self, i_reg: u16) -> Result<(), OutOfBounds> {
+        if i_reg as usize >= MEMORY_SIZE {
+            return Err(OutOfBounds);
+        }
+        self.i_reg = i_reg;
+        Ok(())
+    }
+
+    /// The V(0) to V(F) registers
+    pub fn registers(&self) -> &VariableRegisters {
+        &self.var_reg
+    }
+
+    /// Mutable access to V(0) to V(F)
+    /// (Any u8 is a valid register value, so no checks are needed)
+    pub fn registers_mut(&mut self) -> &mut VariableRegisters {
+        &mut self.var_reg
+    }
+
+    /// A slice of memory, or `None` if the range doesn't fit in memory
+    pub fn memory(&self, range: Range<usize>) -> Option<&[u8]> {
+        self.memory.get(range)
+    }
+
+    /// Copy `data` into memory starting at `addr`.
+    /// Nothing is written if it doesn't fit.
+    pub fn write_memory(&mut self, addr: u16, data: &[u8]) -> Result<(), OutOfBounds> {
+        let start = addr as usize;
+        let end = start + data.len();
+        if end > MEMORY_SIZE {
+            return Err(OutOfBounds);
+        }
+        self.memory[start..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Return addresses on the stack, from the bottom (oldest) to the top
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// If halted by Fx0A, the register the keycode will be stored into
+    pub fn waiting_for_key(&self) -> Option<u8> {
+        self.is_waiting_for_press
+    }
+
+    pub fn keypad(&self) -> &Keypad {
+        &self.keypad
+    }
+
+    /// Press and release keys through this
+    pub fn keypad_mut(&mut self) -> &mut Keypad {
+        &mut self.keypad
+    }
+
+    /// Should the buzzer be playing ?
+    pub fn is_playing_sound(&self) -> bool {
+        self.is_playing_sound
+    }
+
+    pub fn behavior(&self) -> BehaviorConfig {
+        self.behavior
+    }
+
+    /// Change quirks while running (eg: to try another one out)
+    pub fn behavior_mut(&mut self) -> &mut BehaviorConfig {
+        &mut self.behavior
+    }
+
+    /// Start recording when execution depends on a quirk, forgetting what
+    /// was recorded so far
+    pub fn detect_quirks(&mut self) {
+        self.quirk_usage = Some(QuirkUsage::default());
+    }
+
+    /// What was recorded since `detect_quirks`
+    pub fn quirk_usage(&self) -> Option<&QuirkUsage> {
+        self.quirk_usage.as_ref()
+    }
+
+    /// The clock driving the timers (eg: to advance a `ManualClock`)
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    /// Decrement the delay and sound timers once.
+    /// `process_instruction` does this by itself using the clock,
+    /// `run_frame` calls it once per frame.
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer != 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer != 0 {
+            self.sound_timer -= 1;
+            if self.sound_timer == 0 {
+                self.is_playing_sound = false;
+            }
+        }
+    }
+
+    /// Run one 60Hz frame: `instructions` instructions, then a timer tick.
+    /// Unlike `process_instruction`, the clock isn't used at all, so runs are
+    /// deterministic and the speed is up to the caller.
+    /// Outputs a tuple (has_display_updated, should_play_sound)
+    pub fn run_frame(&mut self, instructions: u32) -> (bool, bool) {
+        let mut display_update = false;
+        for _ in 0..instructions {
+            display_update |= self.step().0;
+        }
+        self.tick_timers();
+
+        (display_update, self.is_playing_sound)
+    }
+
     /// Process current instruction
-    /// Takes a Vec<u8> of all the (supported) keys currently being pressed
+    /// Keys are read from `keypad()`
     /// Outputs a tuple (has_display_updated, should_play_sound)
-    pub fn process_instruction(&mut self, keys: Vec<u8>) -> (bool, bool) {
+    pub fn process_instruction(&mut self) -> (bool, bool) {
         // Update timers
-        if self.last_update.elapsed().as_millis() > 17 {
-            if self.delay_timer != 0 {
-                self.delay_timer -= 1;
-            }
-            if self.sound_timer != 0 {
-                self.sound_timer -= 1;
-                if self.sound_timer == 0 {
-                    self.is_playing_sound = false;
-                }
-            }
-            self.last_update = Instant::now();
+        let now = self.clock.now_micros();
+        if now.saturating_sub(self.last_update) > platform::TIMER_PERIOD_MICROS {
+            self.tick_timers();
+            self.last_update = now;
         }
 
-        // Check if it's supposed to wait for a keypress
-        if self.is_waiting_for_press.is_some() {
-            if keys.is_empty() {
-                return (false, self.is_playing_sound);
-            } else {
-                // Put the keycode into the register if there's a key pressed
-                self.var_reg
-                    .set(self.is_waiting_for_press.unwrap(), keys[0]);
+        self.step()
+    }
+
+    /// Execute current instruction, without touching the timers (see `tick_timers`)
+    /// Outputs a tuple (has_display_updated, should_play_sound)
+    pub fn step(&mut self) -> (bool, bool) {
+        // Check if it's supposed to wait for a keypress.
+        // Like on the VIP, it only counts once the key is released again.
+        if let Some(reg) = self.is_waiting_for_press {
+            match self.keypad.take_press_and_release() {
+                Some(key) => {
+                    self.var_reg.set(reg, key);
+                    self.is_waiting_for_press = None;
+                }
+                None => return (false, self.is_playing_sound),
             }
         }
 
@@ -110,7 +330,12 @@ impl Chip {
             .get(self.pc as usize + 1)
             .expect("Program Counter is invalid") as u16;
 
-        let instr = Instruction::from_u16(((first_byte as u16) << 8) | second_byte as u16);
+        let instr = Instruction::from_u16(((first_byte as u16) << 8) | second_byte);
+
+        if let Some(usage) = &mut self.quirk_usage {
+            let v = core::array::from_fn(|reg| self.var_reg.get(reg as u8));
+            usage.observe(self.pc, &instr, v, self.i_reg, &self.memory);
+        }
 
         // Increment to next instruction
         self.pc += 2;
@@ -120,14 +345,17 @@ impl Chip {
                 // 00E0
                 // Clear the display
                 0x00E0 => {
-                    self.display = [[false; 64]; 32];
+                    self.display = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
                     return (true, self.is_playing_sound);
                 }
 
                 // 00EE
                 // Return from subroutine
                 // Pops the return address from stack and sets the PC
-                0x00EE => self.pc = self.stack.pop().unwrap(),
+                0x00EE => {
+                    self.sp = self.sp.checked_sub(1).expect("Stack underflow");
+                    self.pc = self.stack[self.sp];
+                }
 
                 // 0000
                 // Blank
@@ -142,7 +370,8 @@ impl Chip {
             // 2nnn
             // Call subroutine and push current PC to stack
             0x2 => {
-                self.stack.push(self.pc);
+                *self.stack.get_mut(self.sp).expect("Stack overflow") = self.pc;
+                self.sp += 1;
                 self.pc = instr.get_addr();
             }
             // 3xnn
@@ -241,8 +470,9 @@ impl Chip {
                         // Conflicting Implementations
                         // https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy6-and-8xye-shift
 
-                        self.var_reg.set(x_addr, y_val >> 1);
-                        self.var_reg.vf = ((x_val & 0x1) != 0) as u8;
+                        let val = if self.behavior.shift { x_val } else { y_val };
+                        self.var_reg.set(x_addr, val >> 1);
+                        self.var_reg.vf = val & 0x1;
                     }
                     // 8xy7
                     // Set V(x) = V(y) - V(x) & set V(F) as NOT borrow
@@ -263,8 +493,9 @@ impl Chip {
                         // Conflicting Implementations
                         // https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy6-and-8xye-shift
 
-                        self.var_reg.set(x_addr, y_val << 1);
-                        self.var_reg.vf = ((y_val & 0x80) != 0) as u8;
+                        let val = if self.behavior.shift { x_val } else { y_val };
+                        self.var_reg.set(x_addr, val << 1);
+                        self.var_reg.vf = val >> 7;
                     }
                     _ => panic!(),
                 };
@@ -273,19 +504,26 @@ impl Chip {
             // Skip next instruction if V(x) != V(y)
             0x9 => {
                 if self.var_reg.get(instr.get_nib(1)) != self.var_reg.get(instr.get_nib(2)) {
-                    self.pc = self.pc + 2;
+                    self.pc += 2;
                 }
             }
             // Annn
             // Set I to nnn
             0xA => self.i_reg = instr.get_addr(),
             // Bnnn
-            // Set PC to nnn + V(0)
-            0xB => self.pc = instr.get_addr() + (self.var_reg.v0 as u16),
+            // Set PC to nnn + V(0), or nnn + V(x) with the jump quirk (Bxnn)
+            0xB => {
+                let offset = if self.behavior.jump {
+                    self.var_reg.get(instr.get_nib(1))
+                } else {
+                    self.var_reg.v0
+                };
+                self.pc = instr.get_addr() + offset as u16;
+            }
             // Cxnn
             // Generate a random number from 0 to 255 and AND it with nn
             0xC => {
-                let num = rand::thread_rng().gen_range(0..256) as u8;
+                let num = self.rng.next_byte();
                 self.var_reg.set(instr.get_nib(1), instr.get_lbyte() & num);
             }
             // Dxyn
@@ -310,7 +548,7 @@ impl Chip {
                     let sprite_data = self.memory[(addr + i as u16) as usize];
 
                     // For every bit..
-                    for j in 0..(8 as u8) {
+                    for j in 0..8_u8 {
                         if sprite_data & (128 >> j) != 0 {
                             if self.display[y_coord][x_coord] {
                                 self.var_reg.vf = 1;
@@ -321,14 +559,21 @@ impl Chip {
 
                         x_coord += 1;
                         if x_coord >= 64 {
-                            break;
+                            // Clipped, unless the wrap quirk is on
+                            if !self.behavior.wrap {
+                                break;
+                            }
+                            x_coord = 0;
                         }
                     }
 
                     x_coord = initial_x as usize;
                     y_coord += 1;
                     if y_coord >= 32 {
-                        break;
+                        if !self.behavior.wrap {
+                            break;
+                        }
+                        y_coord = 0;
                     }
                 }
 
@@ -341,14 +586,14 @@ impl Chip {
                     // Ex9E
                     // Skip instruction if V(x) key is pressed
                     0xE => {
-                        if keys.contains(&x_val) {
+                        if self.keypad.is_held(x_val) {
                             self.pc += 2;
                         }
                     }
                     // ExA1
                     // Skip instruction if V(x) is not pressed
                     0x1 => {
-                        if !keys.contains(&x_val) {
+                        if !self.keypad.is_held(x_val) {
                             self.pc += 2;
                         }
                     }
@@ -367,6 +612,8 @@ impl Chip {
                     // Fx0A
                     // Wait for key press and store value in V(x)
                     0xA => {
+                        // Keys already held down have to be pressed again
+                        self.keypad.clear_edges();
                         self.is_waiting_for_press = Some(x_addr);
                         return (false, self.is_playing_sound);
                     }
@@ -415,6 +662,9 @@ impl Chip {
                                     self.var_reg
                                         .set(i, self.memory[(self.i_reg + i as u16) as usize]);
                                 }
+                                if self.behavior.increment_i_on_save_load {
+                                    self.i_reg += x_addr as u16 + 1;
+                                }
                             }
                             _ => panic!(),
                         }
@@ -425,47 +675,46 @@ impl Chip {
             }
             _ => panic!(),
         }
-        return (false, self.is_playing_sound);
+        (false, self.is_playing_sound)
     }
 
-    pub fn new(program: Vec<u8>, behavior: BehaviorConfig) -> Self {
-        let mut memory: Vec<u8> = vec![];
-
-        // Loads stuff into memory (painful to the eyes)
-        memory.extend_from_slice(&FONT_0);
-        memory.extend_from_slice(&FONT_1);
-        memory.extend_from_slice(&FONT_2);
-        memory.extend_from_slice(&FONT_3);
-        memory.extend_from_slice(&FONT_4);
-        memory.extend_from_slice(&FONT_5);
-        memory.extend_from_slice(&FONT_6);
-        memory.extend_from_slice(&FONT_7);
-        memory.extend_from_slice(&FONT_8);
-        memory.extend_from_slice(&FONT_9);
-        memory.extend_from_slice(&FONT_A);
-        memory.extend_from_slice(&FONT_B);
-        memory.extend_from_slice(&FONT_C);
-        memory.extend_from_slice(&FONT_D);
-        memory.extend_from_slice(&FONT_E);
-        memory.extend_from_slice(&FONT_F);
-
-        memory.resize((Self::PROGRAM_START) as usize, 0);
-        memory.extend(program);
-        memory.resize(4096, 0);
+    /// Load a program with a custom clock and random number generator.
+    /// Programs longer than the 3.5KB after `PROGRAM_START` are cut short,
+    /// `rom::Rom` checks for that (and more) beforehand.
+    pub fn with_platform(
+        program: impl AsRef<[u8]>,
+        behavior: BehaviorConfig,
+        mut clock: C,
+        rng: R,
+    ) -> Self {
+        let mut memory = [0; MEMORY_SIZE];
+
+        for (i, font) in FONTS.iter().enumerate() {
+            memory[i * 5..(i + 1) * 5].copy_from_slice(font);
+        }
+
+        let program = program.as_ref();
+        let len = program.len().min(MEMORY_SIZE - PROGRAM_START as usize);
+        memory[PROGRAM_START as usize..][..len].copy_from_slice(&program[..len]);
 
         Chip {
             memory,
-            display: [[false; 64]; 32],
-            stack: vec![],
+            display: [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            stack: [0; STACK_SIZE],
+            sp: 0,
             delay_timer: 0,
             sound_timer: 0,
-            pc: Self::PROGRAM_START,
+            pc: PROGRAM_START,
             i_reg: 0,
             var_reg: VariableRegisters::new(),
             is_waiting_for_press: None,
-            last_update: Instant::now(),
+            keypad: Keypad::new(),
+            last_update: clock.now_micros(),
+            clock,
+            rng,
             behavior,
             is_playing_sound: false,
+            quirk_usage: None,
         }
     }
 }
@@ -0,0 +1,386 @@
+//! Static quirk linter
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Follows the reachable code of a ROM (without running it) looking for
+//! instructions whose behavior depends on a `BehaviorConfig` field, so we know
+//! which quirks matter for ROMs that aren't in the ROM database.
+//!
+//! It errs on the side of silence: a quirk is only reported when some
+//! instruction is certain to behave differently, given what can be worked
+//! out statically. Code only reachable through `Bnnn` isn't looked at.
+
+pub use crate::quirks::Quirk;
+use crate::{
+    structs::{BehaviorConfig, Instruction},
+    DISPLAY_HEIGHT, DISPLAY_WIDTH, FONTS, PROGRAM_START,
+};
+use std::{collections::BTreeSet, fmt};
+
+/// How many instructions after `8xy1-3` or `Fx55`/`Fx65` are looked at
+const LOOKAHEAD: usize = 16;
+
+/// Instructions from the extensions of CHIP-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Extension {
+    SuperChip,
+    XoChip,
+}
+
+/// Something found at an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Finding {
+    /// This instruction behaves differently depending on a quirk
+    Quirk(Quirk),
+    /// This instruction is from an extension (and isn't supported here)
+    Extension(Extension),
+    /// `Bnnn`, whose target can't be followed
+    ComputedJump,
+}
+
+/// Everything the linter found, sorted by address
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub findings: Vec<(u16, u16, Finding)>,
+}
+
+impl Report {
+    /// Whether any instruction depends on this quirk
+    pub fn matters(&self, quirk: Quirk) -> bool {
+        self.findings
+            .iter()
+            .any(|(_, _, finding)| *finding == Finding::Quirk(quirk))
+    }
+
+    pub fn uses(&self, extension: Extension) -> bool {
+        self.findings
+            .iter()
+            .any(|(_, _, finding)| *finding == Finding::Extension(extension))
+    }
+
+    /// A platform ID for `BehaviorConfig::for_platform`: the first platform
+    /// (of the extension used, if any) whose quirks contradict the fewest
+    /// findings, see `contradictions`
+    pub fn suggested_profile(&self) -> &'static str {
+        let platforms: &[&'static str] = if self.uses(Extension::XoChip) {
+            &["xochip"]
+        } else if self.uses(Extension::SuperChip) {
+            &["superchip", "superchip1"]
+        } else {
+            &BehaviorConfig::PLATFORMS
+        };
+        platforms
+            .iter()
+            .copied()
+            .min_by_key(|id| {
+                BehaviorConfig::for_platform(id).map_or(usize::MAX, |b| self.contradictions(&b))
+            })
+            .unwrap_or("originalChip8")
+    }
+
+    /// How many of the quirks that matter are off in `behavior`, when a ROM
+    /// doing that was most likely written for them to be on: shifts of
+    /// another register, `Bxnn` with x != 0, and sprites drawn across an
+    /// edge. A V(F) read after a logic op or a use of I after a save/load
+    /// could have been meant either way, so they don't count.
+    pub fn contradictions(&self, behavior: &BehaviorConfig) -> usize {
+        [
+            (Quirk::Shift, behavior.shift),
+            (Quirk::Jump, behavior.jump),
+            (Quirk::Wrap, behavior.wrap),
+        ]
+        .into_iter()
+        .filter(|(quirk, on)| self.matters(*quirk) && !on)
+        .count()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (addr, opcode, finding) in &self.findings {
+            let what = match finding {
+                Finding::Quirk(Quirk::VfReset) => "logic op followed by a V(F) read",
+                Finding::Quirk(Quirk::IncrementI) => "save/load followed by a use of I",
+                Finding::Quirk(Quirk::Shift) => "shift with V(x) and V(y) different",
+                Finding::Quirk(Quirk::Jump) => "jump with offset from V(x) != V(0)",
+                Finding::Quirk(Quirk::Wrap) => "sprite drawn across an edge",
+                Finding::Extension(Extension::SuperChip) => "SUPER-CHIP instruction",
+                Finding::Extension(Extension::XoChip) => "XO-CHIP instruction",
+                Finding::ComputedJump => "computed jump, its targets weren't checked",
+            };
+            writeln!(f, "{:#05X}: {:04X}  {}", addr, opcode, what)?;
+        }
+
+        let matters: Vec<_> = Quirk::ALL
+            .into_iter()
+            .filter(|quirk| self.matters(*quirk))
+            .map(|quirk| quirk.field())
+            .collect();
+        match matters.is_empty() {
+            true => writeln!(f, "No quirk matters")?,
+            false => writeln!(f, "Quirks that matter: {}", matters.join(", "))?,
+        }
+        write!(f, "Suggested profile: {}", self.suggested_profile())
+    }
+}
+
+/// Registers and I, where known
+#[derive(Debug, Clone, Copy)]
+struct Known {
+    v: [Option<u8>; 16],
+    i: Option<u16>,
+}
+
+const UNKNOWN: Known = Known {
+    v: [None; 16],
+    i: None,
+};
+
+/// Lint a program, as loaded at `PROGRAM_START`
+pub fn lint(program: &[u8]) -> Report {
+    let mut linter = Linter {
+        program,
+        visited: BTreeSet::new(),
+        findings: BTreeSet::new(),
+    };
+    linter.walk(PROGRAM_START, UNKNOWN);
+
+    Report {
+        findings: linter.findings.into_iter().collect(),
+    }
+}
+
+struct Linter<'a> {
+    program: &'a [u8],
+    visited: BTreeSet<u16>,
+    findings: BTreeSet<(u16, u16, Finding)>,
+}
+
+impl Linter<'_> {
+    /// The opcode at an address, if it is inside the program
+    fn fetch(&self, addr: u16) -> Option<u16> {
+        let offset = addr.checked_sub(PROGRAM_START)? as usize;
+        let bytes = self.program.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn byte(&self, addr: u16) -> Option<u8> {
+        let offset = addr.checked_sub(PROGRAM_START)? as usize;
+        self.program.get(offset).copied()
+    }
+
+    fn report(&mut self, addr: u16, opcode: u16, finding: Finding) {
+        self.findings.insert((addr, opcode, finding));
+    }
+
+    /// Follow every path from an address, each instruction once
+    fn walk(&mut self, start: u16, known: Known) {
+        let mut pending = vec![(start, known)];
+
+        while let Some((addr, mut known)) = pending.pop() {
+            if !self.visited.insert(addr) {
+                continue;
+            }
+            let Some(opcode) = self.fetch(addr) else {
+                continue;
+            };
+            let instr = Instruction::from_u16(opcode);
+            let (x, y, n) = (instr.get_nib(1), instr.get_nib(2), instr.get_nib(3));
+            let (nn, nnn) = (instr.get_lbyte(), instr.get_addr());
+            let next = addr + 2;
+
+            if let Some(extension) = extension(opcode) {
+                self.report(addr, opcode, Finding::Extension(extension));
+            }
+
+            match instr.get_nib(0) {
+                // Return: the caller continues where it was
+                0x0 if opcode == 0x00EE => continue,
+                0x1 => {
+                    pending.push((nnn, known));
+                    continue;
+                }
+                // Call: the subroutine could have changed anything
+                0x2 => {
+                    pending.push((next, UNKNOWN));
+                    pending.push((nnn, known));
+                    continue;
+                }
+                // Skips
+                0x3 | 0x4 | 0x5 | 0x9 | 0xE => pending.push((next + 2, known)),
+                0x8 if matches!(n, 0x1..=0x3) && self.vf_read_after(next) => {
+                    self.report(addr, opcode, Finding::Quirk(Quirk::VfReset));
+                }
+                0x8 if matches!(n, 0x6 | 0xE) && x != y => {
+                    self.report(addr, opcode, Finding::Quirk(Quirk::Shift));
+                }
+                0xB => {
+                    if x != 0 {
+                        self.report(addr, opcode, Finding::Quirk(Quirk::Jump));
+                    }
+                    self.report(addr, opcode, Finding::ComputedJump);
+                    continue;
+                }
+                0xD if self.draws_across_edge(known, x, y, n) => {
+                    self.report(addr, opcode, Finding::Quirk(Quirk::Wrap));
+                }
+                0xF if (nn == 0x55 || nn == 0x65) && self.i_used_after(next) => {
+                    self.report(addr, opcode, Finding::Quirk(Quirk::IncrementI));
+                }
+                _ => {}
+            }
+
+            if is_invalid(opcode) {
+                continue;
+            }
+            update_known(&mut known, opcode);
+            pending.push((next, known));
+        }
+    }
+
+    /// Is V(F) read in the straight-line code from `addr`, before being overwritten ?
+    fn vf_read_after(&self, addr: u16) -> bool {
+        self.scan(addr, |opcode| {
+            let (reads, writes) = Instruction::from_u16(opcode).registers();
+            if reads & 1 << 0xF != 0 {
+                Some(true)
+            } else if writes & 1 << 0xF != 0 {
+                Some(false)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Is I used in the straight-line code from `addr`, before being set ?
+    fn i_used_after(&self, addr: u16) -> bool {
+        self.scan(addr, |opcode| match (opcode >> 12, opcode & 0xFF) {
+            (0xD, _) | (0xF, 0x1E | 0x33 | 0x55 | 0x65) => Some(true),
+            (0xA, _) | (0xF, 0x29) => Some(false),
+            _ => None,
+        })
+    }
+
+    /// Look at up to `LOOKAHEAD` instructions until `check` decides,
+    /// or the code stops being straight
+    fn scan(&self, mut addr: u16, check: impl Fn(u16) -> Option<bool>) -> bool {
+        for _ in 0..LOOKAHEAD {
+            let Some(opcode) = self.fetch(addr) else {
+                return false;
+            };
+            if let Some(decided) = check(opcode) {
+                return decided;
+            }
+            if matches!(opcode >> 12, 0x1 | 0x2 | 0xB) || opcode == 0x00EE || is_invalid(opcode) {
+                return false;
+            }
+            addr += 2;
+        }
+        false
+    }
+
+    /// Does a sprite have lit pixels past the right or bottom edge ?
+    /// Without knowing the sprite, any row or column past the edge counts.
+    fn draws_across_edge(&self, known: Known, x: u8, y: u8, n: u8) -> bool {
+        let (Some(vx), Some(vy)) = (known.v[x as usize], known.v[y as usize]) else {
+            return false;
+        };
+        let x = vx as usize % DISPLAY_WIDTH;
+        let y = vy as usize % DISPLAY_HEIGHT;
+
+        (0..n as u16).any(|row| {
+            let bits = match known.i {
+                Some(i) => self
+                    .font_or_program_byte(i.wrapping_add(row))
+                    .unwrap_or(0xFF),
+                None => 0xFF,
+            };
+            (0..8).any(|column| {
+                bits & (0x80 >> column) != 0
+                    && (x + column >= DISPLAY_WIDTH || y + row as usize >= DISPLAY_HEIGHT)
+            })
+        })
+    }
+
+    fn font_or_program_byte(&self, addr: u16) -> Option<u8> {
+        match addr {
+            0..=0x4F => Some(FONTS[addr as usize / 5][addr as usize % 5]),
+            _ => self.byte(addr),
+        }
+    }
+}
+
+/// Track constants loaded into registers and I
+fn update_known(known: &mut Known, opcode: u16) {
+    let instr = Instruction::from_u16(opcode);
+    let (x, nn, nnn) = (
+        instr.get_nib(1) as usize,
+        instr.get_lbyte(),
+        instr.get_addr(),
+    );
+
+    match (instr.get_nib(0), nn) {
+        (0x6, _) => {
+            known.v[x] = Some(nn);
+            return;
+        }
+        (0x7, _) => {
+            known.v[x] = known.v[x].map(|v| v.wrapping_add(nn));
+            return;
+        }
+        (0xA, _) => {
+            known.i = Some(nnn);
+            return;
+        }
+        (0xF, 0x1E) => {
+            known.i = known
+                .i
+                .zip(known.v[x])
+                .map(|(i, v)| i.wrapping_add(v as u16))
+        }
+        (0xF, 0x29) => known.i = known.v[x].map(|v| (v as u16 & 0xF) * 5),
+        // I depends on the quirk
+        (0xF, 0x55 | 0x65) => known.i = None,
+        _ => {}
+    }
+
+    let (_, writes) = instr.registers();
+    for (register, value) in known.v.iter_mut().enumerate() {
+        if writes & 1 << register != 0 {
+            *value = None;
+        }
+    }
+}
+
+/// SUPER-CHIP and XO-CHIP instructions
+fn extension(opcode: u16) -> Option<Extension> {
+    let instr = Instruction::from_u16(opcode);
+    match (instr.get_nib(0), instr.get_nib(3), instr.get_lbyte()) {
+        (0x0, _, _) if opcode & 0xFFF0 == 0x00C0 => Some(Extension::SuperChip),
+        (0x0, _, _) if matches!(opcode, 0x00FB..=0x00FF) => Some(Extension::SuperChip),
+        (0x0, _, _) if opcode & 0xFFF0 == 0x00D0 => Some(Extension::XoChip),
+        (0x5, 0x2 | 0x3, _) => Some(Extension::XoChip),
+        (0xD, 0x0, _) => Some(Extension::SuperChip),
+        (0xF, _, 0x30 | 0x75 | 0x85) => Some(Extension::SuperChip),
+        (0xF, _, 0x01 | 0x02 | 0x3A) => Some(Extension::XoChip),
+        (0xF, _, 0x00) if opcode == 0xF000 => Some(Extension::XoChip),
+        _ => None,
+    }
+}
+
+/// Not an instruction of CHIP-8 or its extensions (probably data)
+fn is_invalid(opcode: u16) -> bool {
+    let instr = Instruction::from_u16(opcode);
+    if extension(opcode).is_some() {
+        return false;
+    }
+    match (instr.get_nib(0), instr.get_nib(3), instr.get_lbyte()) {
+        (0x5 | 0x9, n, _) => n != 0,
+        (0x8, n, _) => !matches!(n, 0x0..=0x7 | 0xE),
+        (0xE, _, nn) => !matches!(nn, 0x9E | 0xA1),
+        (0xF, _, nn) => !matches!(
+            nn,
+            0x07 | 0x0A | 0x15 | 0x18 | 0x1E | 0x29 | 0x33 | 0x55 | 0x65
+        ),
+        _ => false,
+    }
+}
@@ -2,13 +2,30 @@
 //! @AryaveerSR <me.aryaveer@gmail.com>
 
 use chip8::{
-    helpers,
-    structs::{Beeper, BehaviorConfig},
+    compare::Lockstep,
+    config::{Config, Settings},
+    frontend::{
+        self,
+        beeper::Beeper,
+        browser::{self, Browser, Recent},
+        keymap::{HostKey, Keymap},
+        tty::{self, TtyBell, TtyConfig, TtyStyle},
+        viewport::{Grid, MAX_SCALE},
+        window::{self, WindowConfig},
+    },
+    lint,
+    octo::{self, Cartridge},
+    pack::{self, Pack},
+    palette::Palette,
+    phosphor::Persistence,
+    quirks::Quirk,
+    rom::{self, Rom},
+    romdb::{self, Database},
+    structs::BehaviorConfig,
     Chip,
 };
 use gumdrop::Options;
-use minifb::{Key, Scale, Window, WindowOptions};
-use std::{fs, io};
+use std::{fmt, fs, io, path::Path, process};
 
 #[derive(Options)]
 struct ArgOpts {
@@ -18,72 +35,504 @@ struct ArgOpts {
     #[options(help = "Print Help Message")]
     help: bool,
 
-    #[options(help = "Window update rate limit")]
-    rate: Option<u64>,
+    #[options(
+        no_short,
+        meta = "DIR",
+        help = "Directory listed by the ROM browser, when no ROM is given (default: roms)"
+    )]
+    rom_dir: Option<String>,
+
+    #[options(
+        no_short,
+        meta = "PATH",
+        help = "Configuration file (default: $XDG_CONFIG_HOME/chip8/config.toml)"
+    )]
+    config: Option<String>,
+
+    #[options(help = "Instructions executed per 60Hz frame (default: 11)")]
+    ipf: Option<u32>,
+
+    #[options(
+        help = "Colours: classic, green, amber, lcd, or hex like #000000,#FFFFFF",
+        parse(try_from_str = "Palette::parse")
+    )]
+    palette: Option<Palette>,
+
+    #[options(
+        help = "Anti-flicker: off, decay[:0.0-1.0] (brightness kept per frame) or or[:1-8] (frames)",
+        parse(try_from_str = "Persistence::parse")
+    )]
+    persistence: Option<Persistence>,
+
+    #[options(
+        help = "Keyboard layout: qwerty, azerty, qwertz, dvorak, numpad, or a keymap file",
+        parse(try_from_str = "Keymap::from_preset_or_file")
+    )]
+    keymap: Option<Keymap>,
+
+    #[options(
+        no_short,
+        meta = "KEY=HEX",
+        help = "Bind a key to a hex key, on top of the keymap (eg: --bind up=2). Repeatable"
+    )]
+    bind: Vec<String>,
+
+    #[options(help = "Window scale, from 1 to 32 (default: 16). The window can be resized too")]
+    scale: Option<u32>,
+
+    #[options(
+        no_short,
+        help = "Lines between the pixels: off, scanlines or grid",
+        parse(try_from_str = "Grid::parse")
+    )]
+    grid: Option<Grid>,
+
+    #[options(no_short, help = "Start fullscreen (toggled with F11)")]
+    fullscreen: bool,
+
+    #[options(
+        no_short,
+        help = "List the instructions that depend on quirks, and exit without running"
+    )]
+    lint: bool,
+
+    #[options(
+        no_short,
+        help = "Record when the game depends on a quirk, and print a summary when it exits"
+    )]
+    detect_quirks: bool,
+
+    #[options(
+        no_short,
+        meta = "PROFILE",
+        help = "Run side by side with a platform's quirks (eg: superchip) or one quirk flipped (eg: shift)"
+    )]
+    compare: Option<String>,
+
+    #[options(
+        no_short,
+        help = "Show the frame rate, speed and quirks over the display (toggled with Home)"
+    )]
+    overlay: bool,
+
+    #[options(
+        no_short,
+        help = "Show the hex keypad over the window, clickable (toggled with Insert)"
+    )]
+    keypad: bool,
+
+    #[options(help = "Draw in the terminal instead of a window")]
+    tty: bool,
+
+    #[options(
+        no_short,
+        help = "With --tty, draw with braille characters (half the size)"
+    )]
+    braille: bool,
+
+    #[options(
+        no_short,
+        meta = "ID",
+        help = "Platform the ROM is for, setting its quirks (default: from the ROM database or the extension)"
+    )]
+    platform: Option<String>,
 
     #[options(help = "Whether to reset V(F) after 8xy1, 8xy2, and 8xy3 instructions")]
     vf_reset: Option<bool>,
 
     #[options(help = "Whether to increment I on save and load instructions")]
     increment_i: Option<bool>,
+
+    #[options(
+        no_short,
+        help = "Whether 8xy6 and 8xyE shift V(x) in place, ignoring V(y)"
+    )]
+    shift: Option<bool>,
+
+    #[options(
+        no_short,
+        help = "Whether Bnnn jumps to nnn + V(x) instead of nnn + V(0)"
+    )]
+    jump: Option<bool>,
+
+    #[options(
+        no_short,
+        help = "Whether sprites wrap around the screen instead of being clipped"
+    )]
+    wrap: Option<bool>,
+}
+
+/// `--compare`: a platform's quirks, or these ones with a quirk flipped
+fn compare_behavior(name: &str, behavior: BehaviorConfig) -> Option<BehaviorConfig> {
+    if let Some(platform) = BehaviorConfig::for_platform(name) {
+        return Some(platform);
+    }
+
+    let mut flipped = behavior;
+    match name {
+        "vf_reset" | "vf-reset" => flipped.vf_reset = !flipped.vf_reset,
+        "increment_i" | "increment-i" => {
+            flipped.increment_i_on_save_load = !flipped.increment_i_on_save_load
+        }
+        "shift" => flipped.shift = !flipped.shift,
+        "jump" => flipped.jump = !flipped.jump,
+        "wrap" => flipped.wrap = !flipped.wrap,
+        _ => return None,
+    }
+    Some(flipped)
 }
 
-const WIDTH: usize = 64;
-const HEIGHT: usize = 32;
+/// Settings with every quirk set, if there's a `BehaviorConfig`
+fn quirk_settings(behavior: Option<BehaviorConfig>) -> Settings {
+    Settings {
+        vf_reset: behavior.map(|b| b.vf_reset),
+        increment_i: behavior.map(|b| b.increment_i_on_save_load),
+        shift: behavior.map(|b| b.shift),
+        jump: behavior.map(|b| b.jump),
+        wrap: behavior.map(|b| b.wrap),
+        ..Settings::default()
+    }
+}
+
+/// What the ROM database recommends for a ROM
+fn database_settings(rom: &romdb::Rom) -> Settings {
+    Settings {
+        ipf: rom.tickrate,
+        palette: rom
+            .colors
+            .as_ref()
+            .and_then(|colors| Palette::parse(&colors.pixels.join(",")).ok()),
+        ..quirk_settings(rom.behavior())
+    }
+}
+
+/// The options saved in an Octo cartridge
+fn cartridge_settings(options: &octo::Options) -> Settings {
+    Settings {
+        ipf: options.tickrate,
+        palette: options.palette(),
+        vf_reset: options.quirk(Quirk::VfReset),
+        increment_i: options.quirk(Quirk::IncrementI),
+        shift: options.quirk(Quirk::Shift),
+        jump: options.quirk(Quirk::Jump),
+        wrap: options.quirk(Quirk::Wrap),
+        ..Settings::default()
+    }
+}
+
+/// Report something wrong with what was given (eg: a ROM too large), without
+/// a panic's backtrace hint, and exit
+fn exit_with(message: impl fmt::Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
 
 fn main() {
     let args = ArgOpts::parse_args_default_or_exit();
+    let recent_path = Recent::default_path();
+    let mut recent = recent_path
+        .as_ref()
+        .map_or(Ok(Recent::default()), Recent::load)
+        .unwrap_or_else(|err| {
+            eprintln!("Can't read the recently played ROMs, {}", err);
+            Recent::default()
+        });
+
     let mut file_path: String = "".to_owned();
-    if args.free.len() < 1 {
+    if !args.free.is_empty() {
+        file_path = args.free[0].clone();
+    } else if args.tty {
+        // No window to show the browser in
         println!("Enter path to ROM? ");
         io::stdin()
             .read_line(&mut file_path)
             .expect("Cannot read input");
         file_path = file_path.trim().parse().expect("Error parsing filename");
     } else {
-        file_path = args.free[0].clone();
+        let dir = args.rom_dir.as_deref().unwrap_or("roms");
+        // The recently played ROMs can still be picked without any here
+        let files = browser::scan(dir).unwrap_or_else(|err| {
+            eprintln!("Can't list ROMs in {}: {}", dir, err);
+            vec![]
+        });
+        let mut browser = Browser::new(format!("ROMs in {}", dir), files, &recent);
+        match window::browse("Chip 8 Emulator", &mut browser, &Palette::default())
+            .expect("Unable to open the ROM browser")
+        {
+            Some(path) => file_path = path.display().to_string(),
+            None => return,
+        }
     }
 
-    let mut beep = Beeper::new();
+    // Octo cartridges hold the source of the program, assembled here, and its options
+    let cartridge = octo::is_cartridge(&file_path).then(|| {
+        Cartridge::load(&file_path)
+            .unwrap_or_else(|err| exit_with(format!("Can't read {}: {}", file_path, err)))
+    });
 
-    let update_rate = match args.rate {
-        Some(r) => r,
-        None => 75, // Default value. (feels slightly too fast)
+    // A ROM in a zip pack (`pack.zip:game.ch8`), or a file
+    let program = match (&cartridge, pack::split(&file_path)) {
+        (Some(cartridge), _) => cartridge
+            .assemble()
+            .unwrap_or_else(|err| exit_with(format!("Can't assemble {}: {}", file_path, err))),
+        (None, Some((pack_path, name))) => {
+            let mut pack = Pack::open(&pack_path).unwrap_or_else(|err| exit_with(err));
+            let name = match (name, pack.entries()) {
+                (Some(name), _) => name.to_owned(),
+                (None, [only]) => only.name.clone(),
+                (None, entries) => {
+                    println!(
+                        "{} has {} ROMs, run one with {}:NAME",
+                        pack_path.display(),
+                        entries.len(),
+                        pack_path.display()
+                    );
+                    for entry in entries {
+                        println!("  {} ({} bytes)", entry.name, entry.size);
+                    }
+                    return;
+                }
+            };
+            file_path = format!("{}:{}", pack_path.display(), name);
+            pack.read(&name).unwrap_or_else(|err| exit_with(err))
+        }
+        (None, None) => fs::read(&file_path)
+            .unwrap_or_else(|err| exit_with(format!("Can't read {}: {}", file_path, err))),
     };
 
-    let mut buffer: Vec<u32>;
-    let mut opts = WindowOptions::default();
-    opts.scale = Scale::X16;
+    if args.lint {
+        println!("{}", lint::lint(&program));
+        return;
+    }
+
+    let database = Database::bundled();
+    let known = database.lookup(&program);
+    if let Some((info, _)) = known {
+        match info.authors.is_empty() {
+            true => println!("Running {}", info.title),
+            false => println!("Running {} ({})", info.title, info.authors.join(", ")),
+        }
+    }
 
-    let mut window =
-        Window::new("Chip 8 Emulator", WIDTH, HEIGHT, opts).expect("Should create widnow");
-    window.limit_update_rate(Some(std::time::Duration::from_micros(update_rate)));
+    // Platform from the command line, then the ROM database, then Octo for
+    // cartridges or the extension
+    let platform = args
+        .platform
+        .as_deref()
+        .or_else(|| {
+            let (_, rom) = known?;
+            rom.platforms
+                .iter()
+                .map(String::as_str)
+                .find(|platform| BehaviorConfig::for_platform(platform).is_some())
+        })
+        .or_else(|| cartridge.is_some().then_some(octo::PLATFORM))
+        .or_else(|| rom::platform_for(&file_path))
+        .unwrap_or(rom::DEFAULT_PLATFORM);
+    let platform_behavior = args.platform.as_deref().map(|platform| {
+        BehaviorConfig::for_platform(platform).unwrap_or_else(|| {
+            exit_with(format!(
+                "Invalid --platform {}, expected one of {}",
+                platform,
+                BehaviorConfig::PLATFORMS.join(", ")
+            ))
+        })
+    });
+    let rom = Rom::new(program, platform)
+        .unwrap_or_else(|err| exit_with(format!("Can't load {}: {}", file_path, err)));
 
-    let mut behavior = BehaviorConfig::default();
-    if args.increment_i.is_some() {
-        behavior.increment_i_on_save_load = args.increment_i.unwrap();
+    // Packs aren't listed by the browser, so only remember files
+    if let (Some(path), true) = (&recent_path, Path::new(&file_path).is_file()) {
+        recent.push(&file_path);
+        if let Err(err) = recent.save(path) {
+            eprintln!("Can't save the recently played ROMs, {}", err);
+        }
     }
-    if args.vf_reset.is_some() {
-        behavior.vf_reset = args.vf_reset.unwrap();
+
+    // Config file defaults, then the ROM database, then the Octo cartridge,
+    // then the ROM's section of the config file, then the command line
+    let config = match &args.config {
+        Some(path) => Config::load(path),
+        None => Config::default_path().map_or(Ok(Config::default()), Config::load),
     }
+    .unwrap_or_else(|err| exit_with(format!("Invalid config file {}", err)));
+    let settings = config
+        .defaults
+        .clone()
+        .overridden_by(known.map_or_else(Settings::default, |(_, rom)| database_settings(rom)))
+        .overridden_by(cartridge.map_or_else(Settings::default, |cartridge| {
+            cartridge_settings(&cartridge.options)
+        }))
+        .overridden_by(config.rom_settings(rom.bytes()))
+        .overridden_by(quirk_settings(platform_behavior))
+        .overridden_by(Settings {
+            ipf: args.ipf,
+            vf_reset: args.vf_reset,
+            increment_i: args.increment_i,
+            palette: args.palette,
+            persistence: args.persistence,
+            keymap: args.keymap,
+            scale: args.scale,
+            grid: args.grid,
+            fullscreen: args.fullscreen.then_some(true),
+            shift: args.shift,
+            jump: args.jump,
+            wrap: args.wrap,
+        });
 
-    let mut chip = Chip::new(fs::read(&file_path).unwrap(), behavior);
+    let mut behavior = rom.behavior();
+    if let Some(increment_i) = settings.increment_i {
+        behavior.increment_i_on_save_load = increment_i;
+    }
+    if let Some(vf_reset) = settings.vf_reset {
+        behavior.vf_reset = vf_reset;
+    }
+    if let Some(shift) = settings.shift {
+        behavior.shift = shift;
+    }
+    if let Some(jump) = settings.jump {
+        behavior.jump = jump;
+    }
+    if let Some(wrap) = settings.wrap {
+        behavior.wrap = wrap;
+    }
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        let (display_update, is_playing_sound) =
-            chip.process_instruction(helpers::keys_to_u8(window.get_keys()));
-        beep.update(is_playing_sound);
-        if display_update {
-            buffer = chip
-                .display()
-                .iter()
-                .flatten()
-                .map(|x| if *x { 0x00FFFFFF } else { 0x0000 })
-                .collect();
+    let ipf = settings
+        .ipf
+        .unwrap_or(frontend::DEFAULT_INSTRUCTIONS_PER_FRAME);
+    let palette = settings.palette.unwrap_or_default();
+    let persistence = settings.persistence.unwrap_or_default();
 
-            window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
-        } else {
-            window.update();
+    let mut keymap = settings.keymap.unwrap_or_default();
+    // The database's key hints go on the arrow keys, space and enter
+    if let Some((_, rom)) = known {
+        for (hint, hex) in &rom.keys {
+            let key = match hint.as_str() {
+                "a" => Some(HostKey::Space),
+                "b" => Some(HostKey::Enter),
+                direction => HostKey::from_name(direction).filter(|key| {
+                    matches!(
+                        key,
+                        HostKey::Up | HostKey::Down | HostKey::Left | HostKey::Right
+                    )
+                }),
+            };
+            if let (Some(key), true) = (key, *hex <= 0xF) {
+                keymap.bind(key, *hex);
+            }
         }
     }
+    if let Err(err) = keymap.apply_rom_overrides(&file_path) {
+        eprintln!("Ignoring the ROM's keymap, {}", err);
+    }
+    for binding in &args.bind {
+        keymap
+            .apply(binding)
+            .unwrap_or_else(|err| exit_with(format!("Invalid --bind {}: {}", binding, err)));
+    }
+
+    let scale = settings.scale.unwrap_or(16) as usize;
+    if !(1..=MAX_SCALE).contains(&scale) {
+        exit_with(format!(
+            "Invalid scale {}, expected 1 to {}",
+            scale, MAX_SCALE
+        ));
+    }
+    let grid = settings.grid.unwrap_or_default();
+    let fullscreen = settings.fullscreen.unwrap_or(false);
+    let title = match known {
+        Some((info, _)) => format!("{} - Chip 8 Emulator", info.title),
+        None => "Chip 8 Emulator".to_owned(),
+    };
+
+    if let Some(name) = &args.compare {
+        let other = compare_behavior(name, behavior).unwrap_or_else(|| {
+            exit_with(format!(
+                "Invalid --compare {}, expected a platform ({}) or a quirk",
+                name,
+                BehaviorConfig::PLATFORMS.join(", ")
+            ))
+        });
+        let mut lockstep = Lockstep::new(rom.bytes(), behavior, other);
+
+        let window_config = WindowConfig {
+            palette,
+            keymap,
+            scale,
+            grid,
+            fullscreen,
+            ..WindowConfig::default()
+        };
+        let (mut renderer, mut input) = window::open_side_by_side(
+            &format!("{} (left) vs {} (right)", title, name),
+            window_config,
+        )
+        .expect("Should create window");
+
+        frontend::run_compare(
+            &mut lockstep,
+            ipf,
+            &mut renderer,
+            &mut Beeper::new(),
+            &mut input,
+        );
+
+        match lockstep.divergence() {
+            Some(divergence) => println!("Diverged at {}", divergence),
+            None => println!("No divergence in {} frames", lockstep.frame()),
+        }
+        return;
+    }
+
+    let mut chip = Chip::new(rom.bytes(), behavior);
+    if args.detect_quirks {
+        chip.detect_quirks();
+    }
+
+    if args.tty {
+        let tty_config = TtyConfig {
+            palette,
+            persistence,
+            style: if args.braille {
+                TtyStyle::Braille
+            } else {
+                TtyStyle::HalfBlock
+            },
+            keymap,
+            overlay: args.overlay,
+            ..TtyConfig::default()
+        };
+        let (mut renderer, mut input) = tty::open(tty_config)
+            .unwrap_or_else(|err| exit_with(format!("Can't use the terminal: {}", err)));
+
+        frontend::run(
+            &mut chip,
+            ipf,
+            &mut renderer,
+            &mut TtyBell::default(),
+            &mut input,
+        );
+    } else {
+        let mut beep = Beeper::new();
+
+        let window_config = WindowConfig {
+            palette,
+            persistence,
+            keymap,
+            scale,
+            grid,
+            fullscreen,
+            overlay: args.overlay,
+            keypad: args.keypad,
+            ..WindowConfig::default()
+        };
+        let (mut renderer, mut input) =
+            window::open(&title, window_config).expect("Should create widnow");
+
+        frontend::run(&mut chip, ipf, &mut renderer, &mut beep, &mut input);
+    }
+
+    if let Some(usage) = chip.quirk_usage() {
+        println!("{}", usage);
+    }
 }
@@ -0,0 +1,162 @@
+//! Octo cartridges
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Octo (https://github.com/JohnEarnest/Octo) shares programs as GIF
+//! "cartridges": a label image whose pixels also carry a JSON payload, two
+//! bits in the bottom of each palette index. The payload is the program's
+//! Octo *source* and its options (speed, quirks, colours).
+//!
+//! `assembler` turns the source into the ROM, so a cartridge runs on its own.
+
+pub mod assembler;
+
+use crate::{palette::Palette, quirks::Quirk};
+use serde::Deserialize;
+use std::{error::Error, fmt, fs, path::Path};
+
+/// The platform cartridges run as. Octo's default quirks are XO-CHIP's, and
+/// the cartridge's options change them.
+pub const PLATFORM: &str = "xochip";
+
+/// What a cartridge holds
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Cartridge {
+    /// Octo source code
+    #[serde(default)]
+    pub program: String,
+    #[serde(default)]
+    pub options: Options,
+}
+
+/// Octo's options, with the names it saves them under.
+/// `None` means the cartridge doesn't say.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Options {
+    /// Instructions per frame
+    pub tickrate: Option<u32>,
+    /// Colours as `#RRGGBB`: the first plane, the second, both, and neither
+    pub fill_color: Option<String>,
+    pub fill_color2: Option<String>,
+    pub blend_color: Option<String>,
+    pub background_color: Option<String>,
+    pub buzz_color: Option<String>,
+    pub quiet_color: Option<String>,
+    /// 8xy6/8xyE shift Vx in place
+    pub shift_quirks: Option<bool>,
+    /// Fx55/Fx65 leave I unchanged
+    pub load_store_quirks: Option<bool>,
+    /// Sprites are clipped at the edges instead of wrapping
+    pub clip_quirks: Option<bool>,
+    /// Bnnn jumps to nnn + Vx
+    pub jump_quirks: Option<bool>,
+    /// 8xy1/8xy2/8xy3 reset VF
+    pub logic_quirks: Option<bool>,
+}
+
+impl Options {
+    /// What the cartridge sets a quirk's flag (see `Quirk::flag`) to, if it says
+    pub fn quirk(&self, quirk: Quirk) -> Option<bool> {
+        match quirk {
+            Quirk::VfReset => self.logic_quirks,
+            Quirk::IncrementI => self.load_store_quirks.map(|leave_i| !leave_i),
+            Quirk::Shift => self.shift_quirks,
+            Quirk::Jump => self.jump_quirks,
+            Quirk::Wrap => self.clip_quirks.map(|clip| !clip),
+        }
+    }
+
+    /// The cartridge's colours, if it has at least a background and a foreground
+    pub fn palette(&self) -> Option<Palette> {
+        let background = self.background_color.as_ref()?;
+        let foreground = self.fill_color.as_ref()?;
+        let colors = match (&self.fill_color2, &self.blend_color) {
+            (Some(second), Some(both)) => {
+                format!("{},{},{},{}", background, foreground, second, both)
+            }
+            (Some(second), None) => format!("{},{},{}", background, foreground, second),
+            _ => format!("{},{}", background, foreground),
+        };
+        Palette::parse(&colors).ok()
+    }
+}
+
+impl Cartridge {
+    /// Decode the payload of a cartridge GIF
+    pub fn decode(gif: &[u8]) -> Result<Self, OctoError> {
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(gif)
+            .map_err(|err| OctoError::Gif(err.to_string()))?;
+
+        // Each pixel gives two bits, most significant first
+        let mut bytes = Vec::new();
+        let (mut byte, mut bits) = (0u8, 0);
+        while let Some(frame) = decoder
+            .read_next_frame()
+            .map_err(|err| OctoError::Gif(err.to_string()))?
+        {
+            for index in frame.buffer.iter() {
+                byte = (byte << 2) | (index & 3);
+                bits += 2;
+                if bits == 8 {
+                    bytes.push(byte);
+                    (byte, bits) = (0, 0);
+                }
+            }
+        }
+
+        // A big-endian length, then the JSON
+        let (length, payload) = bytes
+            .split_first_chunk::<4>()
+            .ok_or(OctoError::NotACartridge)?;
+        let json = payload
+            .get(..u32::from_be_bytes(*length) as usize)
+            .ok_or(OctoError::NotACartridge)?;
+        serde_json::from_slice(json).map_err(|_| OctoError::NotACartridge)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, OctoError> {
+        let path = path.as_ref();
+        let gif = fs::read(path)
+            .map_err(|err| OctoError::Io(format!("can't read {}: {}", path.display(), err)))?;
+        Self::decode(&gif)
+    }
+
+    /// Assemble the program into a ROM
+    pub fn assemble(&self) -> Result<Vec<u8>, OctoError> {
+        assembler::assemble(&self.program)
+    }
+}
+
+/// Whether a path looks like a cartridge
+pub fn is_cartridge(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"))
+}
+
+/// A cartridge that can't be read or assembled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OctoError {
+    /// The file couldn't be read
+    Io(String),
+    /// The file isn't a valid GIF
+    Gif(String),
+    /// The GIF doesn't hold an Octo payload
+    NotACartridge,
+    /// The program isn't valid Octo, with the line it's on
+    Compile(String),
+}
+
+impl fmt::Display for OctoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OctoError::Io(reason) => write!(f, "{}", reason),
+            OctoError::Gif(reason) => write!(f, "invalid GIF: {}", reason),
+            OctoError::NotACartridge => write!(f, "not an Octo cartridge"),
+            OctoError::Compile(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl Error for OctoError {}
@@ -0,0 +1,1105 @@
+//! Octo assembler
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Turns Octo source (https://johnearnest.github.io/Octo/docs/Manual.html)
+//! into a ROM, so a cartridge runs from the program it holds. It covers the
+//! whole language: labels, `:const`, `:alias`, structured `if`/`loop`,
+//! macros, `:calc` expressions and string modes, and the SUPER-CHIP and
+//! XO-CHIP instructions (which may not run on this emulator's core).
+
+use super::OctoError;
+use std::{
+    collections::{HashMap, VecDeque},
+    f64::consts,
+    fmt, mem,
+};
+
+/// Where programs are loaded
+const START: u32 = 0x200;
+
+/// Octo's names for the keys of the keypad (`OCTO_KEY_W`), after the keyboard
+/// keys they're on
+const KEYS: [(&str, u8); 16] = [
+    ("1", 0x1),
+    ("2", 0x2),
+    ("3", 0x3),
+    ("4", 0xC),
+    ("Q", 0x4),
+    ("W", 0x5),
+    ("E", 0x6),
+    ("R", 0xD),
+    ("A", 0x7),
+    ("S", 0x8),
+    ("D", 0x9),
+    ("F", 0xE),
+    ("Z", 0xA),
+    ("X", 0x0),
+    ("C", 0xB),
+    ("V", 0xF),
+];
+
+/// Assemble Octo source into a ROM, loaded at 0x200
+pub fn assemble(source: &str) -> Result<Vec<u8>, OctoError> {
+    Assembler::new(tokenize(source)?).run()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    text: String,
+    line: usize,
+    /// Whether it was a `"string"`, which is never a keyword or a name
+    string: bool,
+}
+
+/// Split source into whitespace separated tokens and strings, dropping `#` comments
+fn tokenize(source: &str) -> Result<VecDeque<Token>, OctoError> {
+    let mut tokens = VecDeque::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '#' => while chars.next_if(|c| *c != '\n').is_some() {},
+            '"' => {
+                let start = line;
+                let mut text = String::new();
+                loop {
+                    let c = match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some('v') => '\x0B',
+                            Some('0') => '\0',
+                            Some(c @ ('\\' | '"')) => c,
+                            _ => return Err(compile_error(line, "invalid escape in a string")),
+                        },
+                        Some(c) => c,
+                        None => return Err(compile_error(start, "unterminated string")),
+                    };
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    text.push(c);
+                }
+                tokens.push_back(Token {
+                    text,
+                    line: start,
+                    string: true,
+                });
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut text = String::from(c);
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    text.push(c);
+                }
+                tokens.push_back(Token {
+                    text,
+                    line,
+                    string: false,
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn compile_error(line: usize, reason: impl fmt::Display) -> OctoError {
+    OctoError::Compile(format!("line {}: {}", line, reason))
+}
+
+/// Decimal, `0x` hex or `0b` binary, maybe negative
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let (digits, radix) = if let Some(hex) = digits.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        (binary, 2)
+    } else {
+        (digits, 10)
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    let value = i64::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// How an address is written into the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Patch {
+    /// In the low 12 bits of an instruction (eg: `jump`)
+    Address,
+    /// As two bytes (eg: `:pointer`)
+    Long,
+    /// Split between the two `vx := nn` of `:unpack`
+    Unpack { long: bool },
+}
+
+impl Patch {
+    fn max(&self) -> u32 {
+        match self {
+            Patch::Address | Patch::Unpack { long: false } => 0xFFF,
+            Patch::Long | Patch::Unpack { long: true } => 0xFFFF,
+        }
+    }
+}
+
+/// An address that's known, or a label that may be defined later
+enum Target {
+    Known(u32),
+    Label(String),
+}
+
+/// A use of a label that wasn't defined yet
+struct Fixup {
+    name: String,
+    line: usize,
+    at: u32,
+    patch: Patch,
+}
+
+/// An `if ... begin` or a `loop` that isn't closed yet
+enum Block {
+    /// With the jump past its body, patched by `else` or `end`
+    If { jump: u32, line: usize },
+    /// With the jumps out of it from `while`, patched by `again`
+    Loop {
+        start: u32,
+        breaks: Vec<u32>,
+        line: usize,
+    },
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+    calls: usize,
+}
+
+/// The body of a string mode for each character, with its index in the alphabet
+type StringMode = HashMap<char, (usize, Vec<Token>)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(u8),
+    Byte(u8),
+}
+
+/// What follows `if` and `while`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Condition {
+    Key {
+        x: u8,
+        pressed: bool,
+    },
+    Compare {
+        x: u8,
+        comparison: Comparison,
+        rhs: Operand,
+    },
+}
+
+impl Condition {
+    fn negated(self) -> Self {
+        match self {
+            Condition::Key { x, pressed } => Condition::Key {
+                x,
+                pressed: !pressed,
+            },
+            Condition::Compare { x, comparison, rhs } => Condition::Compare {
+                x,
+                comparison: match comparison {
+                    Comparison::Equal => Comparison::NotEqual,
+                    Comparison::NotEqual => Comparison::Equal,
+                    Comparison::Less => Comparison::GreaterEqual,
+                    Comparison::GreaterEqual => Comparison::Less,
+                    Comparison::Greater => Comparison::LessEqual,
+                    Comparison::LessEqual => Comparison::Greater,
+                },
+                rhs,
+            },
+        }
+    }
+}
+
+struct Assembler {
+    tokens: VecDeque<Token>,
+    /// Line of the last token, for errors
+    line: usize,
+    /// Bytes from 0x200
+    rom: Vec<u8>,
+    here: u32,
+    /// Whether 0x200 holds a jump to `main`, which is left out when `main` comes first
+    main_jump: bool,
+    labels: HashMap<String, u32>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    string_modes: HashMap<String, StringMode>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+}
+
+impl Assembler {
+    fn new(tokens: VecDeque<Token>) -> Self {
+        let constants = KEYS
+            .iter()
+            .map(|(key, hex)| (format!("OCTO_KEY_{}", key), *hex as f64))
+            .collect();
+        // `:unpack` sets these
+        let aliases = [("unpack-hi".to_owned(), 0), ("unpack-lo".to_owned(), 1)].into();
+        Assembler {
+            tokens,
+            line: 1,
+            rom: Vec::new(),
+            here: START,
+            main_jump: true,
+            labels: HashMap::new(),
+            constants,
+            aliases,
+            macros: HashMap::new(),
+            string_modes: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    fn run(mut self) -> Result<Vec<u8>, OctoError> {
+        self.instruction(0x1000)?;
+        while !self.tokens.is_empty() {
+            self.statement()?;
+        }
+
+        if let Some(Block::If { line, .. } | Block::Loop { line, .. }) = self.blocks.last() {
+            return Err(compile_error(*line, "this block is never closed"));
+        }
+        if !self.labels.contains_key("main") {
+            return Err(self.error("the program has no main label"));
+        }
+        if self.main_jump {
+            self.fixups.push(Fixup {
+                name: "main".to_owned(),
+                line: self.line,
+                at: START,
+                patch: Patch::Address,
+            });
+        }
+        for fixup in mem::take(&mut self.fixups) {
+            let address = *self.labels.get(&fixup.name).ok_or_else(|| {
+                compile_error(fixup.line, format!("undefined name {}", fixup.name))
+            })?;
+            if address > fixup.patch.max() {
+                return Err(compile_error(
+                    fixup.line,
+                    format!("{} ({:#X}) is too far to reach", fixup.name, address),
+                ));
+            }
+            self.patch(fixup.at, fixup.patch, address);
+        }
+        Ok(self.rom)
+    }
+
+    fn error(&self, reason: impl fmt::Display) -> OctoError {
+        compile_error(self.line, reason)
+    }
+
+    fn next(&mut self) -> Result<Token, OctoError> {
+        let token = self
+            .tokens
+            .pop_front()
+            .ok_or_else(|| self.error("unexpected end of the program"))?;
+        self.line = token.line;
+        Ok(token)
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.tokens
+            .front()
+            .is_some_and(|token| !token.string && token.text == text)
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), OctoError> {
+        let token = self.next()?;
+        match !token.string && token.text == text {
+            true => Ok(()),
+            false => Err(self.error(format!("expected {}, got {}", text, token.text))),
+        }
+    }
+
+    /// A name being defined
+    fn name(&mut self) -> Result<String, OctoError> {
+        let token = self.next()?;
+        let reserved = token.string
+            || parse_number(&token.text).is_some()
+            || Self::raw_register(&token.text).is_some()
+            || ["{", "}", "(", ")"].contains(&token.text.as_str());
+        match reserved {
+            true => Err(self.error(format!("{} can't be a name", token.text))),
+            false => Ok(token.text),
+        }
+    }
+
+    /// `v0` to `vF`
+    fn raw_register(text: &str) -> Option<u8> {
+        let digit = text.strip_prefix(['v', 'V'])?;
+        match digit.len() {
+            1 => u8::from_str_radix(digit, 16).ok(),
+            _ => None,
+        }
+    }
+
+    fn register_of(&self, token: &Token) -> Option<u8> {
+        match token.string {
+            true => None,
+            false => {
+                (self.aliases.get(&token.text).copied()).or_else(|| Self::raw_register(&token.text))
+            }
+        }
+    }
+
+    fn register(&mut self) -> Result<u8, OctoError> {
+        let token = self.next()?;
+        self.register_of(&token)
+            .ok_or_else(|| self.error(format!("expected a register, got {}", token.text)))
+    }
+
+    /// A number, a constant or a `{ calc expression }`
+    fn number_of(&mut self, token: &Token) -> Result<i64, OctoError> {
+        if !token.string && token.text == "{" {
+            return Ok(self.calc()?.floor() as i64);
+        }
+        let value = match token.string {
+            true => None,
+            false => parse_number(&token.text)
+                .or_else(|| Some(self.constants.get(&token.text)?.floor() as i64)),
+        };
+        value.ok_or_else(|| self.error(format!("expected a number, got {}", token.text)))
+    }
+
+    fn ranged_of(&mut self, token: &Token, min: i64, max: i64) -> Result<i64, OctoError> {
+        let value = self.number_of(token)?;
+        match (min..=max).contains(&value) {
+            true => Ok(value),
+            false => Err(self.error(format!(
+                "{} is out of range, expected {} to {}",
+                value, min, max
+            ))),
+        }
+    }
+
+    /// -128 to 255, wrapped to a byte
+    fn byte_of(&mut self, token: &Token) -> Result<u8, OctoError> {
+        Ok(self.ranged_of(token, -128, 255)? as u8)
+    }
+
+    fn byte(&mut self) -> Result<u8, OctoError> {
+        let token = self.next()?;
+        self.byte_of(&token)
+    }
+
+    fn nibble(&mut self) -> Result<u16, OctoError> {
+        let token = self.next()?;
+        Ok(self.ranged_of(&token, 0, 0xF)? as u16)
+    }
+
+    fn address(&mut self) -> Result<Target, OctoError> {
+        let token = self.next()?;
+        if let Some(address) = self.labels.get(&token.text).filter(|_| !token.string) {
+            return Ok(Target::Known(*address));
+        }
+        let label = !token.string
+            && token.text != "{"
+            && parse_number(&token.text).is_none()
+            && !self.constants.contains_key(&token.text)
+            && self.register_of(&token).is_none();
+        match label {
+            true => Ok(Target::Label(token.text)),
+            false => Ok(Target::Known(self.ranged_of(&token, 0, 0xFFFF)? as u32)),
+        }
+    }
+
+    fn emit(&mut self, byte: u8) -> Result<(), OctoError> {
+        if !(START..=0xFFFF).contains(&self.here) {
+            return Err(self.error(format!("{:#X} is outside of memory", self.here)));
+        }
+        let index = (self.here - START) as usize;
+        if index >= self.rom.len() {
+            self.rom.resize(index + 1, 0);
+        }
+        self.rom[index] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn instruction(&mut self, opcode: u16) -> Result<(), OctoError> {
+        let [high, low] = opcode.to_be_bytes();
+        self.emit(high)?;
+        self.emit(low)
+    }
+
+    /// The address to write now, 0 if it's written once its label is defined
+    fn resolve(&mut self, target: Target, patch: Patch) -> Result<u32, OctoError> {
+        match target {
+            Target::Known(address) if address <= patch.max() => Ok(address),
+            Target::Known(address) => {
+                Err(self.error(format!("{:#X} is too far to reach", address)))
+            }
+            Target::Label(name) => {
+                self.fixups.push(Fixup {
+                    name,
+                    line: self.line,
+                    at: self.here,
+                    patch,
+                });
+                Ok(0)
+            }
+        }
+    }
+
+    /// An instruction with an address in its low 12 bits (eg: `1nnn`)
+    fn with_address(&mut self, opcode: u16, target: Target) -> Result<(), OctoError> {
+        let address = self.resolve(target, Patch::Address)?;
+        self.instruction(opcode | address as u16)
+    }
+
+    fn patch(&mut self, at: u32, patch: Patch, address: u32) {
+        let index = (at - START) as usize;
+        match patch {
+            Patch::Address => {
+                self.rom[index] |= (address >> 8) as u8;
+                self.rom[index + 1] = address as u8;
+            }
+            Patch::Long => {
+                self.rom[index] = (address >> 8) as u8;
+                self.rom[index + 1] = address as u8;
+            }
+            Patch::Unpack { .. } => {
+                self.rom[index + 1] |= (address >> 8) as u8;
+                self.rom[index + 3] = address as u8;
+            }
+        }
+    }
+
+    fn define(&mut self, name: String, address: u32) -> Result<(), OctoError> {
+        if self.labels.contains_key(&name) || self.constants.contains_key(&name) {
+            return Err(self.error(format!("{} is already defined", name)));
+        }
+        self.labels.insert(name, address);
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), OctoError> {
+        let token = self.next()?;
+        if token.string {
+            return Err(self.error(format!("unexpected string \"{}\"", token.text)));
+        }
+        if let Some(x) = self.register_of(&token) {
+            return self.assignment(x);
+        }
+        if parse_number(&token.text).is_some() {
+            let byte = self.byte_of(&token)?;
+            return self.emit(byte);
+        }
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.name()?;
+                if name == "main" && self.main_jump && self.here == START + 2 {
+                    self.rom.clear();
+                    self.here = START;
+                    self.main_jump = false;
+                }
+                self.define(name, self.here)
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.define(name, self.here + 1)
+            }
+            ":const" => {
+                let name = self.name()?;
+                let token = self.next()?;
+                let value = self.number_of(&token)?;
+                self.constants.insert(name, value as f64);
+                Ok(())
+            }
+            ":calc" => {
+                let name = self.name()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.constants.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let x = self.register()?;
+                self.aliases.insert(name, x);
+                Ok(())
+            }
+            ":unpack" => {
+                let long = self.peek_is("long");
+                let nibble = match long {
+                    true => {
+                        self.next()?;
+                        0
+                    }
+                    false => self.nibble()?,
+                };
+                let target = self.address()?;
+                let address = self.resolve(target, Patch::Unpack { long })? as u16;
+                let (high, low) = (self.aliases["unpack-hi"], self.aliases["unpack-lo"]);
+                self.instruction(0x6000 | (high as u16) << 8 | nibble << 4 | address >> 8)?;
+                self.instruction(0x6000 | (low as u16) << 8 | (address & 0xFF))
+            }
+            ":org" => {
+                let token = self.next()?;
+                self.here = self.ranged_of(&token, START as i64, 0xFFFF)? as u32;
+                Ok(())
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit(byte)
+            }
+            ":call" => {
+                let target = self.address()?;
+                self.with_address(0x2000, target)
+            }
+            ":pointer" => {
+                let target = self.address()?;
+                let address = self.resolve(target, Patch::Long)?;
+                self.instruction(address as u16)
+            }
+            ":macro" => self.define_macro(),
+            ":stringmode" => self.define_string_mode(),
+            ":assert" => {
+                let message = match self.tokens.front() {
+                    Some(token) if token.string => Some(self.next()?.text),
+                    _ => None,
+                };
+                self.expect("{")?;
+                match self.calc()? != 0.0 {
+                    true => Ok(()),
+                    false => Err(self.error(message.as_deref().unwrap_or("assertion failed"))),
+                }
+            }
+            // Debugger hints
+            ":breakpoint" | ":proto" => self.next().map(drop),
+            ":monitor" => self.next().and_then(|_| self.next()).map(drop),
+
+            "return" | ";" => self.instruction(0x00EE),
+            "clear" => self.instruction(0x00E0),
+            "scroll-right" => self.instruction(0x00FB),
+            "scroll-left" => self.instruction(0x00FC),
+            "exit" => self.instruction(0x00FD),
+            "lores" => self.instruction(0x00FE),
+            "hires" => self.instruction(0x00FF),
+            "audio" => self.instruction(0xF002),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.instruction(0x00C0 | n)
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.instruction(0x00D0 | n)
+            }
+            "plane" => {
+                let n = self.nibble()?;
+                self.instruction(0xF001 | n << 8)
+            }
+            "bcd" | "saveflags" | "loadflags" => {
+                let x = self.register()? as u16;
+                let low = match token.text.as_str() {
+                    "bcd" => 0x33,
+                    "saveflags" => 0x75,
+                    _ => 0x85,
+                };
+                self.instruction(0xF000 | x << 8 | low)
+            }
+            "save" | "load" => {
+                let save = token.text == "save";
+                let x = self.register()? as u16;
+                match self.peek_is("-") {
+                    true => {
+                        self.next()?;
+                        let y = self.register()? as u16;
+                        self.instruction(0x5000 | x << 8 | y << 4 | if save { 2 } else { 3 })
+                    }
+                    false => self.instruction(0xF000 | x << 8 | if save { 0x55 } else { 0x65 }),
+                }
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble()?;
+                self.instruction(0xD000 | x << 8 | y << 4 | n)
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()? as u16;
+                let low = match token.text.as_str() {
+                    "delay" => 0x15,
+                    "buzzer" => 0x18,
+                    _ => 0x3A,
+                };
+                self.instruction(0xF000 | x << 8 | low)
+            }
+            "jump" | "jump0" | "native" => {
+                let target = self.address()?;
+                let opcode = match token.text.as_str() {
+                    "jump" => 0x1000,
+                    "jump0" => 0xB000,
+                    _ => 0x0000,
+                };
+                self.with_address(opcode, target)
+            }
+            "i" => self.index(),
+
+            "if" => {
+                let condition = self.condition()?;
+                let token = self.next()?;
+                match token.text.as_str() {
+                    "then" if !token.string => self.skip_unless(condition),
+                    "begin" if !token.string => {
+                        self.skip_unless(condition.negated())?;
+                        self.blocks.push(Block::If {
+                            jump: self.here,
+                            line: self.line,
+                        });
+                        self.instruction(0x1000)
+                    }
+                    _ => Err(self.error(format!("expected then or begin, got {}", token.text))),
+                }
+            }
+            "else" | "end" => {
+                let Some(Block::If { jump, .. }) = self.blocks.pop() else {
+                    return Err(self.error(format!("{} without an if ... begin", token.text)));
+                };
+                if token.text == "else" {
+                    self.blocks.push(Block::If {
+                        jump: self.here,
+                        line: self.line,
+                    });
+                    self.instruction(0x1000)?;
+                }
+                self.jump_here(jump)
+            }
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    start: self.here,
+                    breaks: Vec::new(),
+                    line: self.line,
+                });
+                Ok(())
+            }
+            "while" => {
+                let condition = self.condition()?;
+                self.skip_unless(condition.negated())?;
+                let jump = self.here;
+                self.instruction(0x1000)?;
+                let breaks = self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { breaks, .. } => Some(breaks),
+                    Block::If { .. } => None,
+                });
+                match breaks {
+                    Some(breaks) => {
+                        breaks.push(jump);
+                        Ok(())
+                    }
+                    None => Err(self.error("while outside of a loop")),
+                }
+            }
+            "again" => {
+                let Some(Block::Loop { start, breaks, .. }) = self.blocks.pop() else {
+                    return Err(self.error("again without a loop"));
+                };
+                self.with_address(0x1000, Target::Known(start))?;
+                for jump in breaks {
+                    self.jump_here(jump)?;
+                }
+                Ok(())
+            }
+
+            name if self.macros.contains_key(name) => self.expand_macro(name),
+            name if self.string_modes.contains_key(name) => self.expand_string(name),
+            name if self.constants.contains_key(name) => {
+                let byte = self.byte_of(&token)?;
+                self.emit(byte)
+            }
+            // A bare label calls it
+            _ => {
+                let target = match self.labels.get(&token.text) {
+                    Some(address) => Target::Known(*address),
+                    None => Target::Label(token.text),
+                };
+                self.with_address(0x2000, target)
+            }
+        }
+    }
+
+    /// Point the jump at `at` to the current address
+    fn jump_here(&mut self, at: u32) -> Result<(), OctoError> {
+        if self.here > Patch::Address.max() {
+            return Err(self.error(format!("{:#X} is too far to reach", self.here)));
+        }
+        self.patch(at, Patch::Address, self.here);
+        Ok(())
+    }
+
+    /// `vx := ...`, `vx += ...` and the like
+    fn assignment(&mut self, x: u8) -> Result<(), OctoError> {
+        let x = (x as u16) << 8;
+        let operator = self.next()?;
+        let rhs = self.next()?;
+        let y = self.register_of(&rhs).map(|y| (y as u16) << 4);
+
+        let opcode = match (operator.text.as_str(), y) {
+            (":=", Some(y)) => 0x8000 | x | y,
+            (":=", None) => match rhs.text.as_str() {
+                "random" if !rhs.string => 0xC000 | x | self.byte()? as u16,
+                "key" if !rhs.string => 0xF00A | x,
+                "delay" if !rhs.string => 0xF007 | x,
+                _ => 0x6000 | x | self.byte_of(&rhs)? as u16,
+            },
+            ("+=", Some(y)) => 0x8004 | x | y,
+            ("+=", None) => 0x7000 | x | self.byte_of(&rhs)? as u16,
+            ("-=", Some(y)) => 0x8005 | x | y,
+            ("-=", None) => 0x7000 | x | self.byte_of(&rhs)?.wrapping_neg() as u16,
+            ("|=", Some(y)) => 0x8001 | x | y,
+            ("&=", Some(y)) => 0x8002 | x | y,
+            ("^=", Some(y)) => 0x8003 | x | y,
+            ("=-", Some(y)) => 0x8007 | x | y,
+            (">>=", Some(y)) => 0x8006 | x | y,
+            ("<<=", Some(y)) => 0x800E | x | y,
+            (_, None)
+                if ["|=", "&=", "^=", "=-", ">>=", "<<="].contains(&operator.text.as_str()) =>
+            {
+                return Err(self.error(format!("expected a register, got {}", rhs.text)))
+            }
+            _ => return Err(self.error(format!("unknown operator {}", operator.text))),
+        };
+        self.instruction(opcode)
+    }
+
+    /// `i := ...` and `i += vx`
+    fn index(&mut self) -> Result<(), OctoError> {
+        let operator = self.next()?;
+        match operator.text.as_str() {
+            ":=" if self.peek_is("long") => {
+                self.next()?;
+                let target = self.address()?;
+                self.instruction(0xF000)?;
+                let address = self.resolve(target, Patch::Long)?;
+                self.instruction(address as u16)
+            }
+            ":=" if self.peek_is("hex") || self.peek_is("bighex") => {
+                let low = if self.next()?.text == "hex" {
+                    0x29
+                } else {
+                    0x30
+                };
+                let x = self.register()? as u16;
+                self.instruction(0xF000 | x << 8 | low)
+            }
+            ":=" => {
+                let target = self.address()?;
+                self.with_address(0xA000, target)
+            }
+            "+=" => {
+                let x = self.register()? as u16;
+                self.instruction(0xF01E | x << 8)
+            }
+            _ => Err(self.error(format!("unknown operator {}", operator.text))),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Condition, OctoError> {
+        let x = self.register()?;
+        let operator = self.next()?;
+        let comparison = match operator.text.as_str() {
+            "key" => return Ok(Condition::Key { x, pressed: true }),
+            "-key" => return Ok(Condition::Key { x, pressed: false }),
+            "==" => Comparison::Equal,
+            "!=" => Comparison::NotEqual,
+            "<" => Comparison::Less,
+            ">" => Comparison::Greater,
+            "<=" => Comparison::LessEqual,
+            ">=" => Comparison::GreaterEqual,
+            _ => return Err(self.error(format!("unknown comparison {}", operator.text))),
+        };
+        let token = self.next()?;
+        let rhs = match self.register_of(&token) {
+            Some(y) => Operand::Register(y),
+            None => Operand::Byte(self.byte_of(&token)?),
+        };
+        Ok(Condition::Compare { x, comparison, rhs })
+    }
+
+    /// What skips the next instruction when `condition` is false. `<`, `>`,
+    /// `<=` and `>=` subtract into vF and test the borrow, so they change vF.
+    fn skip_unless(&mut self, condition: Condition) -> Result<(), OctoError> {
+        let (x, comparison, rhs) = match condition {
+            Condition::Key { x, pressed } => {
+                let low = if pressed { 0xA1 } else { 0x9E };
+                return self.instruction(0xE000 | (x as u16) << 8 | low);
+            }
+            Condition::Compare { x, comparison, rhs } => ((x as u16) << 8, comparison, rhs),
+        };
+        match (comparison, rhs) {
+            (Comparison::Equal, Operand::Byte(n)) => self.instruction(0x4000 | x | n as u16),
+            (Comparison::Equal, Operand::Register(y)) => {
+                self.instruction(0x9000 | x | (y as u16) << 4)
+            }
+            (Comparison::NotEqual, Operand::Byte(n)) => self.instruction(0x3000 | x | n as u16),
+            (Comparison::NotEqual, Operand::Register(y)) => {
+                self.instruction(0x5000 | x | (y as u16) << 4)
+            }
+            (comparison, rhs) => {
+                match rhs {
+                    Operand::Register(y) => self.instruction(0x8F00 | (y as u16) << 4)?,
+                    Operand::Byte(n) => self.instruction(0x6F00 | n as u16)?,
+                }
+                // vF is 1 when vx >= rhs after `vf =- vx`, and when vx <= rhs after `vf -= vx`
+                match comparison {
+                    Comparison::Less | Comparison::GreaterEqual => {
+                        self.instruction(0x8F07 | x >> 4)?
+                    }
+                    _ => self.instruction(0x8F05 | x >> 4)?,
+                }
+                match comparison {
+                    Comparison::Less | Comparison::Greater => self.instruction(0x4F00),
+                    _ => self.instruction(0x4F01),
+                }
+            }
+        }
+    }
+
+    /// The tokens up to the `}` closing an opened `{`
+    fn braced(&mut self) -> Result<Vec<Token>, OctoError> {
+        let mut depth = 1;
+        let mut body = Vec::new();
+        loop {
+            let token = self.next()?;
+            if !token.string {
+                match token.text.as_str() {
+                    "{" => depth += 1,
+                    "}" if depth == 1 => return Ok(body),
+                    "}" => depth -= 1,
+                    _ => {}
+                }
+            }
+            body.push(token);
+        }
+    }
+
+    /// `:macro name params... { body }`
+    fn define_macro(&mut self) -> Result<(), OctoError> {
+        let name = self.name()?;
+        let mut params = Vec::new();
+        while !self.peek_is("{") {
+            params.push(self.name()?);
+        }
+        self.next()?;
+        let body = self.braced()?;
+        self.macros.insert(
+            name,
+            Macro {
+                params,
+                body,
+                calls: 0,
+            },
+        );
+        Ok(())
+    }
+
+    fn expand_macro(&mut self, name: &str) -> Result<(), OctoError> {
+        let Some(definition) = self.macros.get_mut(name) else {
+            return Ok(());
+        };
+        definition.calls += 1;
+        let (params, body, calls) = (
+            definition.params.clone(),
+            definition.body.clone(),
+            definition.calls - 1,
+        );
+        let args = params
+            .iter()
+            .map(|_| self.next())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let expanded = body.into_iter().map(|token| {
+            match params.iter().position(|param| *param == token.text) {
+                Some(_) if token.string => token,
+                Some(index) => args[index].clone(),
+                None if !token.string && token.text == "CALLS" => Token {
+                    text: calls.to_string(),
+                    ..token
+                },
+                None => token,
+            }
+        });
+        self.push_front(expanded.collect());
+        Ok(())
+    }
+
+    /// `:stringmode name "alphabet" { body }`, which can be repeated to add characters
+    fn define_string_mode(&mut self) -> Result<(), OctoError> {
+        let name = self.name()?;
+        let alphabet = self.next()?;
+        if !alphabet.string {
+            return Err(self.error(format!("expected a string, got {}", alphabet.text)));
+        }
+        self.expect("{")?;
+        let body = self.braced()?;
+        let mode = self.string_modes.entry(name).or_default();
+        for (value, c) in alphabet.text.chars().enumerate() {
+            mode.insert(c, (value, body.clone()));
+        }
+        Ok(())
+    }
+
+    /// The body of a string mode for each character of the string after it,
+    /// with `CHAR` (its code), `INDEX` (in the string) and `VALUE` (in the alphabet)
+    fn expand_string(&mut self, name: &str) -> Result<(), OctoError> {
+        let text = self.next()?;
+        if !text.string {
+            return Err(self.error(format!("{} needs a string, got {}", name, text.text)));
+        }
+        let mode = &self.string_modes[name];
+        let mut expanded = Vec::new();
+        for (index, c) in text.text.chars().enumerate() {
+            let (value, body) = mode
+                .get(&c)
+                .ok_or_else(|| self.error(format!("{} can't show {:?}", name, c)))?;
+            expanded.extend(body.iter().map(|token| {
+                let number = match token.text.as_str() {
+                    _ if token.string => None,
+                    "CHAR" => Some(c as usize),
+                    "INDEX" => Some(index),
+                    "VALUE" => Some(*value),
+                    _ => None,
+                };
+                match number {
+                    Some(number) => Token {
+                        text: number.to_string(),
+                        ..token.clone()
+                    },
+                    None => token.clone(),
+                }
+            }));
+        }
+        self.push_front(expanded);
+        Ok(())
+    }
+
+    fn push_front(&mut self, tokens: Vec<Token>) {
+        for token in tokens.into_iter().rev() {
+            self.tokens.push_front(token);
+        }
+    }
+
+    /// A `:calc` expression, after its `{`. Octo gives all operators the same
+    /// precedence and evaluates right to left, so `1 - 2 - 3` is `1 - (2 - 3)`.
+    fn calc(&mut self) -> Result<f64, OctoError> {
+        let value = self.expression()?;
+        self.expect("}")?;
+        Ok(value)
+    }
+
+    fn expression(&mut self) -> Result<f64, OctoError> {
+        let lhs = self.term()?;
+        if self.peek_is("}") || self.peek_is(")") {
+            return Ok(lhs);
+        }
+        let operator = self.next()?;
+        let rhs = self.expression()?;
+        let (a, b) = (lhs as i64, rhs as i64);
+        let value = match operator.text.as_str() {
+            "+" => lhs + rhs,
+            "-" => lhs - rhs,
+            "*" => lhs * rhs,
+            "/" => lhs / rhs,
+            "%" => lhs % rhs,
+            "pow" => lhs.powf(rhs),
+            "min" => lhs.min(rhs),
+            "max" => lhs.max(rhs),
+            "&" => (a & b) as f64,
+            "|" => (a | b) as f64,
+            "^" => (a ^ b) as f64,
+            "<<" => a.wrapping_shl(b as u32) as f64,
+            ">>" => a.wrapping_shr(b as u32) as f64,
+            "<" => (lhs < rhs) as u8 as f64,
+            ">" => (lhs > rhs) as u8 as f64,
+            "<=" => (lhs <= rhs) as u8 as f64,
+            ">=" => (lhs >= rhs) as u8 as f64,
+            "==" => (lhs == rhs) as u8 as f64,
+            "!=" => (lhs != rhs) as u8 as f64,
+            _ => return Err(self.error(format!("unknown operator {}", operator.text))),
+        };
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, OctoError> {
+        let token = self.next()?;
+        if token.string {
+            return Err(self.error(format!("unexpected string \"{}\"", token.text)));
+        }
+        let value = match token.text.as_str() {
+            "(" => {
+                let value = self.expression()?;
+                self.expect(")")?;
+                value
+            }
+            "-" => -self.term()?,
+            "~" => !(self.term()? as i64) as f64,
+            "!" => (self.term()? == 0.0) as u8 as f64,
+            "sin" => self.term()?.sin(),
+            "cos" => self.term()?.cos(),
+            "tan" => self.term()?.tan(),
+            "exp" => self.term()?.exp(),
+            "log" => self.term()?.ln(),
+            "abs" => self.term()?.abs(),
+            "sqrt" => self.term()?.sqrt(),
+            "sign" => match self.term()? {
+                0.0 => 0.0,
+                value => value.signum(),
+            },
+            "ceil" => self.term()?.ceil(),
+            "floor" => self.term()?.floor(),
+            // A byte of the program so far
+            "@" => {
+                let address = self.term()? as i64 - START as i64;
+                let byte = usize::try_from(address)
+                    .ok()
+                    .and_then(|index| self.rom.get(index));
+                byte.copied().unwrap_or(0) as f64
+            }
+            "HERE" => self.here as f64,
+            "PI" => consts::PI,
+            "E" => consts::E,
+            text => match parse_number(text) {
+                Some(number) => number as f64,
+                None => match (self.constants.get(text), self.labels.get(text)) {
+                    (Some(value), _) => *value,
+                    (None, Some(address)) => *address as f64,
+                    (None, None) => return Err(self.error(format!("undefined name {}", text))),
+                },
+            },
+        };
+        Ok(value)
+    }
+}
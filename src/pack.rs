@@ -0,0 +1,107 @@
+//! ROM packs
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Zip files of ROMs, read in place. A ROM inside a pack is named like
+//! `pack.zip:game.ch8`, and goes through the same checks as a ROM file
+//! (see `rom.rs`).
+
+use crate::rom::{self, Rom, RomError, DEFAULT_PLATFORM};
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+/// Separates a pack's path from the name of a ROM in it
+const SEPARATOR: &str = ".zip:";
+
+/// A ROM in a pack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Path inside the pack
+    pub name: String,
+    /// Uncompressed size
+    pub size: u64,
+}
+
+/// An open zip file of ROMs
+pub struct Pack<R = File> {
+    archive: ZipArchive<R>,
+    /// For error messages
+    path: String,
+    entries: Vec<Entry>,
+}
+
+impl Pack {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, RomError> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| RomError::Io(format!("can't read {}: {}", path.display(), err)))?;
+        Self::new(file, path.display().to_string())
+    }
+}
+
+impl<R: Read + Seek> Pack<R> {
+    /// A pack from any zip reader. `path` is only used in error messages.
+    pub fn new(reader: R, path: String) -> Result<Self, RomError> {
+        let mut archive = ZipArchive::new(reader)
+            .map_err(|err| RomError::Io(format!("can't read {}: {}", path, err)))?;
+
+        let mut entries = Vec::new();
+        for index in 0..archive.len() {
+            let file = archive
+                .by_index(index)
+                .map_err(|err| RomError::Io(format!("can't read {}: {}", path, err)))?;
+            if file.is_file() && rom::platform_for(file.name()).is_some() {
+                entries.push(Entry {
+                    name: file.name().to_owned(),
+                    size: file.size(),
+                });
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Pack {
+            archive,
+            path,
+            entries,
+        })
+    }
+
+    /// The ROMs in the pack (files with a ROM extension), sorted by name
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The bytes of a file in the pack
+    pub fn read(&mut self, name: &str) -> Result<Vec<u8>, RomError> {
+        let mut file = self
+            .archive
+            .by_name(name)
+            .map_err(|err| RomError::Io(format!("can't read {}:{}: {}", self.path, name, err)))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .map_err(|err| RomError::Io(format!("can't read {}:{}: {}", self.path, name, err)))?;
+        Ok(bytes)
+    }
+
+    /// A ROM in the pack, picking the platform from its extension (like `Rom::load`)
+    pub fn load(&mut self, name: &str) -> Result<Rom, RomError> {
+        let bytes = self.read(name)?;
+        Rom::new(bytes, rom::platform_for(name).unwrap_or(DEFAULT_PLATFORM))
+    }
+}
+
+/// Split `pack.zip:game.ch8` into the pack and the ROM in it, or `pack.zip`
+/// into just the pack. `None` for paths that aren't packs.
+pub fn split(path: &str) -> Option<(PathBuf, Option<&str>)> {
+    let lowercase = path.to_ascii_lowercase();
+    if let Some(at) = lowercase.find(SEPARATOR) {
+        let end = at + SEPARATOR.len();
+        return Some((PathBuf::from(&path[..end - 1]), Some(&path[end..])));
+    }
+    lowercase
+        .ends_with(".zip")
+        .then(|| (PathBuf::from(path), None))
+}
@@ -0,0 +1,181 @@
+//! Colour palettes
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Turns the display into pixels for a framebuffer, so every frontend draws
+//! the same colours.
+
+use crate::{phosphor::Intensity, Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use core::{error::Error, fmt};
+
+/// Colours to draw the display with, as 0xRRGGBB.
+/// Indexed by the planes a pixel is lit on: `0` is the background, `1` the
+/// first plane, `2` the second plane and `3` both (for multi-plane variants).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [u32; 4],
+}
+
+impl Palette {
+    /// White on black
+    pub const CLASSIC: Palette = Palette::new(0x000000, 0xFFFFFF);
+    /// Green phosphor CRT
+    pub const GREEN: Palette = Palette::new(0x0A1A0A, 0x33FF66);
+    /// Amber phosphor CRT
+    pub const AMBER: Palette = Palette::new(0x1A0F00, 0xFFB000);
+    /// Greenish LCD, like early handhelds
+    pub const LCD: Palette = Palette {
+        colors: [0x9BBC0F, 0x0F380F, 0x306230, 0x8BAC0F],
+    };
+
+    /// Every named preset, for `from_name` and help messages
+    pub const PRESETS: [(&'static str, Palette); 4] = [
+        ("classic", Self::CLASSIC),
+        ("green", Self::GREEN),
+        ("amber", Self::AMBER),
+        ("lcd", Self::LCD),
+    ];
+
+    /// Two-colour palette. Other planes use the foreground too.
+    pub const fn new(background: u32, foreground: u32) -> Self {
+        Palette {
+            colors: [background, foreground, foreground, foreground],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|(_, palette)| *palette)
+    }
+
+    /// Either a preset name, or 2 to 4 comma-separated hex colours
+    /// (eg: `#000000,#FFFFFF` or `112233,aabbcc,ff0000,00ff00`).
+    pub fn parse(s: &str) -> Result<Self, ParsePaletteError> {
+        if let Some(palette) = Self::from_name(s.trim()) {
+            return Ok(palette);
+        }
+
+        let mut colors = [0; 4];
+        let mut count = 0;
+        for part in s.split(',') {
+            if count == colors.len() {
+                return Err(ParsePaletteError);
+            }
+            colors[count] = parse_hex(part.trim())?;
+            count += 1;
+        }
+
+        match count {
+            2 => Ok(Self::new(colors[0], colors[1])),
+            3 => Ok(Palette {
+                colors: [colors[0], colors[1], colors[2], colors[1]],
+            }),
+            4 => Ok(Palette { colors }),
+            _ => Err(ParsePaletteError),
+        }
+    }
+
+    pub fn background(&self) -> u32 {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> u32 {
+        self.colors[1]
+    }
+
+    /// Colour for a pixel lit on the given planes (bit 0 = plane 1, bit 1 = plane 2)
+    pub fn color(&self, planes: u8) -> u32 {
+        self.colors[(planes & 0b11) as usize]
+    }
+
+    /// Convert the display into 0xAARRGGBB pixels (row-major, fully opaque).
+    /// `out` must hold at least `DISPLAY_WIDTH * DISPLAY_HEIGHT` pixels.
+    pub fn render(&self, display: &Display, out: &mut [u32]) {
+        for (pixel, on) in out.iter_mut().zip(display.iter().flatten()) {
+            *pixel = 0xFF000000 | self.color(*on as u8);
+        }
+    }
+
+    /// Same as `render`, with a display per plane
+    pub fn render_planes(&self, planes: &[Display], out: &mut [u32]) {
+        for (i, pixel) in out
+            .iter_mut()
+            .take(DISPLAY_WIDTH * DISPLAY_HEIGHT)
+            .enumerate()
+        {
+            let (x, y) = (i % DISPLAY_WIDTH, i / DISPLAY_WIDTH);
+            let lit = planes
+                .iter()
+                .take(2)
+                .enumerate()
+                .fold(0, |bits, (plane, display)| {
+                    bits | ((display[y][x] as u8) << plane)
+                });
+            *pixel = 0xFF000000 | self.color(lit);
+        }
+    }
+
+    /// Same as `render`, for a blended frame (see `phosphor`).
+    /// Partly lit pixels are mixed between the background and the foreground.
+    pub fn render_intensity(&self, intensity: &Intensity, out: &mut [u32]) {
+        let (background, foreground) = (self.background(), self.foreground());
+        for (pixel, level) in out.iter_mut().zip(intensity.iter().flatten()) {
+            *pixel = 0xFF000000 | mix(background, foreground, *level);
+        }
+    }
+
+    /// Same as `render`, as R, G, B, A bytes.
+    /// `out` must hold at least `DISPLAY_WIDTH * DISPLAY_HEIGHT * 4` bytes.
+    pub fn render_rgba(&self, display: &Display, out: &mut [u8]) {
+        for (pixel, on) in out.chunks_exact_mut(4).zip(display.iter().flatten()) {
+            let [_, r, g, b] = self.color(*on as u8).to_be_bytes();
+            pixel.copy_from_slice(&[r, g, b, 0xFF]);
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+/// Blend two 0xRRGGBB colours, channel by channel. `amount` 0 is all `from`, 255 all `to`.
+fn mix(from: u32, to: u32, amount: u8) -> u32 {
+    let (from, to) = (from.to_be_bytes(), to.to_be_bytes());
+    let mut mixed = [0; 4];
+    for (channel, (from, to)) in mixed.iter_mut().zip(from.iter().zip(to.iter())) {
+        let (from, to, amount) = (*from as i32, *to as i32, amount as i32);
+        *channel = (from + (to - from) * amount / 255) as u8;
+    }
+    u32::from_be_bytes(mixed)
+}
+
+/// `RRGGBB`, optionally prefixed with `#` or `0x`
+fn parse_hex(s: &str) -> Result<u32, ParsePaletteError> {
+    let digits = s
+        .strip_prefix('#')
+        .or_else(|| s.strip_prefix("0x"))
+        .unwrap_or(s);
+
+    if digits.len() != 6 {
+        return Err(ParsePaletteError);
+    }
+    u32::from_str_radix(digits, 16).map_err(|_| ParsePaletteError)
+}
+
+/// Returned by `Palette::parse` for unknown presets and malformed colours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsePaletteError;
+
+impl fmt::Display for ParsePaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a preset (classic, green, amber, lcd) or 2 to 4 hex colours like #000000,#FFFFFF"
+        )
+    }
+}
+
+impl Error for ParsePaletteError {}
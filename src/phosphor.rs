@@ -0,0 +1,134 @@
+//! Phosphor persistence
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Sprites are erased and redrawn with XOR, so moving ones are off for part
+//! of every frame and flicker. Feeding the display in once per 60Hz frame
+//! and blending it with the previous ones hides that, the way a CRT would.
+
+use crate::{Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use core::{error::Error, fmt};
+
+/// Brightness of each pixel, from 0 (off) to 255 (fully lit)
+pub type Intensity = [[u8; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+
+/// Most frames `Persistence::Or` can remember
+pub const MAX_FRAMES: usize = 8;
+
+/// How frames are blended together
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Persistence {
+    /// Show every frame as is
+    #[default]
+    Off,
+    /// Lit pixels fade out instead of turning off.
+    /// Holds the fraction of brightness kept each frame (0.0 to 1.0).
+    Decay(f32),
+    /// A pixel is lit if it was lit in any of the last N frames (1 to `MAX_FRAMES`)
+    Or(usize),
+}
+
+impl Persistence {
+    /// `off`, `decay` / `decay:FACTOR` or `or` / `or:FRAMES`
+    pub fn parse(s: &str) -> Result<Self, ParsePersistenceError> {
+        let (mode, arg) = match s.trim().split_once(':') {
+            Some((mode, arg)) => (mode, Some(arg.trim())),
+            None => (s.trim(), None),
+        };
+
+        match (mode, arg) {
+            ("off", None) => Ok(Persistence::Off),
+            ("decay", None) => Ok(Persistence::Decay(0.6)),
+            ("decay", Some(arg)) => match arg.parse::<f32>() {
+                Ok(factor) if (0.0..=1.0).contains(&factor) => Ok(Persistence::Decay(factor)),
+                _ => Err(ParsePersistenceError),
+            },
+            ("or", None) => Ok(Persistence::Or(2)),
+            ("or", Some(arg)) => match arg.parse::<usize>() {
+                Ok(frames) if (1..=MAX_FRAMES).contains(&frames) => Ok(Persistence::Or(frames)),
+                _ => Err(ParsePersistenceError),
+            },
+            _ => Err(ParsePersistenceError),
+        }
+    }
+}
+
+/// Blends the frames it is fed according to a `Persistence` mode
+#[derive(Debug, Clone)]
+pub struct Phosphor {
+    mode: Persistence,
+    intensity: Intensity,
+    /// Last frames, for `Persistence::Or`. `next` is the oldest one.
+    history: [Display; MAX_FRAMES],
+    next: usize,
+}
+
+impl Phosphor {
+    pub fn new(mode: Persistence) -> Self {
+        Phosphor {
+            mode,
+            intensity: [[0; DISPLAY_WIDTH]; DISPLAY_HEIGHT],
+            history: [[[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT]; MAX_FRAMES],
+            next: 0,
+        }
+    }
+
+    pub fn mode(&self) -> Persistence {
+        self.mode
+    }
+
+    /// Feed in the display. Call it once per 60Hz frame.
+    pub fn push(&mut self, display: &Display) {
+        match self.mode {
+            Persistence::Off => {
+                for (row, on_row) in self.intensity.iter_mut().zip(display) {
+                    for (pixel, on) in row.iter_mut().zip(on_row) {
+                        *pixel = if *on { 255 } else { 0 };
+                    }
+                }
+            }
+
+            Persistence::Decay(keep) => {
+                for (row, on_row) in self.intensity.iter_mut().zip(display) {
+                    for (pixel, on) in row.iter_mut().zip(on_row) {
+                        *pixel = if *on {
+                            255
+                        } else {
+                            (*pixel as f32 * keep) as u8
+                        };
+                    }
+                }
+            }
+
+            Persistence::Or(frames) => {
+                let frames = frames.clamp(1, MAX_FRAMES);
+                self.next %= frames;
+                self.history[self.next] = *display;
+                self.next = (self.next + 1) % frames;
+
+                for (y, row) in self.intensity.iter_mut().enumerate() {
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let on = self.history[..frames].iter().any(|frame| frame[y][x]);
+                        *pixel = if on { 255 } else { 0 };
+                    }
+                }
+            }
+        }
+    }
+
+    /// The blended frame
+    pub fn intensity(&self) -> &Intensity {
+        &self.intensity
+    }
+}
+
+/// Returned by `Persistence::parse` for unknown modes and out of range values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParsePersistenceError;
+
+impl fmt::Display for ParsePersistenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected off, decay[:0.0-1.0] or or[:1-{}]", MAX_FRAMES)
+    }
+}
+
+impl Error for ParsePersistenceError {}
@@ -0,0 +1,124 @@
+//! Hooks into the host platform
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! The core needs a clock for the 60Hz timers and a source of random numbers
+//! for Cxnn. Both are traits so they can be swapped out on targets without `std`.
+
+/// Microseconds between two timer ticks (60Hz)
+pub const TIMER_PERIOD_MICROS: u64 = 16_667;
+
+/// A monotonic clock used to decrement the delay and sound timers
+pub trait Clock {
+    /// Microseconds since some fixed point in the past
+    fn now_micros(&mut self) -> u64;
+}
+
+/// Random numbers for the Cxnn instruction
+pub trait RandomSource {
+    fn next_byte(&mut self) -> u8;
+}
+
+/// A clock that only moves when told to.
+/// Useful on microcontrollers (advance it from a timer interrupt)
+/// and for deterministic runs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ManualClock {
+    micros: u64,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        ManualClock { micros: 0 }
+    }
+
+    pub fn advance(&mut self, micros: u64) {
+        self.micros += micros;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_micros(&mut self) -> u64 {
+        self.micros
+    }
+}
+
+/// Wall clock time using `std::time::Instant`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct StdClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl StdClock {
+    pub fn new() -> Self {
+        StdClock {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now_micros(&mut self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+}
+
+/// Small and fast xorshift32 generator.
+/// Nowhere near cryptographic, but plenty for games.
+#[derive(Debug, Clone, Copy)]
+pub struct XorShift {
+    state: u32,
+}
+
+impl XorShift {
+    /// A zero seed would get stuck, so it is replaced with a fixed non-zero one
+    pub fn new(seed: u32) -> Self {
+        XorShift {
+            state: if seed == 0 { 0x2545_F491 } else { seed },
+        }
+    }
+
+    /// Seeded from the randomness `std` uses for `HashMap`s
+    #[cfg(feature = "std")]
+    pub fn from_entropy() -> Self {
+        use std::hash::{BuildHasher, Hasher};
+
+        let seed = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        Self::new((seed ^ (seed >> 32)) as u32)
+    }
+}
+
+impl Default for XorShift {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl RandomSource for XorShift {
+    fn next_byte(&mut self) -> u8 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        (x >> 24) as u8
+    }
+}
+
+/// Clock used by `Chip::new`
+#[cfg(feature = "std")]
+pub type DefaultClock = StdClock;
+/// Clock used by `Chip::new`
+#[cfg(not(feature = "std"))]
+pub type DefaultClock = ManualClock;
@@ -0,0 +1,180 @@
+//! Runtime quirk detection
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! Records when execution actually depends on a `BehaviorConfig` field: the
+//! other setting would have given a different result that the program then
+//! used. Turned on with `Chip::detect_quirks`, it tells which toggles to try
+//! when a game misbehaves.
+
+use crate::{
+    structs::{BehaviorConfig, Instruction},
+    DISPLAY_HEIGHT, DISPLAY_WIDTH,
+};
+use core::fmt;
+
+/// A `BehaviorConfig` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quirk {
+    VfReset,
+    IncrementI,
+    Shift,
+    Jump,
+    Wrap,
+}
+
+impl Quirk {
+    pub const ALL: [Quirk; 5] = [
+        Quirk::VfReset,
+        Quirk::IncrementI,
+        Quirk::Shift,
+        Quirk::Jump,
+        Quirk::Wrap,
+    ];
+
+    /// Name of the field in `BehaviorConfig`
+    pub fn field(&self) -> &'static str {
+        match self {
+            Quirk::VfReset => "vf_reset",
+            Quirk::IncrementI => "increment_i_on_save_load",
+            Quirk::Shift => "shift",
+            Quirk::Jump => "jump",
+            Quirk::Wrap => "wrap",
+        }
+    }
+
+    /// The field itself, to read or change it
+    pub fn flag<'a>(&self, behavior: &'a mut BehaviorConfig) -> &'a mut bool {
+        match self {
+            Quirk::VfReset => &mut behavior.vf_reset,
+            Quirk::IncrementI => &mut behavior.increment_i_on_save_load,
+            Quirk::Shift => &mut behavior.shift,
+            Quirk::Jump => &mut behavior.jump,
+            Quirk::Wrap => &mut behavior.wrap,
+        }
+    }
+}
+
+/// How often a quirk mattered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuirkCount {
+    pub count: u32,
+    /// Address of the first instruction affected by it
+    pub first_pc: Option<u16>,
+}
+
+/// What was seen so far
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuirkUsage {
+    counts: [QuirkCount; 5],
+    /// `8xy1-3` whose V(F) depends on `vf_reset`, until V(F) is overwritten
+    pending_vf: Option<u16>,
+    /// `Fx55` or `Fx65` whose I depends on `increment_i_on_save_load`, until I is set
+    pending_i: Option<u16>,
+}
+
+impl QuirkUsage {
+    pub fn get(&self, quirk: Quirk) -> QuirkCount {
+        self.counts[quirk as usize]
+    }
+
+    /// Whether any quirk mattered
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|count| count.count == 0)
+    }
+
+    fn record(&mut self, quirk: Quirk, pc: u16) {
+        let count = &mut self.counts[quirk as usize];
+        count.count += 1;
+        count.first_pc.get_or_insert(pc);
+    }
+
+    /// Look at an instruction about to be executed at `pc`
+    pub(crate) fn observe(
+        &mut self,
+        pc: u16,
+        instr: &Instruction,
+        v: [u8; 16],
+        i_reg: u16,
+        memory: &[u8],
+    ) {
+        let (x, y, n) = (instr.get_nib(1), instr.get_nib(2), instr.get_nib(3));
+        let (vx, vy) = (v[x as usize], v[y as usize]);
+
+        // Uses of a V(F) or I left behind by an earlier instruction
+        let (reads, writes) = instr.registers();
+        if let Some(logic_pc) = self.pending_vf {
+            if reads & 1 << 0xF != 0 {
+                self.record(Quirk::VfReset, logic_pc);
+                self.pending_vf = None;
+            } else if writes & 1 << 0xF != 0 {
+                self.pending_vf = None;
+            }
+        }
+        if let Some(save_pc) = self.pending_i {
+            match (instr.get_nib(0), instr.get_lbyte()) {
+                (0xD, _) | (0xF, 0x1E | 0x33 | 0x55 | 0x65) => {
+                    self.record(Quirk::IncrementI, save_pc);
+                    self.pending_i = None;
+                }
+                (0xA, _) | (0xF, 0x29) => self.pending_i = None,
+                _ => {}
+            }
+        }
+
+        match (instr.get_nib(0), n, instr.get_lbyte()) {
+            // V(F) is either reset, or kept (or the result, for 8Fy1-3)
+            (0x8, 0x1..=0x3, _) => {
+                let kept = match (x, n) {
+                    (0xF, 0x1) => vx | vy,
+                    (0xF, 0x2) => vx & vy,
+                    (0xF, _) => vx ^ vy,
+                    _ => v[0xF],
+                };
+                self.pending_vf = (kept != 0).then_some(pc);
+            }
+            (0x8, 0x6 | 0xE, _) if vx != vy => self.record(Quirk::Shift, pc),
+            (0xB, _, _) if vx != v[0] => self.record(Quirk::Jump, pc),
+            (0xD, _, _) if crosses_edge(vx, vy, n, i_reg, memory) => self.record(Quirk::Wrap, pc),
+            (0xF, _, 0x55 | 0x65) => self.pending_i = Some(pc),
+            _ => {}
+        }
+    }
+}
+
+/// Does the sprite have lit pixels past the right or bottom edge ?
+fn crosses_edge(vx: u8, vy: u8, n: u8, i_reg: u16, memory: &[u8]) -> bool {
+    let x = vx as usize % DISPLAY_WIDTH;
+    let y = vy as usize % DISPLAY_HEIGHT;
+
+    (0..n as usize).any(|row| {
+        let bits = memory.get(i_reg as usize + row).copied().unwrap_or(0);
+        let past_right = match DISPLAY_WIDTH - x {
+            width if width < 8 => bits & (0xFF >> width) != 0,
+            _ => false,
+        };
+        bits != 0 && (y + row >= DISPLAY_HEIGHT || past_right)
+    })
+}
+
+impl fmt::Display for QuirkUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No quirk made a difference");
+        }
+
+        write!(f, "Quirks that made a difference:")?;
+        for quirk in Quirk::ALL {
+            let count = self.get(quirk);
+            if let Some(pc) = count.first_pc {
+                write!(
+                    f,
+                    "\n  {}: {}x, first at {:#05X}",
+                    quirk.field(),
+                    count.count,
+                    pc
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
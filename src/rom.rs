@@ -0,0 +1,142 @@
+//! ROM loading
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! `Chip::new` takes whatever bytes it is given and cuts them short if they
+//! don't fit. `Rom` checks them first, and guesses which platform a ROM was
+//! written for from its extension.
+
+use crate::{structs::BehaviorConfig, MEMORY_SIZE, PROGRAM_START};
+use std::{error::Error, fmt, fs, path::Path};
+
+/// Platform assumed when the extension doesn't tell
+pub const DEFAULT_PLATFORM: &str = "originalChip8";
+
+/// Extensions and the platform (see `BehaviorConfig::for_platform`) they imply
+pub const EXTENSIONS: [(&str, &str); 5] = [
+    ("ch8", "originalChip8"),
+    ("c8", "originalChip8"),
+    ("hc8", "hybridVIP"),
+    ("sc8", "superchip"),
+    ("xo8", "xochip"),
+];
+
+/// Most bytes that fit in memory after `PROGRAM_START`
+pub const MAX_SIZE: usize = MEMORY_SIZE - PROGRAM_START as usize;
+
+/// A validated program, and the platform it is meant for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rom {
+    bytes: Vec<u8>,
+    platform: &'static str,
+}
+
+impl Rom {
+    /// Check that a program isn't empty and fits the platform's memory
+    pub fn new(bytes: Vec<u8>, platform: &str) -> Result<Self, RomError> {
+        let platform = BehaviorConfig::PLATFORMS
+            .into_iter()
+            .find(|id| *id == platform)
+            .ok_or_else(|| RomError::UnknownPlatform(platform.to_owned()))?;
+
+        let max = max_size(platform);
+        match bytes.len() {
+            0 => Err(RomError::Empty),
+            size if size > max => Err(RomError::TooLarge {
+                size,
+                max,
+                platform,
+            }),
+            _ => Ok(Rom { bytes, platform }),
+        }
+    }
+
+    /// Read a ROM file, picking the platform from its extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RomError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|err| RomError::Io(format!("can't read {}: {}", path.display(), err)))?;
+        Self::new(bytes, platform_for(path).unwrap_or(DEFAULT_PLATFORM))
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Platform ID, for `BehaviorConfig::for_platform`
+    pub fn platform(&self) -> &'static str {
+        self.platform
+    }
+
+    /// Quirks of the platform
+    pub fn behavior(&self) -> BehaviorConfig {
+        BehaviorConfig::for_platform(self.platform).unwrap_or_default()
+    }
+
+    /// Lowercase hex SHA-1, as used by the ROM database and config file
+    pub fn sha1(&self) -> String {
+        sha1(&self.bytes)
+    }
+}
+
+/// The platform implied by a file's extension (ignoring case), if it is a known one
+pub fn platform_for(path: impl AsRef<Path>) -> Option<&'static str> {
+    let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, platform)| *platform)
+}
+
+/// Most bytes a platform can load. XO-CHIP has 64KB of memory, but this
+/// emulator only has 4KB, so everything is capped at `MAX_SIZE`.
+pub fn max_size(platform: &str) -> usize {
+    match platform {
+        // 0xEA0 up is the stack and display buffer of the COSMAC VIP
+        "originalChip8" | "hybridVIP" => 0xEA0 - PROGRAM_START as usize,
+        _ => MAX_SIZE,
+    }
+}
+
+/// Lowercase hex SHA-1 of a program
+pub fn sha1(bytes: &[u8]) -> String {
+    sha1_smol::Sha1::from(bytes).hexdigest()
+}
+
+/// A ROM that can't be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    /// The file couldn't be read
+    Io(String),
+    Empty,
+    TooLarge {
+        size: usize,
+        max: usize,
+        platform: &'static str,
+    },
+    UnknownPlatform(String),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Io(reason) => write!(f, "{}", reason),
+            RomError::Empty => write!(f, "the ROM is empty"),
+            RomError::TooLarge {
+                size,
+                max,
+                platform,
+            } => write!(
+                f,
+                "the ROM is {} bytes, but {} ROMs can't be more than {}",
+                size, platform, max
+            ),
+            RomError::UnknownPlatform(platform) => write!(f, "unknown platform `{}`", platform),
+        }
+    }
+}
+
+impl Error for RomError {}
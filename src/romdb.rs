@@ -0,0 +1,168 @@
+//! ROM database
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! What's known about ROMs, looked up by their SHA-1: title, authors, the
+//! platform they were written for (and so their quirks), speed, key hints and
+//! colours. Uses the format of the community CHIP-8 database
+//! (https://github.com/chip-8/chip-8-database), whose `programs.json` can be
+//! loaded with `Database::load`. The bundled database only knows the ROMs in `roms/`.
+
+use crate::{rom, structs::BehaviorConfig};
+use serde::Deserialize;
+use std::{collections::HashMap, error::Error, fmt, fs, path::Path};
+
+/// The database bundled with the emulator
+const BUNDLED: &str = include_str!("../data/programs.json");
+
+/// A program, which can have several ROMs (versions, ports...)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Program {
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    pub release: Option<String>,
+    /// ROMs, keyed by their lowercase hex SHA-1
+    pub roms: HashMap<String, Rom>,
+}
+
+/// One ROM file of a program
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rom {
+    pub file: Option<String>,
+    /// Platform IDs it runs on, best first (eg: `"originalChip8"`)
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    /// Quirks that differ from the platform's
+    #[serde(default)]
+    pub quirky_platforms: HashMap<String, Quirks>,
+    /// Instructions per frame
+    pub tickrate: Option<u32>,
+    /// Hex keys used for each direction and for `a`/`b` (eg: `"up": 5`)
+    #[serde(default)]
+    pub keys: HashMap<String, u8>,
+    pub colors: Option<Colors>,
+}
+
+/// Quirk overrides. `None` keeps the platform's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quirks {
+    pub shift: Option<bool>,
+    pub memory_leave_i_unchanged: Option<bool>,
+    pub wrap: Option<bool>,
+    pub jump: Option<bool>,
+    pub logic: Option<bool>,
+}
+
+impl Quirks {
+    pub fn apply(&self, behavior: &mut BehaviorConfig) {
+        if let Some(shift) = self.shift {
+            behavior.shift = shift;
+        }
+        if let Some(leave_i) = self.memory_leave_i_unchanged {
+            behavior.increment_i_on_save_load = !leave_i;
+        }
+        if let Some(wrap) = self.wrap {
+            behavior.wrap = wrap;
+        }
+        if let Some(jump) = self.jump {
+            behavior.jump = jump;
+        }
+        if let Some(logic) = self.logic {
+            behavior.vf_reset = logic;
+        }
+    }
+}
+
+/// Colours as `#RRGGBB` strings
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Colors {
+    /// Background, then foreground (and the other bitplanes, for XO-CHIP)
+    #[serde(default)]
+    pub pixels: Vec<String>,
+    pub buzzer: Option<String>,
+    pub silence: Option<String>,
+}
+
+impl Rom {
+    /// The first platform we know of, with this ROM's quirks on top
+    pub fn behavior(&self) -> Option<BehaviorConfig> {
+        self.platforms.iter().find_map(|platform| {
+            let mut behavior = BehaviorConfig::for_platform(platform)?;
+            if let Some(quirks) = self.quirky_platforms.get(platform) {
+                quirks.apply(&mut behavior);
+            }
+            Some(behavior)
+        })
+    }
+}
+
+/// Programs, indexed by the SHA-1 of their ROMs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Database {
+    programs: Vec<Program>,
+    /// SHA-1 to index in `programs`
+    hashes: HashMap<String, usize>,
+}
+
+impl Database {
+    /// A `programs.json`
+    pub fn parse(json: &str) -> Result<Self, RomDbError> {
+        let programs: Vec<Program> =
+            serde_json::from_str(json).map_err(|err| RomDbError(err.to_string()))?;
+
+        let mut hashes = HashMap::new();
+        for (index, program) in programs.iter().enumerate() {
+            for hash in program.roms.keys() {
+                hashes.insert(hash.to_ascii_lowercase(), index);
+            }
+        }
+        Ok(Database { programs, hashes })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RomDbError> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|err| RomDbError(format!("can't read {}: {}", path.display(), err)))?;
+        Self::parse(&json).map_err(|err| RomDbError(format!("{}: {}", path.display(), err.0)))
+    }
+
+    pub fn bundled() -> Self {
+        Self::parse(BUNDLED).expect("The bundled ROM database should be valid")
+    }
+
+    pub fn programs(&self) -> &[Program] {
+        &self.programs
+    }
+
+    /// The program and ROM with this SHA-1 (in hex)
+    pub fn find(&self, hash: &str) -> Option<(&Program, &Rom)> {
+        let hash = hash.to_ascii_lowercase();
+        let program = &self.programs[*self.hashes.get(&hash)?];
+        let rom = program
+            .roms
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&hash))
+            .map(|(_, rom)| rom)?;
+        Some((program, rom))
+    }
+
+    /// The program and ROM matching a ROM's contents
+    pub fn lookup(&self, program: &[u8]) -> Option<(&Program, &Rom)> {
+        self.find(&rom::sha1(program))
+    }
+}
+
+/// A database that couldn't be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomDbError(pub String);
+
+impl fmt::Display for RomDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for RomDbError {}
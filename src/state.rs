@@ -0,0 +1,114 @@
+//! Save states
+//! @AryaveerSR <me.aryaveer@gmail.com>
+//!
+//! A snapshot of everything a running program can observe, in a fixed-size
+//! byte format. Behavior configs, the clock and the RNG are not included.
+//!
+//! Layout (multi-byte values are little endian):
+//!
+//! | Offset | Size | Field                                  |
+//! | ------ | ---- | -------------------------------------- |
+//! | 0      | 4    | Magic (`C8ST`)                         |
+//! | 4      | 1    | Version                                |
+//! | 5      | 2    | PC                                     |
+//! | 7      | 2    | I                                      |
+//! | 9      | 16   | V(0) to V(F)                           |
+//! | 25     | 1    | Stack pointer                          |
+//! | 26     | 32   | Stack                                  |
+//! | 58     | 1    | Delay timer                            |
+//! | 59     | 1    | Sound timer                            |
+//! | 60     | 1    | Register waited on by Fx0A, or `0xFF`  |
+//! | 61     | 1    | Sound playing (0 or 1)                 |
+//! | 62     | 256  | Display, one bit per pixel, row-major  |
+//! | 318    | 4096 | Memory                                 |
+
+use crate::{
+    platform::{Clock, RandomSource},
+    structs::InvalidState,
+    Chip, DISPLAY_HEIGHT, DISPLAY_WIDTH, MEMORY_SIZE, STACK_SIZE,
+};
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 1;
+
+const DISPLAY_BYTES: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT / 8;
+const HEADER_SIZE: usize = 62;
+
+/// Size of a save state in bytes
+pub const STATE_SIZE: usize = HEADER_SIZE + DISPLAY_BYTES + MEMORY_SIZE;
+
+impl<C: Clock, R: RandomSource> Chip<C, R> {
+    /// Write a snapshot of the machine into `out`
+    pub fn save_state(&self, out: &mut [u8; STATE_SIZE]) {
+        out[0..4].copy_from_slice(MAGIC);
+        out[4] = VERSION;
+        out[5..7].copy_from_slice(&self.pc.to_le_bytes());
+        out[7..9].copy_from_slice(&self.i_reg.to_le_bytes());
+        for reg in 0..16 {
+            out[9 + reg as usize] = self.var_reg.get(reg);
+        }
+        out[25] = self.sp as u8;
+        for (i, addr) in self.stack.iter().enumerate() {
+            out[26 + i * 2..28 + i * 2].copy_from_slice(&addr.to_le_bytes());
+        }
+        out[58] = self.delay_timer;
+        out[59] = self.sound_timer;
+        out[60] = self.is_waiting_for_press.unwrap_or(0xFF);
+        out[61] = self.is_playing_sound as u8;
+
+        let display = &mut out[HEADER_SIZE..HEADER_SIZE + DISPLAY_BYTES];
+        display.fill(0);
+        for (i, pixel) in self.display.iter().flatten().enumerate() {
+            if *pixel {
+                display[i / 8] |= 0x80 >> (i % 8);
+            }
+        }
+
+        out[HEADER_SIZE + DISPLAY_BYTES..].copy_from_slice(&self.memory);
+    }
+
+    /// Restore a snapshot made by `save_state`.
+    /// The machine is left untouched if the snapshot is invalid.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), InvalidState> {
+        if data.len() != STATE_SIZE || &data[0..4] != MAGIC || data[4] != VERSION {
+            return Err(InvalidState);
+        }
+
+        let pc = u16::from_le_bytes([data[5], data[6]]);
+        let i_reg = u16::from_le_bytes([data[7], data[8]]);
+        let sp = data[25] as usize;
+        let waiting = data[60];
+        if pc as usize + 1 >= MEMORY_SIZE
+            || i_reg as usize >= MEMORY_SIZE
+            || sp > STACK_SIZE
+            || (waiting != 0xFF && waiting > 0xF)
+        {
+            return Err(InvalidState);
+        }
+
+        self.pc = pc;
+        self.i_reg = i_reg;
+        for reg in 0..16 {
+            self.var_reg.set(reg, data[9 + reg as usize]);
+        }
+        self.sp = sp;
+        for (i, addr) in self.stack.iter_mut().enumerate() {
+            *addr = u16::from_le_bytes([data[26 + i * 2], data[27 + i * 2]]);
+        }
+        self.delay_timer = data[58];
+        self.sound_timer = data[59];
+        self.is_waiting_for_press = if waiting == 0xFF { None } else { Some(waiting) };
+        self.is_playing_sound = data[61] != 0;
+
+        let display = &data[HEADER_SIZE..HEADER_SIZE + DISPLAY_BYTES];
+        for (i, pixel) in self.display.iter_mut().flatten().enumerate() {
+            *pixel = display[i / 8] & (0x80 >> (i % 8)) != 0;
+        }
+
+        self.memory
+            .copy_from_slice(&data[HEADER_SIZE + DISPLAY_BYTES..]);
+        self.last_update = self.clock.now_micros();
+
+        Ok(())
+    }
+}
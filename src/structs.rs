@@ -1,17 +1,8 @@
-use cpal::{
-    self,
-    traits::{DeviceTrait, HostTrait, StreamTrait},
-    BuildStreamError, FromSample, Sample, SizedSample, Stream,
-};
-use std::{
-    f32::consts,
-    sync::mpsc::{channel, Sender},
-    thread,
-};
+use core::{error::Error, fmt};
 
 /// Structure for general-purpose registers.
 /// Simplies accessing them from instructions.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VariableRegisters {
     pub v0: u8,
     pub v1: u8,
@@ -100,6 +91,12 @@ impl VariableRegisters {
     }
 }
 
+impl Default for VariableRegisters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Contains helpful methods for parsing instructions
 #[derive(Debug)]
 pub struct Instruction(pub u16);
@@ -107,7 +104,7 @@ pub struct Instruction(pub u16);
 impl Instruction {
     // Group of 4 bits. Index from most to least significant
     pub fn get_nib(&self, index: u8) -> u8 {
-        ((self.0 & (0x000f << 4 * (3 - index as i8))) >> (4 * (3 - index as i8))) as u8
+        ((self.0 & (0x000f << (4 * (3 - index as i8)))) >> (4 * (3 - index as i8))) as u8
     }
 
     // Lowest 8 bits (lower byte)
@@ -117,143 +114,145 @@ impl Instruction {
 
     // Lowest 12 bits
     pub fn get_addr(&self) -> u16 {
-        (self.0 & 0x0fff) as u16
+        self.0 & 0x0fff
     }
 
     pub fn from_u16(n: u16) -> Self {
         Instruction(n)
     }
-}
 
-/// Behavior Configurations for conflicting implementations
-#[derive(Debug)]
-pub struct BehaviorConfig {
-    /// Does it reset V(F) register to 0 for 8xy1, 8xy2 and 8xy3 instructions
-    pub vf_reset: bool,
-    /// Does it increment I register on save and load operations
-    pub increment_i_on_save_load: bool,
-}
+    /// Registers read and written (bit N => V(N)), counting V(F) as written
+    /// whenever some quirk or result can change it
+    pub fn registers(&self) -> (u16, u16) {
+        let (x, y) = (1 << self.get_nib(1), 1 << self.get_nib(2));
+        let up_to_x = u16::MAX >> (15 - self.get_nib(1));
+        const VF: u16 = 1 << 0xF;
 
-impl BehaviorConfig {
-    pub fn default() -> Self {
-        BehaviorConfig {
-            vf_reset: true,
-            increment_i_on_save_load: true,
+        match (self.get_nib(0), self.get_nib(3), self.get_lbyte()) {
+            (0x3 | 0x4, _, _) => (x, 0),
+            (0x5 | 0x9, _, _) => (x | y, 0),
+            (0x6 | 0xC, _, _) => (0, x),
+            (0x7, _, _) => (x, x),
+            (0x8, 0x0, _) => (y, x),
+            (0x8, 0x1..=0x7 | 0xE, _) => (x | y, x | VF),
+            (0xB, _, _) => (1 | x, 0),
+            (0xD, _, _) => (x | y, VF),
+            (0xE, _, 0x9E | 0xA1) => (x, 0),
+            (0xF, _, 0x07 | 0x0A) => (0, x),
+            (0xF, _, 0x15 | 0x18 | 0x1E | 0x29 | 0x33) => (x, 0),
+            (0xF, _, 0x55) => (up_to_x, 0),
+            (0xF, _, 0x65) => (0, up_to_x),
+            _ => (0, 0),
         }
     }
 }
 
-/// Messages to send to the beeper thread
-enum BeeperMessage {
-    Play,
-    Pause,
-    Stop,
-}
+/// Returned when an address or range falls outside of the 4KB memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBounds;
 
-/// A simple Beeper implementation using `cpal` crate
-#[derive(Debug)]
-pub struct Beeper {
-    tx: Sender<BeeperMessage>,
-    is_on: bool,
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "address is outside of memory")
+    }
 }
 
-impl Beeper {
-    pub fn new() -> Self {
-        let host = cpal::default_host();
-        let device = host.default_output_device().unwrap();
-        let config = device.default_output_config().unwrap();
-
-        let (tx, rx) = channel::<BeeperMessage>();
-
-        // A thread dedicated to making noise
-        thread::spawn(move || {
-            let stream = match config.sample_format() {
-                cpal::SampleFormat::F32 => Self::create_stream::<f32>(&device, &config.into()),
-                cpal::SampleFormat::I16 => Self::create_stream::<i16>(&device, &config.into()),
-                cpal::SampleFormat::U16 => Self::create_stream::<u16>(&device, &config.into()),
-                cpal::SampleFormat::I8 => Self::create_stream::<i8>(&device, &config.into()),
-                cpal::SampleFormat::I32 => Self::create_stream::<i32>(&device, &config.into()),
-                cpal::SampleFormat::I64 => Self::create_stream::<i64>(&device, &config.into()),
-                cpal::SampleFormat::U8 => Self::create_stream::<u8>(&device, &config.into()),
-                cpal::SampleFormat::U64 => Self::create_stream::<u64>(&device, &config.into()),
-                cpal::SampleFormat::F64 => Self::create_stream::<f64>(&device, &config.into()),
-                _ => panic!(),
-            };
+impl Error for OutOfBounds {}
 
-            match stream {
-                Ok(stream) => {
-                    stream.pause().unwrap();
-                    loop {
-                        match rx.recv() {
-                            Ok(BeeperMessage::Play) => stream.play().unwrap(),
-                            Ok(BeeperMessage::Pause) => stream.pause().unwrap(),
-                            Ok(BeeperMessage::Stop) => {
-                                stream.pause().unwrap();
-                                return;
-                            }
-                            Err(err) => panic!("{}", err),
-                        }
-                    }
-                }
-                Err(err) => panic!("{}", err),
-            }
-        });
+/// Returned when loading a save state that is corrupt or from another version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidState;
 
-        Beeper { tx, is_on: false }
+impl fmt::Display for InvalidState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid save state")
     }
+}
 
-    pub fn update(&mut self, state: bool) {
-        if state != self.is_on {
-            if state {
-                self.tx.send(BeeperMessage::Play).unwrap();
-            } else {
-                self.tx.send(BeeperMessage::Pause).unwrap();
-            }
-            self.is_on = state;
-        }
-    }
+impl Error for InvalidState {}
 
-    pub fn stop(&self) {
-        self.tx.send(BeeperMessage::Stop).unwrap();
-    }
+/// Behavior Configurations for conflicting implementations
+///
+/// https://github.com/chip-8/chip-8-database/blob/master/database/quirks.json
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BehaviorConfig {
+    /// Does it reset V(F) register to 0 for 8xy1, 8xy2 and 8xy3 instructions
+    pub vf_reset: bool,
+    /// Does it increment I register on save and load operations
+    pub increment_i_on_save_load: bool,
+    /// Do 8xy6 and 8xyE shift V(x) in place, ignoring V(y) (CHIP-48 and SUPER-CHIP)
+    pub shift: bool,
+    /// Does Bnnn jump to nnn + V(x) (x being the highest nibble of nnn) instead of nnn + V(0)
+    pub jump: bool,
+    /// Do sprites wrap around the edges of the screen instead of being clipped
+    pub wrap: bool,
+}
 
-    fn create_stream<T>(
-        device: &cpal::Device,
-        config: &cpal::StreamConfig,
-    ) -> Result<Stream, BuildStreamError>
-    where
-        T: SizedSample + FromSample<f32>,
-    {
-        let sample_rate = config.sample_rate.0 as f32;
-        let channels = config.channels as usize;
+impl BehaviorConfig {
+    /// Platform IDs understood by `BehaviorConfig::for_platform`
+    pub const PLATFORMS: [&'static str; 7] = [
+        "originalChip8",
+        "hybridVIP",
+        "modernChip8",
+        "chip48",
+        "superchip1",
+        "superchip",
+        "xochip",
+    ];
 
-        let mut sample_clock = 0f32;
-        let mut next_value = move || {
-            sample_clock = (sample_clock + 1.0) % sample_rate;
-            (sample_clock * 440.0 * 2.0 * consts::PI / sample_rate).sin()
+    /// Quirks of a platform, by its ID in the CHIP-8 database (eg: `"superchip"`).
+    /// Only the quirks of the original CHIP-8 instructions are set.
+    ///
+    /// https://github.com/chip-8/chip-8-database/blob/master/database/platforms.json
+    pub fn for_platform(id: &str) -> Option<Self> {
+        let original = BehaviorConfig::default();
+        let config = match id {
+            "originalChip8" | "hybridVIP" => original,
+            "modernChip8" => BehaviorConfig {
+                vf_reset: false,
+                increment_i_on_save_load: false,
+                ..original
+            },
+            "chip48" => BehaviorConfig {
+                vf_reset: false,
+                shift: true,
+                jump: true,
+                ..original
+            },
+            "superchip1" | "superchip" => BehaviorConfig {
+                vf_reset: false,
+                increment_i_on_save_load: false,
+                shift: true,
+                jump: true,
+                ..original
+            },
+            "xochip" => BehaviorConfig {
+                vf_reset: false,
+                wrap: true,
+                ..original
+            },
+            _ => return None,
         };
+        Some(config)
+    }
 
-        let err_fn = |err| panic!("{}", err);
-
-        device.build_output_stream(
-            config,
-            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
-                Self::write_data(data, channels, &mut next_value)
-            },
-            err_fn,
-            None,
-        )
+    /// The first platform in `PLATFORMS` with exactly these quirks
+    pub fn platform(&self) -> Option<&'static str> {
+        Self::PLATFORMS
+            .into_iter()
+            .find(|id| Self::for_platform(id) == Some(*self))
     }
+}
 
-    fn write_data<T>(output: &mut [T], channels: usize, next_sample: &mut dyn FnMut() -> f32)
-    where
-        T: Sample + FromSample<f32>,
-    {
-        for frame in output.chunks_mut(channels) {
-            let value: T = T::from_sample(next_sample());
-            for sample in frame.iter_mut() {
-                *sample = value;
-            }
+impl Default for BehaviorConfig {
+    /// The original COSMAC VIP interpreter
+    fn default() -> Self {
+        BehaviorConfig {
+            vf_reset: true,
+            increment_i_on_save_load: true,
+            shift: false,
+            jump: false,
+            wrap: false,
         }
     }
 }
@@ -0,0 +1,142 @@
+use chip8::{
+    frontend::{
+        browser::{self, Browser, Recent, RECENT_LIMIT},
+        text::Canvas,
+    },
+    palette::Palette,
+    rom,
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// A fresh directory with a few files, removed when dropped
+struct Dir(PathBuf);
+
+impl Dir {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("chip8-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("games")).unwrap();
+        fs::write(dir.join("games/pong.ch8"), [0x12, 0x00]).unwrap();
+        fs::write(dir.join("car.sc8"), [0x00, 0xFF, 0x12, 0x02]).unwrap();
+        fs::write(dir.join("readme.txt"), "Not a ROM").unwrap();
+        Dir(dir)
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_scan() {
+    let dir = Dir::new("scan");
+    let files = browser::scan(&dir.0).unwrap();
+
+    let names: Vec<_> = files.iter().map(|file| file.name.as_str()).collect();
+    let pong = Path::new("games").join("pong.ch8").display().to_string();
+    assert_eq!(names, ["car.sc8", pong.as_str()]);
+    assert_eq!(files[1].size, 2);
+    assert_eq!(files[1].sha1, rom::sha1(&[0x12, 0x00]));
+    assert_eq!(files[1].path, dir.0.join("games/pong.ch8"));
+
+    assert_eq!(browser::scan(dir.0.join("missing")).unwrap(), []);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_scan_skips_unreadable() {
+    let dir = Dir::new("scan-unreadable");
+    std::os::unix::fs::symlink(dir.0.join("gone.ch8"), dir.0.join("broken.ch8")).unwrap();
+
+    let files = browser::scan(&dir.0).unwrap();
+    let names: Vec<_> = files.iter().map(|file| file.name.as_str()).collect();
+    let pong = Path::new("games").join("pong.ch8").display().to_string();
+    assert_eq!(names, ["car.sc8", pong.as_str()]);
+}
+
+#[test]
+fn test_recent() {
+    let dir = Dir::new("recent");
+    let path = dir.0.join("state/recent");
+
+    assert_eq!(Recent::load(&path).unwrap(), Recent::default());
+
+    let mut recent = Recent::default();
+    recent.push(dir.0.join("car.sc8"));
+    recent.push(dir.0.join("games/pong.ch8"));
+    recent.push(dir.0.join("car.sc8"));
+    recent.save(&path).unwrap();
+
+    let loaded = Recent::load(&path).unwrap();
+    assert_eq!(loaded, recent);
+    assert_eq!(loaded.roms.len(), 2);
+    assert!(loaded.roms[0].ends_with("car.sc8"));
+
+    for n in 0..RECENT_LIMIT + 2 {
+        recent.push(format!("missing-{}.ch8", n));
+    }
+    assert_eq!(recent.roms.len(), RECENT_LIMIT);
+}
+
+#[test]
+fn test_browser() {
+    let dir = Dir::new("browser");
+    let mut recent = Recent::default();
+    recent.push(dir.0.join("games/pong.ch8"));
+    recent.push("missing.ch8");
+
+    let mut browser = Browser::new("ROMs".to_owned(), browser::scan(&dir.0).unwrap(), &recent);
+
+    // Recently played first, and missing ROMs are skipped
+    assert_eq!(browser.files().len(), 2);
+    assert_eq!(browser.selected().unwrap().name, recent_name());
+    assert!(browser.is_recent(0));
+    assert!(!browser.is_recent(1));
+
+    browser.move_by(-1);
+    assert_eq!(browser.selected_index(), 0);
+    browser.move_by(5);
+    assert_eq!(browser.selected().unwrap().name, "car.sc8");
+    browser.select_first();
+    assert_eq!(browser.selected_index(), 0);
+    browser.select_last();
+    assert_eq!(browser.selected_index(), 1);
+
+    let empty = Browser::new("ROMs".to_owned(), vec![], &Recent::default());
+    assert!(empty.selected().is_none());
+}
+
+fn recent_name() -> String {
+    Path::new("games").join("pong.ch8").display().to_string()
+}
+
+#[test]
+fn test_render() {
+    let dir = Dir::new("render");
+    let browser = Browser::new(
+        "ROMs".to_owned(),
+        browser::scan(&dir.0).unwrap(),
+        &Recent::default(),
+    );
+
+    let (width, height) = (1024, 512);
+    let mut buffer = vec![0; width * height];
+    let palette = Palette::new(0x000000, 0xFFFFFF);
+    browser.render(
+        &mut Canvas {
+            buffer: &mut buffer,
+            width,
+        },
+        &palette,
+    );
+
+    // The selected row is highlighted across the whole width
+    let highlighted = (0..height).filter(|y| buffer[y * width + width - 1] == 0xFFFFFF);
+    assert!(highlighted.count() > 0);
+    assert!(Browser::visible_rows(height) >= 20);
+}
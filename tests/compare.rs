@@ -0,0 +1,114 @@
+use chip8::{
+    compare::{Difference, Divergence, Lockstep},
+    frontend::{self, NullAudio, NullInput, PairRenderer},
+    structs::BehaviorConfig,
+    Display,
+};
+use std::fs;
+
+#[test]
+fn test_same_behavior_never_diverges() {
+    let program = fs::read("roms/games/tetris.ch8").unwrap();
+    let mut lockstep = Lockstep::new(
+        &program,
+        BehaviorConfig::default(),
+        BehaviorConfig::default(),
+    );
+
+    for _ in 0..300 {
+        lockstep.run_frame(11, 0b0010_0000);
+    }
+
+    assert_eq!(lockstep.frame(), 300);
+    assert_eq!(lockstep.divergence(), None);
+    assert_eq!(lockstep.left().display(), lockstep.right().display());
+}
+
+#[test]
+fn test_first_divergence() {
+    let program = [
+        0x60, 0x02, // 0x200: V0 = 2
+        0x61, 0x03, // 0x202: V1 = 3
+        0x80, 0x06, // 0x204: V0 >>= 1, same either way
+        0x80, 0x16, // 0x206: V0 = V1 >> 1 (or V0 >> 1)
+        0x12, 0x08, // 0x208: loop
+    ];
+    let shift = BehaviorConfig {
+        shift: true,
+        ..BehaviorConfig::default()
+    };
+    let mut lockstep = Lockstep::new(program, BehaviorConfig::default(), shift);
+
+    lockstep.run_frame(2, 0);
+    assert_eq!(lockstep.divergence(), None);
+    lockstep.run_frame(2, 0);
+
+    assert_eq!(
+        lockstep.divergence(),
+        Some(Divergence {
+            frame: 1,
+            instruction: 3,
+            pc: 0x206,
+            opcode: 0x8016,
+            difference: Difference::Registers,
+        })
+    );
+    assert_eq!(lockstep.left().registers().v0, 1);
+    assert_eq!(lockstep.right().registers().v0, 0);
+}
+
+/// Counts frames and divergences, and closes after a fixed number of frames
+struct CountingRenderer {
+    frames_left: u32,
+    divergences: u32,
+    last: Option<(Display, Display)>,
+}
+
+impl PairRenderer for CountingRenderer {
+    fn draw_pair(&mut self, left: &Display, right: &Display) {
+        self.frames_left -= 1;
+        self.last = Some((*left, *right));
+    }
+
+    fn diverged(&mut self, _divergence: &Divergence) {
+        self.divergences += 1;
+    }
+
+    fn is_open(&self) -> bool {
+        self.frames_left > 0
+    }
+}
+
+#[test]
+fn test_run_compare() {
+    // Draws a sprite across the right edge
+    let program = [0x60, 62, 0x61, 0, 0xA0, 0x00, 0xD0, 0x15, 0x12, 0x08];
+    let wrap = BehaviorConfig {
+        wrap: true,
+        ..BehaviorConfig::default()
+    };
+    let mut lockstep = Lockstep::new(program, BehaviorConfig::default(), wrap);
+    let mut renderer = CountingRenderer {
+        frames_left: 10,
+        divergences: 0,
+        last: None,
+    };
+
+    frontend::run_compare(
+        &mut lockstep,
+        11,
+        &mut renderer,
+        &mut NullAudio,
+        &mut NullInput,
+    );
+
+    assert_eq!(lockstep.frame(), 10);
+    assert_eq!(renderer.divergences, 1);
+    assert_eq!(
+        lockstep.divergence().map(|d| d.difference),
+        Some(Difference::Display)
+    );
+
+    let (left, right) = renderer.last.unwrap();
+    assert!(!left[0][0] && right[0][0]);
+}
@@ -0,0 +1,129 @@
+use chip8::{
+    config::{Config, Settings},
+    frontend::{keymap::Keymap, viewport::Grid},
+    palette::Palette,
+    phosphor::Persistence,
+    rom,
+};
+use std::{env, fs};
+
+#[test]
+fn test_parse_defaults() {
+    let config = Config::parse(
+        r#"
+        ipf = 15
+        vf_reset = false
+        palette = "amber"
+        persistence = "decay:0.5"
+        keymap = "azerty"
+        scale = 8
+        grid = "scanlines"
+        fullscreen = true
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.defaults,
+        Settings {
+            ipf: Some(15),
+            vf_reset: Some(false),
+            increment_i: None,
+            palette: Some(Palette::AMBER),
+            persistence: Some(Persistence::Decay(0.5)),
+            keymap: Keymap::preset("azerty"),
+            scale: Some(8),
+            grid: Some(Grid::Scanlines),
+            fullscreen: Some(true),
+            ..Settings::default()
+        }
+    );
+    assert!(config.roms.is_empty());
+}
+
+#[test]
+fn test_parse_rejects_invalid_settings() {
+    assert!(Config::parse("speed = 3").is_err());
+    assert!(Config::parse("ipf = \"fast\"").is_err());
+    assert!(Config::parse("palette = \"sepia\"").is_err());
+    assert!(Config::parse("grid = \"dots\"").is_err());
+    assert!(Config::parse("[roms.abc]\nspeed = 3").is_err());
+}
+
+#[test]
+fn test_settings_for_rom() {
+    let tetris = fs::read("roms/games/tetris.ch8").unwrap();
+    let ibm = fs::read("roms/tests/ibm.ch8").unwrap();
+    assert_eq!(
+        rom::sha1(&tetris),
+        "5f518084744bf3cb8733f6e5454dfd1634320563"
+    );
+
+    let config = Config::parse(
+        r#"
+        ipf = 15
+        palette = "amber"
+
+        [roms.5F518084744BF3CB8733F6E5454DFD1634320563]
+        ipf = 30
+        "#,
+    )
+    .unwrap();
+
+    let settings = config.settings_for(&tetris);
+    assert_eq!(settings.ipf, Some(30));
+    assert_eq!(settings.palette, Some(Palette::AMBER));
+
+    assert_eq!(config.settings_for(&ibm), config.defaults);
+}
+
+#[test]
+fn test_overridden_by() {
+    let file = Settings {
+        ipf: Some(15),
+        vf_reset: Some(true),
+        ..Settings::default()
+    };
+    let cli = Settings {
+        ipf: Some(20),
+        scale: Some(4),
+        ..Settings::default()
+    };
+
+    assert_eq!(
+        file.overridden_by(cli),
+        Settings {
+            ipf: Some(20),
+            vf_reset: Some(true),
+            scale: Some(4),
+            ..Settings::default()
+        }
+    );
+}
+
+#[test]
+fn test_load_relative_keymap() {
+    let dir = env::temp_dir().join(format!("chip8-config-{}", std::process::id()));
+    fs::create_dir_all(dir.join("keymaps")).unwrap();
+    fs::write(dir.join("keymaps/mine.keymap"), "preset = azerty\n").unwrap();
+    fs::write(
+        dir.join("config.toml"),
+        "keymap = \"keymaps/mine.keymap\"\n[roms.abc]\nkeymap = \"keymaps/mine.keymap\"\n",
+    )
+    .unwrap();
+
+    // Found next to the configuration file, wherever it is run from
+    let config = Config::load(dir.join("config.toml"));
+    fs::remove_dir_all(&dir).unwrap();
+    let config = config.unwrap();
+    assert_eq!(config.defaults.keymap, Keymap::preset("azerty"));
+    assert_eq!(config.roms["abc"].keymap, Keymap::preset("azerty"));
+}
+
+#[test]
+fn test_load_missing_file() {
+    assert_eq!(
+        Config::load("does/not/exist.toml").unwrap(),
+        Config::default()
+    );
+}
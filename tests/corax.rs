@@ -3,15 +3,13 @@ use std::fs;
 
 #[test]
 fn test_corax_output() {
-    let mut instr: u32 = 0;
     let mut chip = Chip::new(
         fs::read("roms/tests/corax.ch8").unwrap(),
         BehaviorConfig::default(),
     );
 
     for _ in 0..INSTRUCTION_COUNT {
-        chip.process_instruction(vec![]);
-        instr += 1;
+        chip.process_instruction();
     }
 
     assert_eq!(chip.display(), CORAX_DISPLAY);
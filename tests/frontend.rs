@@ -0,0 +1,509 @@
+use chip8::{
+    frontend::{
+        self, Action, AudioSink, Hotkey, InputSource, NullAudio, NullInput, Renderer, Status,
+        FAST_FORWARD_FRAMES, SLOW_MOTION_FRAMES,
+    },
+    quirks::Quirk,
+    structs::BehaviorConfig,
+    Chip, Display,
+};
+use std::{collections::VecDeque, fs};
+
+/// Keeps the last frame, and closes after a fixed number of iterations
+struct RecordingRenderer {
+    iterations_left: u32,
+    draws: u32,
+    last_frame: Option<Display>,
+}
+
+impl Renderer for RecordingRenderer {
+    fn draw(&mut self, display: &Display) {
+        self.draws += 1;
+        self.last_frame = Some(*display);
+        self.update();
+    }
+
+    fn update(&mut self) {
+        self.iterations_left -= 1;
+    }
+
+    fn is_open(&self) -> bool {
+        self.iterations_left > 0
+    }
+}
+
+#[test]
+fn test_run_with_custom_renderer() {
+    let mut chip = Chip::new(
+        fs::read("roms/tests/ibm.ch8").unwrap(),
+        BehaviorConfig::default(),
+    );
+    let mut renderer = RecordingRenderer {
+        iterations_left: 100,
+        draws: 0,
+        last_frame: None,
+    };
+
+    frontend::run(&mut chip, 11, &mut renderer, &mut NullAudio, &mut NullInput);
+
+    assert!(renderer.draws > 0);
+    assert_eq!(renderer.last_frame, Some(chip.display()));
+}
+
+/// Asks to quit straight away
+struct QuitInput;
+
+impl InputSource for QuitInput {
+    fn held_keys(&mut self) -> u16 {
+        0
+    }
+
+    fn quit_requested(&mut self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn test_run_stops_on_quit() {
+    let mut chip = Chip::new(
+        fs::read("roms/tests/ibm.ch8").unwrap(),
+        BehaviorConfig::default(),
+    );
+
+    frontend::run(
+        &mut chip,
+        11,
+        &mut frontend::NullRenderer,
+        &mut NullAudio,
+        &mut QuitInput,
+    );
+
+    assert_eq!(chip.pc(), 0x200);
+}
+
+/// Holds down a hotkey the whole time
+struct HotkeyInput(Hotkey);
+
+impl InputSource for HotkeyInput {
+    fn held_keys(&mut self) -> u16 {
+        0
+    }
+
+    fn hotkey_held(&mut self, hotkey: Hotkey) -> bool {
+        hotkey == self.0
+    }
+}
+
+/// The chip after running `frames` frames of a single instruction directly.
+/// `ibm.ch8` settles into a loop after 21 instructions.
+fn after_frames(frames: u32) -> Chip {
+    let mut chip = Chip::new(
+        fs::read("roms/tests/ibm.ch8").unwrap(),
+        BehaviorConfig::default(),
+    );
+    for _ in 0..frames {
+        chip.run_frame(1);
+    }
+    chip
+}
+
+#[test]
+fn test_run_speed_hotkeys() {
+    for (hotkey, frames) in [
+        (Hotkey::FastForward, 4 * FAST_FORWARD_FRAMES),
+        (Hotkey::SlowMotion, 4 / SLOW_MOTION_FRAMES),
+    ] {
+        let mut chip = after_frames(0);
+        let mut renderer = RecordingRenderer {
+            iterations_left: 4,
+            draws: 0,
+            last_frame: None,
+        };
+
+        frontend::run(
+            &mut chip,
+            1,
+            &mut renderer,
+            &mut NullAudio,
+            &mut HotkeyInput(hotkey),
+        );
+
+        assert_eq!(chip.pc(), after_frames(frames).pc(), "{:?}", hotkey);
+    }
+}
+
+/// Sends actions on given frames (counted from 0)
+struct ScriptedInput {
+    frame: u32,
+    script: VecDeque<(u32, Action)>,
+}
+
+impl ScriptedInput {
+    fn new(script: &[(u32, Action)]) -> Self {
+        ScriptedInput {
+            frame: 0,
+            script: script.iter().copied().collect(),
+        }
+    }
+}
+
+impl InputSource for ScriptedInput {
+    /// Asked once per frame, after the actions
+    fn held_keys(&mut self) -> u16 {
+        self.frame += 1;
+        0
+    }
+
+    fn next_action(&mut self) -> Option<Action> {
+        match self.script.front() {
+            Some((frame, _)) if *frame <= self.frame => self.script.pop_front().map(|(_, a)| a),
+            _ => None,
+        }
+    }
+}
+
+/// Keeps the messages, and closes after a fixed number of frames
+struct MessageRenderer {
+    frames_left: u32,
+    messages: Vec<String>,
+}
+
+impl MessageRenderer {
+    fn new(frames: u32) -> Self {
+        MessageRenderer {
+            frames_left: frames,
+            messages: vec![],
+        }
+    }
+}
+
+impl Renderer for MessageRenderer {
+    fn draw(&mut self, _display: &Display) {
+        self.update();
+    }
+
+    fn update(&mut self) {
+        self.frames_left -= 1;
+    }
+
+    fn is_open(&self) -> bool {
+        self.frames_left > 0
+    }
+
+    fn message(&mut self, text: &str) {
+        self.messages.push(text.to_owned());
+    }
+}
+
+/// Runs ibm.ch8 at one instruction per frame with a script of actions
+fn run_script(frames: u32, script: &[(u32, Action)]) -> (Chip, Vec<String>) {
+    let mut chip = after_frames(0);
+    let mut renderer = MessageRenderer::new(frames);
+    frontend::run(
+        &mut chip,
+        1,
+        &mut renderer,
+        &mut NullAudio,
+        &mut ScriptedInput::new(script),
+    );
+    (chip, renderer.messages)
+}
+
+#[test]
+fn test_pause_and_step() {
+    let (chip, messages) = run_script(10, &[(2, Action::Pause)]);
+    assert_eq!(chip.pc(), after_frames(2).pc());
+    assert_eq!(messages, ["Paused"]);
+
+    let (chip, messages) = run_script(
+        10,
+        &[
+            (2, Action::StepFrame),
+            (4, Action::StepFrame),
+            (5, Action::StepFrame),
+            (6, Action::Pause),
+        ],
+    );
+    // Paused after 2 frames, stepped twice, then ran frames 6 to 9
+    assert_eq!(chip.pc(), after_frames(2 + 2 + 4).pc());
+    assert_eq!(messages, ["Paused", "Next frame", "Next frame", "Resumed"]);
+}
+
+#[test]
+fn test_reset() {
+    let (chip, messages) = run_script(10, &[(8, Action::Reset)]);
+
+    // Reset before frame 8 ran, then ran frames 8 and 9
+    assert_eq!(chip.pc(), after_frames(2).pc());
+    assert_eq!(chip.registers(), after_frames(2).registers());
+    assert_eq!(messages, ["Reset"]);
+
+    // Quirks toggled since are reset too
+    let (chip, messages) = run_script(
+        3,
+        &[(0, Action::ToggleQuirk(Quirk::Shift)), (1, Action::Reset)],
+    );
+    assert_eq!(chip.behavior(), BehaviorConfig::default());
+    assert_eq!(messages, ["shift on", "Reset"]);
+}
+
+#[test]
+fn test_reset_quirk_usage() {
+    // 6101, then 8016 (a shift) and a jump back to it
+    let rom = vec![0x61, 0x01, 0x80, 0x16, 0x12, 0x02];
+    let mut chip = Chip::new(rom, BehaviorConfig::default());
+    chip.detect_quirks();
+    frontend::run(
+        &mut chip,
+        1,
+        &mut MessageRenderer::new(6),
+        &mut NullAudio,
+        &mut ScriptedInput::new(&[(4, Action::Reset)]),
+    );
+
+    // Only the shift run after the reset is left
+    let usage = chip.quirk_usage().unwrap();
+    assert_eq!(usage.get(Quirk::Shift).count, 1);
+}
+
+#[test]
+fn test_speed_actions() {
+    let mut chip = after_frames(0);
+    let mut renderer = MessageRenderer::new(3);
+    let script = [
+        (0, Action::SpeedUp),
+        (0, Action::SpeedUp),
+        (1, Action::SpeedDown),
+    ];
+    frontend::run(
+        &mut chip,
+        4,
+        &mut renderer,
+        &mut NullAudio,
+        &mut ScriptedInput::new(&script),
+    );
+
+    // 8 instructions in the first frame, 6 in the others
+    assert_eq!(chip.pc(), after_frames(8 + 6 + 6).pc());
+    assert_eq!(
+        renderer.messages,
+        [
+            "Speed 150% (6 instructions per frame)",
+            "Speed 200% (8 instructions per frame)",
+            "Speed 150% (6 instructions per frame)",
+        ]
+    );
+
+    // Never below one instruction per frame
+    let slower = [(0, Action::SpeedDown); 10];
+    let (chip, messages) = run_script(3, &slower);
+    assert_eq!(chip.pc(), after_frames(3).pc());
+    assert_eq!(messages[9], "Speed 10% (1 instructions per frame)");
+
+    // Saturating rather than overflowing
+    let mut chip = after_frames(0);
+    let mut renderer = MessageRenderer::new(1);
+    let mut script = vec![(0, Action::Pause)];
+    script.extend([(0, Action::SpeedUp); 4]);
+    frontend::run(
+        &mut chip,
+        u32::MAX / 2,
+        &mut renderer,
+        &mut NullAudio,
+        &mut ScriptedInput::new(&script),
+    );
+    assert_eq!(
+        renderer.messages[4],
+        format!("Speed 800% ({} instructions per frame)", u32::MAX)
+    );
+}
+
+#[test]
+fn test_toggle_quirk() {
+    let (chip, messages) = run_script(
+        3,
+        &[
+            (0, Action::ToggleQuirk(Quirk::Shift)),
+            (1, Action::ToggleQuirk(Quirk::VfReset)),
+        ],
+    );
+
+    assert!(chip.behavior().shift);
+    assert!(!chip.behavior().vf_reset);
+    assert_eq!(messages, ["shift on", "vf_reset off"]);
+}
+
+/// Keeps every volume set
+#[derive(Default)]
+struct VolumeAudio {
+    volumes: Vec<f32>,
+}
+
+impl AudioSink for VolumeAudio {
+    fn set_playing(&mut self, _playing: bool) {}
+
+    fn set_volume(&mut self, volume: f32) {
+        self.volumes.push(volume);
+    }
+}
+
+#[test]
+fn test_volume_actions() {
+    let mut chip = after_frames(0);
+    let mut renderer = MessageRenderer::new(3);
+    let mut audio = VolumeAudio::default();
+    let script = [
+        (0, Action::VolumeDown),
+        (0, Action::VolumeDown),
+        (0, Action::Mute),
+        (1, Action::Mute),
+        (1, Action::Mute),
+        (2, Action::VolumeUp),
+        (2, Action::VolumeUp),
+        (2, Action::VolumeUp),
+    ];
+    frontend::run(
+        &mut chip,
+        1,
+        &mut renderer,
+        &mut audio,
+        &mut ScriptedInput::new(&script),
+    );
+
+    // Changing the volume unmutes, and it stops at 100%
+    assert_eq!(audio.volumes, [0.9, 0.8, 0.0, 0.8, 0.0, 0.9, 1.0, 1.0]);
+    assert_eq!(
+        renderer.messages,
+        [
+            "Volume 90%",
+            "Volume 80%",
+            "Muted",
+            "Volume 80%",
+            "Muted",
+            "Volume 90%",
+            "Volume 100%",
+            "Volume 100%",
+        ]
+    );
+}
+
+#[test]
+fn test_screenshot_unsupported() {
+    let (_, messages) = run_script(2, &[(0, Action::Screenshot)]);
+    assert_eq!(messages, ["Screenshots aren't supported here"]);
+}
+
+#[test]
+fn test_function_keys() {
+    assert_eq!(
+        frontend::function_key_action(1),
+        Some(Action::ToggleQuirk(Quirk::VfReset))
+    );
+    assert_eq!(
+        frontend::function_key_action(5),
+        Some(Action::ToggleQuirk(Quirk::Wrap))
+    );
+    assert_eq!(frontend::function_key_action(6), Some(Action::Pause));
+    assert_eq!(frontend::function_key_action(8), Some(Action::Reset));
+    assert_eq!(
+        frontend::function_key_action(11),
+        Some(Action::ToggleFullscreen)
+    );
+    assert_eq!(frontend::function_key_action(13), None);
+}
+
+/// Keeps every status
+struct StatusRenderer {
+    frames_left: u32,
+    statuses: Vec<Status>,
+}
+
+impl Renderer for StatusRenderer {
+    fn draw(&mut self, _display: &Display) {
+        self.update();
+    }
+
+    fn update(&mut self) {
+        self.frames_left -= 1;
+    }
+
+    fn is_open(&self) -> bool {
+        self.frames_left > 0
+    }
+
+    fn status(&mut self, status: &Status) {
+        self.statuses.push(*status);
+    }
+}
+
+#[test]
+fn test_status() {
+    let mut chip = after_frames(0);
+    let mut renderer = StatusRenderer {
+        frames_left: 3,
+        statuses: vec![],
+    };
+    let script = [(1, Action::SpeedUp), (2, Action::Pause)];
+    frontend::run(
+        &mut chip,
+        10,
+        &mut renderer,
+        &mut NullAudio,
+        &mut ScriptedInput::new(&script),
+    );
+
+    let status = |frames_run, instructions_per_frame, speed, paused| Status {
+        frames_run,
+        instructions_per_frame,
+        speed,
+        paused,
+        behavior: BehaviorConfig::default(),
+        held_keys: 0,
+        waiting_for_key: None,
+    };
+    assert_eq!(
+        renderer.statuses,
+        [
+            status(1, 10, 100, false),
+            status(1, 15, 150, false),
+            status(0, 15, 150, true),
+        ]
+    );
+
+    // Toggling the overlay needs a renderer with one
+    let (_, messages) = run_script(2, &[(0, Action::ToggleOverlay)]);
+    assert_eq!(messages, ["No overlay here"]);
+}
+
+/// Holds the same keys down all along
+struct HeldInput(u16);
+
+impl InputSource for HeldInput {
+    fn held_keys(&mut self) -> u16 {
+        self.0
+    }
+}
+
+#[test]
+fn test_status_keys() {
+    // Fx0A waits for V3, with key 5 held but not yet released
+    let mut chip = Chip::new(vec![0xF3, 0x0A, 0x12, 0x02], BehaviorConfig::default());
+    let mut renderer = StatusRenderer {
+        frames_left: 2,
+        statuses: vec![],
+    };
+    frontend::run(
+        &mut chip,
+        10,
+        &mut renderer,
+        &mut NullAudio,
+        &mut HeldInput(1 << 5),
+    );
+
+    let last = renderer.statuses.last().unwrap();
+    assert_eq!(last.held_keys, 1 << 5);
+    assert_eq!(last.waiting_for_key, Some(3));
+
+    let (_, messages) = run_script(2, &[(0, Action::ToggleKeypad)]);
+    assert_eq!(messages, ["No keypad here"]);
+}
@@ -0,0 +1,90 @@
+use chip8::frontend::keymap::{HostKey, Keymap};
+use std::{env, fs};
+
+#[test]
+fn test_presets() {
+    let qwerty = Keymap::default();
+    assert_eq!(qwerty.held([HostKey::Char('1')]), 1 << 0x1);
+    assert_eq!(qwerty.held([HostKey::Char('4')]), 1 << 0xC);
+    assert_eq!(qwerty.held([HostKey::Char('x')]), 1 << 0x0);
+
+    let azerty = Keymap::preset("azerty").unwrap();
+    assert_eq!(azerty.held([HostKey::Char('a')]), 1 << 0x4);
+    assert_eq!(azerty.held([HostKey::Char('w')]), 1 << 0xA);
+
+    let dvorak = Keymap::preset("Dvorak").unwrap();
+    assert_eq!(dvorak.held([HostKey::Char(',')]), 1 << 0x5);
+
+    let numpad = Keymap::preset("numpad").unwrap();
+    assert_eq!(numpad.held([HostKey::Numpad(8)]), 1 << 0x8);
+    assert_eq!(numpad.held([HostKey::NumpadDecimal]), 1 << 0xF);
+
+    // Every preset covers the whole keypad
+    for name in Keymap::PRESETS {
+        let keymap = Keymap::preset(name).unwrap();
+        let all = keymap.held(keymap.bindings().iter().map(|(key, _)| *key));
+        assert_eq!(all, 0xFFFF, "{}", name);
+    }
+    assert_eq!(Keymap::preset("colemak"), None);
+}
+
+#[test]
+fn test_host_key_names() {
+    assert_eq!(HostKey::from_name("Q"), Some(HostKey::Char('q')));
+    assert_eq!(HostKey::from_name("numpad7"), Some(HostKey::Numpad(7)));
+    assert_eq!(HostKey::from_name("numpad+"), Some(HostKey::NumpadAdd));
+    assert_eq!(HostKey::from_name("UP"), Some(HostKey::Up));
+    assert_eq!(HostKey::from_name("numpad10"), None);
+    assert_eq!(HostKey::from_name("hyper"), None);
+    assert_eq!(HostKey::Numpad(3).to_string(), "numpad3");
+    assert_eq!(HostKey::Space.to_string(), "space");
+}
+
+#[test]
+fn test_parse() {
+    let keymap = Keymap::parse(
+        "# Movement on the arrows too\n\
+         preset = qwertz\n\
+         up = 2\n\
+         down = 8\n\
+         i = 2 # several keys on the same hex key\n",
+    )
+    .unwrap();
+
+    assert_eq!(keymap.held([HostKey::Char('y')]), 1 << 0xA);
+    assert_eq!(keymap.held([HostKey::Up]), 1 << 0x2);
+    assert_eq!(keymap.held([HostKey::Char('i')]), 1 << 0x2);
+    assert_eq!(keymap.held([HostKey::Char('2')]), 1 << 0x2);
+
+    let err = Keymap::parse("up = 2\nhyper = 1").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(Keymap::parse("up = 10").is_err());
+    assert!(Keymap::parse("up 2").is_err());
+    assert!(Keymap::parse("preset = colemak").is_err());
+}
+
+#[test]
+fn test_overrides_replace_bindings() {
+    let mut keymap = Keymap::default();
+    keymap.apply("q = 2\nq = 8").unwrap();
+    assert_eq!(keymap.held([HostKey::Char('q')]), 1 << 0x2 | 1 << 0x8);
+
+    // Other keys keep their bindings
+    assert_eq!(keymap.held([HostKey::Char('w')]), 1 << 0x5);
+}
+
+#[test]
+fn test_rom_overrides() {
+    let dir = env::temp_dir().join("chip8-keymap-test");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("pong.keymap"), "up = 1\ndown = 4\n").unwrap();
+
+    let mut keymap = Keymap::default();
+    keymap.apply_rom_overrides(dir.join("pong.ch8")).unwrap();
+    assert_eq!(keymap.held([HostKey::Up]), 1 << 0x1);
+
+    // No override file is fine
+    let mut keymap = Keymap::default();
+    keymap.apply_rom_overrides(dir.join("tetris.ch8")).unwrap();
+    assert_eq!(keymap, Keymap::default());
+}
@@ -0,0 +1,120 @@
+use chip8::{keypad::Keypad, structs::BehaviorConfig, Chip};
+
+#[test]
+fn test_edges() {
+    let mut keypad = Keypad::new();
+    keypad.key_down(0x5);
+    assert!(keypad.is_held(0x5));
+    assert_eq!(keypad.take_press_and_release(), None);
+
+    keypad.key_up(0x5);
+    assert!(!keypad.is_held(0x5));
+    assert_eq!(keypad.take_press_and_release(), Some(0x5));
+    assert_eq!(keypad.take_press_and_release(), None);
+
+    // Out of range keys are ignored
+    keypad.key_down(0x10);
+    assert_eq!(keypad.held(), 0);
+}
+
+#[test]
+fn test_set_held() {
+    let mut keypad = Keypad::new();
+    keypad.set_held(0b1010);
+    assert!(keypad.is_held(0x1) && keypad.is_held(0x3));
+    keypad.set_held(0b1000);
+    assert_eq!(keypad.take_press_and_release(), Some(0x1));
+    keypad.set_held(0);
+    assert_eq!(keypad.take_press_and_release(), Some(0x3));
+}
+
+/// `F30A` (wait for a key into V3), then loop forever
+fn waiting_chip() -> Chip {
+    let mut chip = Chip::new([0xF3, 0x0A, 0x12, 0x02], BehaviorConfig::default());
+    chip.process_instruction();
+    assert_eq!(chip.waiting_for_key(), Some(3));
+    chip
+}
+
+#[test]
+fn test_wait_completes_on_release() {
+    let mut chip = waiting_chip();
+
+    chip.keypad_mut().key_down(0xA);
+    chip.run_frame(10);
+    assert_eq!(chip.waiting_for_key(), Some(3));
+
+    chip.keypad_mut().key_up(0xA);
+    chip.process_instruction();
+    assert_eq!(chip.waiting_for_key(), None);
+    assert_eq!(chip.registers().get(3), 0xA);
+    assert_eq!(chip.pc(), 0x202);
+}
+
+#[test]
+fn test_wait_ignores_keys_already_held() {
+    let mut chip = Chip::new([0x00, 0xE0, 0xF3, 0x0A], BehaviorConfig::default());
+    chip.keypad_mut().key_down(0x7);
+    chip.process_instruction();
+    chip.process_instruction();
+    assert_eq!(chip.waiting_for_key(), Some(3));
+
+    // Releasing a key held since before Fx0A doesn't count
+    chip.keypad_mut().key_up(0x7);
+    chip.run_frame(10);
+    assert_eq!(chip.waiting_for_key(), Some(3));
+
+    chip.keypad_mut().key_down(0x2);
+    chip.keypad_mut().key_up(0x2);
+    chip.process_instruction();
+    assert_eq!(chip.registers().get(3), 0x2);
+}
+
+#[test]
+fn test_wait_needs_release_after_press() {
+    // Held before Fx0A, released, then pressed again: still down, so still waiting
+    let mut chip = Chip::new([0x00, 0xE0, 0xF3, 0x0A], BehaviorConfig::default());
+    chip.keypad_mut().key_down(0x7);
+    chip.process_instruction();
+    chip.process_instruction();
+    chip.keypad_mut().key_up(0x7);
+    chip.keypad_mut().key_down(0x7);
+    chip.run_frame(10);
+    assert_eq!(chip.waiting_for_key(), Some(3));
+
+    chip.keypad_mut().key_up(0x7);
+    chip.process_instruction();
+    assert_eq!(chip.waiting_for_key(), None);
+    assert_eq!(chip.registers().get(3), 0x7);
+
+    // The same when polling
+    let mut chip = Chip::new([0x00, 0xE0, 0xF3, 0x0A], BehaviorConfig::default());
+    chip.keypad_mut().set_held(1 << 0x7);
+    chip.process_instruction();
+    chip.process_instruction();
+    chip.keypad_mut().set_held(0);
+    chip.keypad_mut().set_held(1 << 0x7);
+    chip.run_frame(10);
+    assert_eq!(chip.waiting_for_key(), Some(3));
+
+    chip.keypad_mut().set_held(0);
+    chip.process_instruction();
+    assert_eq!(chip.registers().get(3), 0x7);
+}
+
+#[test]
+fn test_skip_if_held() {
+    // E09E: skip if V0 (0) is held, then E0A1: skip if not held
+    let program = [0xE0, 0x9E, 0x00, 0xE0, 0xE0, 0xA1];
+
+    let mut chip = Chip::new(program, BehaviorConfig::default());
+    chip.keypad_mut().key_down(0x0);
+    chip.process_instruction();
+    assert_eq!(chip.pc(), 0x204);
+    chip.process_instruction();
+    assert_eq!(chip.pc(), 0x206);
+
+    let mut chip = Chip::new(program, BehaviorConfig::default());
+    chip.process_instruction();
+    assert_eq!(chip.pc(), 0x202);
+}
@@ -0,0 +1,129 @@
+use chip8::{
+    lint::{lint, Extension, Finding, Quirk, Report},
+    structs::BehaviorConfig,
+};
+use std::fs;
+
+#[test]
+fn test_lint_quirks_test_rom() {
+    // The quirks test exercises every quirk
+    let report = lint(&fs::read("roms/tests/quirks.ch8").unwrap());
+    for quirk in Quirk::ALL {
+        assert!(report.matters(quirk), "{:?}", quirk);
+    }
+}
+
+#[test]
+fn test_lint_ibm() {
+    let report = lint(&fs::read("roms/tests/ibm.ch8").unwrap());
+    assert_eq!(report.findings, vec![]);
+    assert_eq!(report.suggested_profile(), "originalChip8");
+}
+
+#[test]
+fn test_lint_only_what_matters() {
+    let program = [
+        0x80, 0x11, // 0x200: V0 |= V1, VF overwritten below
+        0x6F, 0x00, // 0x202: VF = 0
+        0x8F, 0x01, // 0x204: VF |= V0, VF overwritten below
+        0x6F, 0x00, // 0x206: VF = 0
+        0x82, 0x26, // 0x208: V2 >>= 1, same register
+        0x82, 0x36, // 0x20A: V2 = V3 >> 1 (or V2 >> 1)
+        0x80, 0x12, // 0x20C: V0 &= V1
+        0x30, 0x00, // 0x20E: skip if V0 == 0, reads V0...
+        0x3F, 0x00, // 0x210: ...then VF
+        0x12, 0x10, // 0x212: loop
+    ];
+    let report = lint(&program);
+
+    assert_eq!(
+        report.findings,
+        vec![
+            (0x20A, 0x8236, Finding::Quirk(Quirk::Shift)),
+            (0x20C, 0x8012, Finding::Quirk(Quirk::VfReset)),
+        ]
+    );
+    // Shifting another register was most likely written for shift in place
+    assert_eq!(report.suggested_profile(), "chip48");
+}
+
+#[test]
+fn test_lint_i_past_memory() {
+    // I = 0xFFF, then a sprite from there, which is past the program
+    let report = lint(&[0xAF, 0xFF, 0x60, 0x3C, 0xD0, 0x0F, 0x12, 0x06]);
+    assert_eq!(
+        report.findings,
+        vec![(0x204, 0xD00F, Finding::Quirk(Quirk::Wrap))]
+    );
+
+    // I += 0xFF until it goes past 0xFFFF
+    let mut program = vec![0xAF, 0xFF, 0x60, 0xFF];
+    for _ in 0..256 {
+        program.extend([0xF0, 0x1E]);
+    }
+    program.extend([0xD0, 0x0F]);
+    lint(&program);
+}
+
+#[test]
+fn test_suggested_profile() {
+    let report = |findings: &[Finding]| Report {
+        findings: findings
+            .iter()
+            .map(|finding| (0x200, 0, *finding))
+            .collect(),
+    };
+
+    // Findings that could go either way don't rule out the original
+    let logic = report(&[
+        Finding::Quirk(Quirk::VfReset),
+        Finding::Quirk(Quirk::IncrementI),
+    ]);
+    assert_eq!(logic.suggested_profile(), "originalChip8");
+
+    let jump = report(&[Finding::Quirk(Quirk::Jump)]);
+    assert_eq!(jump.suggested_profile(), "chip48");
+    assert_eq!(jump.contradictions(&BehaviorConfig::default()), 1);
+
+    let wrap = report(&[Finding::Quirk(Quirk::Wrap)]);
+    assert_eq!(wrap.suggested_profile(), "xochip");
+
+    // Nothing has all three, so the first with the fewest off
+    let all = report(&[
+        Finding::Quirk(Quirk::Shift),
+        Finding::Quirk(Quirk::Jump),
+        Finding::Quirk(Quirk::Wrap),
+    ]);
+    assert_eq!(all.suggested_profile(), "chip48");
+
+    // Extensions narrow it down to their platforms
+    let superchip = report(&[
+        Finding::Quirk(Quirk::Shift),
+        Finding::Extension(Extension::SuperChip),
+    ]);
+    assert_eq!(superchip.suggested_profile(), "superchip");
+}
+
+#[test]
+fn test_lint_edges_and_extensions() {
+    let program = [
+        0x60, 60, // V0 = 60
+        0x61, 0, // V1 = 0
+        0xA0, 0x05, // I = font "1" (0x20, 0x60, ...), pixels in columns 1 and 2
+        0xD0, 0x15, // draw at (60, 0): fits
+        0x60, 62, // V0 = 62
+        0xD0, 0x15, // draw at (62, 0): crosses the right edge
+        0x00, 0xFF, // SUPER-CHIP high resolution
+        0x12, 0x0E, // loop
+    ];
+    let report = lint(&program);
+
+    assert_eq!(
+        report.findings,
+        vec![
+            (0x20A, 0xD015, Finding::Quirk(Quirk::Wrap)),
+            (0x20C, 0x00FF, Finding::Extension(Extension::SuperChip)),
+        ]
+    );
+    assert_eq!(report.suggested_profile(), "superchip");
+}
@@ -0,0 +1,217 @@
+use chip8::{
+    octo::{self, assembler, Cartridge, OctoError},
+    palette::Palette,
+    quirks::Quirk,
+    structs::BehaviorConfig,
+    Chip,
+};
+
+const WIDTH: u16 = 128;
+
+/// A GIF with `payload` in the bottom two bits of its pixels, the way Octo saves cartridges
+fn cartridge_gif(payload: &[u8]) -> Vec<u8> {
+    let mut bytes = (payload.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(payload);
+
+    let mut pixels: Vec<u8> = bytes
+        .iter()
+        .flat_map(|byte| [6, 4, 2, 0].map(|shift| (byte >> shift) & 3))
+        .collect();
+    let height = pixels.len().div_ceil(WIDTH as usize) as u16;
+    pixels.resize(WIDTH as usize * height as usize, 0);
+
+    let palette = [0, 0, 0, 85, 85, 85, 170, 170, 170, 255, 255, 255];
+    let mut gif = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut gif, WIDTH, height, &palette).unwrap();
+        let frame = gif::Frame::from_indexed_pixels(WIDTH, height, pixels, None);
+        encoder.write_frame(&frame).unwrap();
+    }
+    gif
+}
+
+#[test]
+fn test_decode() {
+    let json = r##"{
+        "key": "ignored",
+        "program": ": main\n  loop again",
+        "options": {
+            "tickrate": 500,
+            "fillColor": "#FFCC00",
+            "backgroundColor": "#996600",
+            "buzzColor": "#FFAA00",
+            "shiftQuirks": true,
+            "loadStoreQuirks": true,
+            "clipQuirks": false,
+            "jumpQuirks": false,
+            "logicQuirks": false,
+            "maxSize": 3584,
+            "screenRotation": 0
+        }
+    }"##;
+    let cartridge = Cartridge::decode(&cartridge_gif(json.as_bytes())).unwrap();
+
+    assert_eq!(cartridge.program, ": main\n  loop again");
+    assert_eq!(cartridge.options.tickrate, Some(500));
+    assert_eq!(
+        cartridge.options.palette(),
+        Some(Palette::new(0x996600, 0xFFCC00))
+    );
+
+    let mut behavior = BehaviorConfig::default();
+    for quirk in Quirk::ALL {
+        if let Some(set) = cartridge.options.quirk(quirk) {
+            *quirk.flag(&mut behavior) = set;
+        }
+    }
+    assert_eq!(
+        behavior,
+        BehaviorConfig {
+            vf_reset: false,
+            increment_i_on_save_load: false,
+            shift: true,
+            jump: false,
+            wrap: true,
+        }
+    );
+}
+
+#[test]
+fn test_missing_options() {
+    let cartridge = Cartridge::decode(&cartridge_gif(br#"{"program": ""}"#)).unwrap();
+
+    for quirk in Quirk::ALL {
+        assert_eq!(cartridge.options.quirk(quirk), None);
+    }
+    assert_eq!(cartridge.options.palette(), None);
+}
+
+#[test]
+fn test_not_a_cartridge() {
+    assert_eq!(
+        Cartridge::decode(&cartridge_gif(b"")),
+        Err(OctoError::NotACartridge)
+    );
+    assert!(matches!(
+        Cartridge::decode(b"not a gif"),
+        Err(OctoError::Gif(_))
+    ));
+}
+
+#[test]
+fn test_is_cartridge() {
+    assert!(octo::is_cartridge("game.gif"));
+    assert!(octo::is_cartridge("GAME.GIF"));
+    assert!(!octo::is_cartridge("game.ch8"));
+}
+
+#[test]
+fn test_assemble() {
+    let source = "
+        : main
+            v0 := 5
+            v1 += 0x10
+            v1 -= 1
+            v2 := random 0b1111
+            i := sprite # defined below
+            sprite v0 v1 3
+            if v0 == 5 then v0 += v1
+            loop
+                v3 := key
+                if v3 != 9 begin
+                    clear
+                else
+                    return
+                end
+                while v3 < v4
+            again
+        : sprite
+            0xFF -1 0b10101010
+    ";
+    #[rustfmt::skip]
+    let rom = [
+        0x60, 0x05, 0x71, 0x10, 0x71, 0xFF, 0xC2, 0x0F, 0xA2, 0x26, 0xD0, 0x13,
+        // if ... then
+        0x40, 0x05, 0x80, 0x14,
+        // loop, if ... begin ... else ... end
+        0xF3, 0x0A, 0x43, 0x09, 0x12, 0x1A, 0x00, 0xE0, 0x12, 0x1C, 0x00, 0xEE,
+        // while, comparing through vF, and again
+        0x8F, 0x40, 0x8F, 0x37, 0x4F, 0x01, 0x12, 0x26, 0x12, 0x10,
+        0xFF, 0xFF, 0xAA,
+    ];
+    assert_eq!(assembler::assemble(source), Ok(rom.to_vec()));
+}
+
+#[test]
+fn test_assemble_definitions() {
+    let source = r#"
+        :const SPEED 3
+        :alias speed v5
+        :macro twice op { op op }
+        # Right to left: SPEED * (4 / 2)
+        :calc HALF { SPEED * 4 / 2 }
+        : draw
+            speed := SPEED
+            twice clear
+            return
+        : main
+            draw
+            :unpack 0xA data
+            i := long data
+            :next target v0 := HALF
+            :byte { target & 0xFF }
+            :stringmode text "AB" { :byte { VALUE + CHAR } }
+            text "BA"
+        : data
+    "#;
+    #[rustfmt::skip]
+    let rom = [
+        // A jump to main, as it doesn't come first
+        0x12, 0x0A,
+        0x65, 0x03, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0xEE,
+        0x22, 0x02, 0x60, 0xA2, 0x61, 0x19, 0xF0, 0x00, 0x02, 0x19, 0x60, 0x06,
+        0x15, 0x43, 0x41,
+    ];
+    assert_eq!(assembler::assemble(source), Ok(rom.to_vec()));
+}
+
+#[test]
+fn test_assemble_errors() {
+    let line_of = |source| match assembler::assemble(source) {
+        Err(OctoError::Compile(reason)) => reason.split(':').next().unwrap().to_owned(),
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(line_of(""), "line 1");
+    assert_eq!(line_of(": main\n  jump nowhere"), "line 2");
+    assert_eq!(line_of(": main\n\n  v0 := 300"), "line 3");
+    assert_eq!(line_of(": main\n  loop\n  clear"), "line 2");
+    assert_eq!(line_of(": main\n  :byte \"unterminated"), "line 2");
+}
+
+#[test]
+fn test_run_cartridge() {
+    let program = "
+        : main
+            v0 := 10
+            loop
+                while v0 != 0
+                v1 += 2
+                v0 -= 1
+            again
+            if v1 > 15 then v2 := 1
+            if v1 <= 15 then v3 := 1
+        : halt
+            loop again
+    ";
+    let json = serde_json::json!({ "program": program, "options": { "tickrate": 20 } });
+    let cartridge = Cartridge::decode(&cartridge_gif(json.to_string().as_bytes())).unwrap();
+
+    let rom = cartridge.assemble().unwrap();
+    let mut chip = Chip::new(&rom, BehaviorConfig::for_platform(octo::PLATFORM).unwrap());
+    for _ in 0..100 {
+        chip.process_instruction();
+    }
+    let registers = chip.registers();
+    assert_eq!((registers.v0, registers.v1), (0, 20));
+    assert_eq!((registers.v2, registers.v3), (1, 0));
+}
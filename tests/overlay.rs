@@ -0,0 +1,155 @@
+use chip8::{
+    frontend::{
+        overlay::{self, Overlay, Stats},
+        text::Canvas,
+        Status, MESSAGE_FRAMES,
+    },
+    palette::Palette,
+    structs::BehaviorConfig,
+};
+use std::time::Duration;
+
+fn status(paused: bool) -> Status {
+    Status {
+        frames_run: if paused { 0 } else { 1 },
+        instructions_per_frame: 11,
+        speed: 100,
+        paused,
+        behavior: BehaviorConfig::default(),
+        held_keys: 0,
+        waiting_for_key: None,
+    }
+}
+
+#[test]
+fn test_measure() {
+    // Half speed: 60 frames shown but 30 run, over a second
+    let stats = Stats::measure(60, 30, 330, Duration::from_secs(1));
+    assert_eq!(stats.fps, 60.0);
+    assert_eq!(stats.instructions_per_second, 330);
+    assert_eq!(stats.speed, 50);
+
+    let stats = Stats::measure(120, 480, 4800, Duration::from_secs(2));
+    assert_eq!(stats.fps, 60.0);
+    assert_eq!(stats.instructions_per_second, 2400);
+    assert_eq!(stats.speed, 400);
+
+    assert_eq!(Stats::measure(1, 1, 1, Duration::ZERO), Stats::default());
+}
+
+#[test]
+fn test_profile_name() {
+    assert_eq!(
+        overlay::profile_name(&BehaviorConfig::default()),
+        "originalChip8"
+    );
+    let custom = BehaviorConfig {
+        shift: true,
+        ..BehaviorConfig::default()
+    };
+    assert_eq!(
+        overlay::profile_name(&custom),
+        "custom: vf_reset, increment_i_on_save_load, shift"
+    );
+    let none = BehaviorConfig {
+        vf_reset: false,
+        increment_i_on_save_load: false,
+        ..BehaviorConfig::default()
+    };
+    assert_eq!(overlay::profile_name(&none), "modernChip8");
+}
+
+#[test]
+fn test_message_times_out() {
+    let mut overlay = Overlay::new(false);
+    assert!(!overlay.needs_redraw());
+
+    overlay.message("Paused");
+    for _ in 0..MESSAGE_FRAMES {
+        overlay.status(&status(false));
+        assert_eq!(overlay.current_message(), Some("Paused"));
+        assert!(overlay.needs_redraw());
+    }
+    overlay.status(&status(false));
+    assert_eq!(overlay.current_message(), None);
+
+    // Once more to draw the display without it
+    assert!(overlay.needs_redraw());
+    assert!(!overlay.needs_redraw());
+}
+
+#[test]
+fn test_lines() {
+    let mut overlay = Overlay::new(false);
+    overlay.status(&status(false));
+    assert!(overlay.lines().is_empty());
+
+    assert!(overlay.toggle());
+    assert!(overlay.is_visible());
+    let lines = overlay.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with('%'), "{}", lines[0]);
+    assert_eq!(lines[1], "originalChip8");
+
+    overlay.status(&Status {
+        speed: 200,
+        instructions_per_frame: 22,
+        ..status(false)
+    });
+    assert_eq!(overlay.lines()[2], "Speed 200% (22 per frame)");
+
+    assert!(!overlay.toggle());
+    assert!(overlay.lines().is_empty());
+}
+
+#[test]
+fn test_render() {
+    let (width, height) = (1024, 512);
+    let palette = Palette::new(0x000000, 0xFFFFFF);
+    let lit = |buffer: &[u32], x0: usize, x1: usize, y0: usize, y1: usize| {
+        (y0..y1).any(|y| (x0..x1).any(|x| buffer[y * width + x] == 0xFFFFFF))
+    };
+
+    let mut overlay = Overlay::new(false);
+    let mut buffer = vec![0; width * height];
+    overlay.render(
+        &mut Canvas {
+            buffer: &mut buffer,
+            width,
+        },
+        &palette,
+        2,
+    );
+    assert!(!lit(&buffer, 0, width, 0, height));
+
+    // The message on the left, the statistics on the right
+    overlay.message("Reset");
+    overlay.toggle();
+    overlay.status(&status(false));
+    overlay.render(
+        &mut Canvas {
+            buffer: &mut buffer,
+            width,
+        },
+        &palette,
+        2,
+    );
+    assert!(lit(&buffer, 0, 100, 0, 20));
+    assert!(lit(&buffer, width - 100, width, 0, 20));
+    assert!(!lit(&buffer, 300, 700, 200, 300));
+
+    // Pausing shows in the middle, even with the statistics hidden
+    let mut overlay = Overlay::new(false);
+    let mut buffer = vec![0; width * height];
+    overlay.status(&status(true));
+    overlay.render(
+        &mut Canvas {
+            buffer: &mut buffer,
+            width,
+        },
+        &palette,
+        2,
+    );
+    assert!(lit(&buffer, 300, 700, 200, 300));
+    assert!(!lit(&buffer, 0, width, 0, 20));
+}
@@ -0,0 +1,86 @@
+use chip8::{
+    pack::{self, Pack},
+    rom::RomError,
+};
+use std::{
+    io::{Cursor, Write},
+    path::PathBuf,
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// A pack with two ROMs, one in a folder, and a file that isn't a ROM
+fn pack() -> Pack<Cursor<Vec<u8>>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let files: [(&str, &[u8]); 4] = [
+        ("readme.txt", b"Some games"),
+        ("superchip/car.sc8", &[0x00, 0xFF, 0x12, 0x02]),
+        ("pong.ch8", &[0x12, 0x00]),
+        ("empty.ch8", &[]),
+    ];
+    for (name, bytes) in files {
+        writer
+            .start_file(name, SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(bytes).unwrap();
+    }
+    let zip = writer.finish().unwrap().into_inner();
+    Pack::new(Cursor::new(zip), "pack.zip".to_owned()).unwrap()
+}
+
+#[test]
+fn test_entries() {
+    let pack = pack();
+    let names: Vec<_> = pack
+        .entries()
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect();
+
+    assert_eq!(names, ["empty.ch8", "pong.ch8", "superchip/car.sc8"]);
+    assert_eq!(pack.entries()[2].size, 4);
+}
+
+#[test]
+fn test_load() {
+    let mut pack = pack();
+
+    let rom = pack.load("pong.ch8").unwrap();
+    assert_eq!(rom.bytes(), [0x12, 0x00]);
+    assert_eq!(rom.platform(), "originalChip8");
+
+    let rom = pack.load("superchip/car.sc8").unwrap();
+    assert_eq!(rom.platform(), "superchip");
+
+    assert_eq!(pack.load("empty.ch8"), Err(RomError::Empty));
+    assert!(matches!(pack.load("missing.ch8"), Err(RomError::Io(_))));
+    assert_eq!(pack.read("readme.txt").unwrap(), b"Some games");
+}
+
+#[test]
+fn test_not_a_pack() {
+    assert!(matches!(
+        Pack::new(Cursor::new(b"not a zip".to_vec()), "pack.zip".to_owned()),
+        Err(RomError::Io(_))
+    ));
+    assert!(matches!(
+        Pack::open("roms/missing.zip"),
+        Err(RomError::Io(_))
+    ));
+}
+
+#[test]
+fn test_split() {
+    assert_eq!(
+        pack::split("roms/pack.zip:games/pong.ch8"),
+        Some((PathBuf::from("roms/pack.zip"), Some("games/pong.ch8")))
+    );
+    assert_eq!(
+        pack::split("PACK.ZIP:Pong.ch8"),
+        Some((PathBuf::from("PACK.ZIP"), Some("Pong.ch8")))
+    );
+    assert_eq!(
+        pack::split("roms/pack.zip"),
+        Some((PathBuf::from("roms/pack.zip"), None))
+    );
+    assert_eq!(pack::split("roms/games/tetris.ch8"), None);
+}
@@ -0,0 +1,58 @@
+use chip8::{palette::Palette, Display, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+#[test]
+fn test_parse_presets() {
+    assert_eq!(Palette::parse("classic"), Ok(Palette::CLASSIC));
+    assert_eq!(Palette::parse("Amber"), Ok(Palette::AMBER));
+    assert!(Palette::parse("sepia").is_err());
+}
+
+#[test]
+fn test_parse_hex() {
+    assert_eq!(
+        Palette::parse("#102030, 0xA0B0C0"),
+        Ok(Palette::new(0x102030, 0xA0B0C0))
+    );
+    assert_eq!(
+        Palette::parse("000000,ffffff,ff0000,00ff00").map(|p| p.colors),
+        Ok([0x000000, 0xFFFFFF, 0xFF0000, 0x00FF00])
+    );
+
+    assert!(Palette::parse("#000000").is_err());
+    assert!(Palette::parse("#000000,#GGGGGG").is_err());
+    assert!(Palette::parse("#000,#FFF").is_err());
+    assert!(Palette::parse("1,2,3,4,5").is_err());
+}
+
+#[test]
+fn test_render() {
+    let mut display: Display = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    display[0][1] = true;
+    display[1][0] = true;
+
+    let palette = Palette::new(0x112233, 0x445566);
+    let mut argb = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+    palette.render(&display, &mut argb);
+    assert_eq!(argb[0], 0xFF112233);
+    assert_eq!(argb[1], 0xFF445566);
+    assert_eq!(argb[DISPLAY_WIDTH], 0xFF445566);
+
+    let mut rgba = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+    palette.render_rgba(&display, &mut rgba);
+    assert_eq!(rgba[..8], [0x11, 0x22, 0x33, 0xFF, 0x44, 0x55, 0x66, 0xFF]);
+}
+
+#[test]
+fn test_render_planes() {
+    let mut first: Display = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+    let mut second = first;
+    first[0][0] = true;
+    first[0][2] = true;
+    second[0][1] = true;
+    second[0][2] = true;
+
+    let palette = Palette::parse("000000,111111,222222,333333").unwrap();
+    let mut argb = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+    palette.render_planes(&[first, second], &mut argb);
+    assert_eq!(argb[..4], [0xFF111111, 0xFF222222, 0xFF333333, 0xFF000000]);
+}
@@ -0,0 +1,68 @@
+use chip8::{
+    palette::Palette,
+    phosphor::{Persistence, Phosphor},
+    Display, DISPLAY_HEIGHT, DISPLAY_WIDTH,
+};
+
+const BLANK: Display = [[false; DISPLAY_WIDTH]; DISPLAY_HEIGHT];
+
+fn lit() -> Display {
+    let mut display = BLANK;
+    display[0][0] = true;
+    display
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(Persistence::parse("off"), Ok(Persistence::Off));
+    assert_eq!(Persistence::parse("decay:0.5"), Ok(Persistence::Decay(0.5)));
+    assert_eq!(Persistence::parse("or:3"), Ok(Persistence::Or(3)));
+    assert!(Persistence::parse("decay:2").is_err());
+    assert!(Persistence::parse("or:0").is_err());
+    assert!(Persistence::parse("or:9").is_err());
+    assert!(Persistence::parse("blur").is_err());
+}
+
+#[test]
+fn test_off() {
+    let mut phosphor = Phosphor::new(Persistence::Off);
+    phosphor.push(&lit());
+    assert_eq!(phosphor.intensity()[0][0], 255);
+    phosphor.push(&BLANK);
+    assert_eq!(phosphor.intensity()[0][0], 0);
+}
+
+#[test]
+fn test_decay() {
+    let mut phosphor = Phosphor::new(Persistence::Decay(0.5));
+    phosphor.push(&lit());
+    assert_eq!(phosphor.intensity()[0][0], 255);
+    phosphor.push(&BLANK);
+    assert_eq!(phosphor.intensity()[0][0], 127);
+    phosphor.push(&BLANK);
+    assert_eq!(phosphor.intensity()[0][0], 63);
+    phosphor.push(&lit());
+    assert_eq!(phosphor.intensity()[0][0], 255);
+}
+
+#[test]
+fn test_or() {
+    let mut phosphor = Phosphor::new(Persistence::Or(2));
+    phosphor.push(&lit());
+    phosphor.push(&BLANK);
+    assert_eq!(phosphor.intensity()[0][0], 255);
+    phosphor.push(&BLANK);
+    assert_eq!(phosphor.intensity()[0][0], 0);
+}
+
+#[test]
+fn test_render_intensity() {
+    let mut phosphor = Phosphor::new(Persistence::Decay(0.5));
+    phosphor.push(&lit());
+    phosphor.push(&BLANK);
+
+    let mut argb = [0; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+    Palette::new(0x000000, 0xFF00FE).render_intensity(phosphor.intensity(), &mut argb);
+    assert_eq!(argb[0], 0xFF7F007E);
+    assert_eq!(argb[1], 0xFF000000);
+}
@@ -0,0 +1,49 @@
+use chip8::{
+    platform::{ManualClock, XorShift, TIMER_PERIOD_MICROS},
+    structs::BehaviorConfig,
+    Chip,
+};
+
+#[test]
+fn test_manual_clock_drives_timers() {
+    // 603C   V(0) = 60
+    // F015   Delay Timer = V(0)
+    // 1204   Loop forever
+    let mut chip = Chip::with_platform(
+        [0x60, 0x3C, 0xF0, 0x15, 0x12, 0x04],
+        BehaviorConfig::default(),
+        ManualClock::new(),
+        XorShift::default(),
+    );
+
+    for _ in 0..10 {
+        chip.process_instruction();
+    }
+    assert_eq!(chip.delay_timer(), 60);
+
+    for _ in 0..5 {
+        chip.clock_mut().advance(TIMER_PERIOD_MICROS + 1);
+        chip.process_instruction();
+    }
+    assert_eq!(chip.delay_timer(), 55);
+}
+
+#[test]
+fn test_seeded_rng_is_deterministic() {
+    // C0FF   V(0) = random
+    // C1FF   V(1) = random
+    let program = [0xC0, 0xFF, 0xC1, 0xFF];
+    let run = || {
+        let mut chip = Chip::with_platform(
+            program,
+            BehaviorConfig::default(),
+            ManualClock::new(),
+            XorShift::new(1234),
+        );
+        chip.process_instruction();
+        chip.process_instruction();
+        (chip.registers().v0, chip.registers().v1)
+    };
+
+    assert_eq!(run(), run());
+}
@@ -0,0 +1,218 @@
+use chip8::{
+    quirks::{Quirk, QuirkCount, QuirkUsage},
+    structs::BehaviorConfig,
+    Chip,
+};
+
+fn run(program: &[u8], behavior: BehaviorConfig) -> Chip {
+    let mut chip = Chip::new(program, behavior);
+    for _ in 0..program.len() / 2 {
+        chip.process_instruction();
+    }
+    chip
+}
+
+#[test]
+fn test_for_platform() {
+    assert_eq!(
+        BehaviorConfig::for_platform("originalChip8"),
+        Some(BehaviorConfig::default())
+    );
+    for id in BehaviorConfig::PLATFORMS {
+        assert!(BehaviorConfig::for_platform(id).is_some(), "{}", id);
+    }
+    assert_eq!(BehaviorConfig::for_platform("megachip8"), None);
+}
+
+#[test]
+fn test_platform_of_behavior() {
+    assert_eq!(BehaviorConfig::default().platform(), Some("originalChip8"));
+    for id in ["modernChip8", "chip48", "xochip"] {
+        let behavior = BehaviorConfig::for_platform(id).unwrap();
+        assert_eq!(behavior.platform(), Some(id));
+    }
+    // The first of the platforms with the same quirks
+    let superchip = BehaviorConfig::for_platform("superchip").unwrap();
+    assert_eq!(superchip.platform(), Some("superchip1"));
+
+    let mut custom = BehaviorConfig::default();
+    *Quirk::Wrap.flag(&mut custom) = true;
+    assert!(custom.wrap);
+    assert_eq!(custom.platform(), None);
+}
+
+#[test]
+fn test_shift() {
+    // V0 = 0x03, V1 = 0x80, V0 = V1 >> 1 (or V0 >> 1)
+    let program = [0x60, 0x03, 0x61, 0x80, 0x80, 0x16];
+
+    let chip = run(&program, BehaviorConfig::default());
+    assert_eq!(chip.registers().v0, 0x40);
+    assert_eq!(chip.registers().vf, 0);
+
+    let shift = BehaviorConfig {
+        shift: true,
+        ..BehaviorConfig::default()
+    };
+    let chip = run(&program, shift);
+    assert_eq!(chip.registers().v0, 0x01);
+    assert_eq!(chip.registers().vf, 1);
+}
+
+#[test]
+fn test_jump() {
+    // V0 = 0x10, V3 = 0x20, B300
+    let program = [0x60, 0x10, 0x63, 0x20, 0xB3, 0x00];
+
+    let chip = run(&program, BehaviorConfig::default());
+    assert_eq!(chip.pc(), 0x310);
+
+    let jump = BehaviorConfig {
+        jump: true,
+        ..BehaviorConfig::default()
+    };
+    let chip = run(&program, jump);
+    assert_eq!(chip.pc(), 0x320);
+}
+
+#[test]
+fn test_increment_i() {
+    // I = 0x300, then load (or save) V0 to V2
+    for program in [[0xA3, 0x00, 0xF2, 0x65], [0xA3, 0x00, 0xF2, 0x55]] {
+        let increment = BehaviorConfig {
+            increment_i_on_save_load: true,
+            ..BehaviorConfig::default()
+        };
+        let chip = run(&program, increment);
+        assert_eq!(chip.i_reg(), 0x303);
+
+        let keep = BehaviorConfig {
+            increment_i_on_save_load: false,
+            ..BehaviorConfig::default()
+        };
+        let chip = run(&program, keep);
+        assert_eq!(chip.i_reg(), 0x300);
+    }
+}
+
+#[test]
+fn test_wrap() {
+    // V0 = 62, V1 = 31, I = font "0", draw 5 rows at (62, 31)
+    let program = [0x60, 62, 0x61, 31, 0xA0, 0x00, 0xD0, 0x15];
+
+    // Top row of "0" is 0xF0: four pixels, two off the right edge
+    let chip = run(&program, BehaviorConfig::default());
+    let display = chip.display();
+    assert!(display[31][62] && display[31][63]);
+    assert!(!display[31][0] && !display[0][62]);
+
+    let wrap = BehaviorConfig {
+        wrap: true,
+        ..BehaviorConfig::default()
+    };
+    let chip = run(&program, wrap);
+    let display = chip.display();
+    assert!(display[31][62] && display[31][63]);
+    assert!(display[31][0] && display[31][1]);
+    // Second row (0x90) wraps to the top
+    assert!(display[0][62] && !display[0][63]);
+}
+
+fn detect(program: &[u8], instructions: usize) -> QuirkUsage {
+    let mut chip = Chip::new(program, BehaviorConfig::default());
+    chip.detect_quirks();
+    for _ in 0..instructions {
+        chip.process_instruction();
+    }
+    chip.quirk_usage().unwrap().clone()
+}
+
+#[test]
+fn test_detect_nothing_by_default() {
+    let mut chip = Chip::new(
+        [0x60, 0x03, 0x61, 0x80, 0x80, 0x16],
+        BehaviorConfig::default(),
+    );
+    chip.run_frame(3);
+    assert_eq!(chip.quirk_usage(), None);
+}
+
+#[test]
+fn test_detect_vf_reset() {
+    let usage = detect(
+        &[
+            0x6F, 0x01, // 0x200: VF = 1
+            0x80, 0x11, // 0x202: V0 |= V1, VF is 0 or stays 1
+            0x40, 0x00, // 0x204: reads V0, not VF
+            0x4F, 0x00, // 0x206: reads VF
+            0x6F, 0x00, // 0x208: VF = 0
+            0x80, 0x11, // 0x20A: VF is 0 either way
+            0x4F, 0x00, // 0x20C: reads VF
+        ],
+        7,
+    );
+
+    assert_eq!(
+        usage.get(Quirk::VfReset),
+        QuirkCount {
+            count: 1,
+            first_pc: Some(0x202)
+        }
+    );
+}
+
+#[test]
+fn test_detect_increment_i() {
+    let usage = detect(
+        &[
+            0xA3, 0x00, // I = 0x300
+            0xF1, 0x55, // save V0, V1
+            0xA3, 0x00, // I = 0x300 again, so it didn't matter
+            0xF1, 0x65, // load V0, V1
+            0xF1, 0x1E, // I += V1, depends on the load
+        ],
+        5,
+    );
+
+    // Only the load mattered
+    assert_eq!(
+        usage.get(Quirk::IncrementI),
+        QuirkCount {
+            count: 1,
+            first_pc: Some(0x206)
+        }
+    );
+
+    let usage = detect(&[0xA3, 0x00, 0xF1, 0x55, 0xF1, 0x55], 3);
+    assert_eq!(
+        usage.get(Quirk::IncrementI),
+        QuirkCount {
+            count: 1,
+            first_pc: Some(0x202)
+        }
+    );
+}
+
+#[test]
+fn test_detect_shift_jump_and_wrap() {
+    // Same registers: nothing matters
+    let usage = detect(&[0x80, 0x06, 0xB0, 0x00], 2);
+    assert!(usage.is_empty());
+
+    let usage = detect(
+        &[
+            0x61, 0x01, // 0x200: V1 = 1
+            0x80, 0x16, // 0x202: shift, V0 != V1
+            0x60, 62, // 0x204: V0 = 62
+            0xA0, 0x00, // 0x206: I = font "0"
+            0xD0, 0x15, // 0x208: draw at (62, 1), across the right edge
+            0xB1, 0x00, // 0x20A: jump, V1 != V0
+        ],
+        6,
+    );
+
+    assert_eq!(usage.get(Quirk::Shift).first_pc, Some(0x202));
+    assert_eq!(usage.get(Quirk::Wrap).first_pc, Some(0x208));
+    assert_eq!(usage.get(Quirk::Jump).first_pc, Some(0x20A));
+    assert_eq!(usage.get(Quirk::VfReset).count, 0);
+}
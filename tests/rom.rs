@@ -0,0 +1,69 @@
+use chip8::{
+    rom::{self, Rom, RomError, MAX_SIZE},
+    structs::BehaviorConfig,
+};
+use std::{env, fs};
+
+#[test]
+fn test_load() {
+    let rom = Rom::load("roms/games/tetris.ch8").unwrap();
+
+    assert_eq!(rom.bytes(), fs::read("roms/games/tetris.ch8").unwrap());
+    assert_eq!(rom.platform(), "originalChip8");
+    assert_eq!(rom.behavior(), BehaviorConfig::default());
+    assert_eq!(rom.sha1(), "5f518084744bf3cb8733f6e5454dfd1634320563");
+
+    assert!(matches!(
+        Rom::load("roms/games/missing.ch8"),
+        Err(RomError::Io(_))
+    ));
+}
+
+#[test]
+fn test_platform_from_extension() {
+    assert_eq!(rom::platform_for("game.ch8"), Some("originalChip8"));
+    assert_eq!(rom::platform_for("game.C8"), Some("originalChip8"));
+    assert_eq!(rom::platform_for("game.hc8"), Some("hybridVIP"));
+    assert_eq!(rom::platform_for("dir/game.sc8"), Some("superchip"));
+    assert_eq!(rom::platform_for("game.xo8"), Some("xochip"));
+    assert_eq!(rom::platform_for("game.bin"), None);
+    assert_eq!(rom::platform_for("game"), None);
+
+    let path = env::temp_dir().join(format!("chip8-rom-{}.sc8", std::process::id()));
+    fs::write(&path, [0x00, 0xFF]).unwrap();
+    let rom = Rom::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    let rom = rom.unwrap();
+    assert_eq!(rom.platform(), "superchip");
+    assert_eq!(
+        rom.behavior(),
+        BehaviorConfig::for_platform("superchip").unwrap()
+    );
+}
+
+#[test]
+fn test_size_validation() {
+    assert_eq!(Rom::new(vec![], "originalChip8"), Err(RomError::Empty));
+
+    // The VIP keeps its stack and display at the top of memory
+    let vip_max = rom::max_size("originalChip8");
+    assert!(vip_max < MAX_SIZE);
+    assert!(Rom::new(vec![0; vip_max], "originalChip8").is_ok());
+    assert_eq!(
+        Rom::new(vec![0; vip_max + 1], "originalChip8"),
+        Err(RomError::TooLarge {
+            size: vip_max + 1,
+            max: vip_max,
+            platform: "originalChip8"
+        })
+    );
+
+    assert!(Rom::new(vec![0; MAX_SIZE], "superchip").is_ok());
+    assert!(Rom::new(vec![0; MAX_SIZE + 1], "xochip").is_err());
+
+    assert_eq!(
+        Rom::new(vec![0], "gameboy"),
+        Err(RomError::UnknownPlatform("gameboy".to_owned()))
+    );
+}
@@ -0,0 +1,100 @@
+use chip8::{
+    romdb::{Database, Quirks},
+    structs::BehaviorConfig,
+};
+use std::fs;
+
+#[test]
+fn test_bundled_knows_the_roms() {
+    let database = Database::bundled();
+
+    for path in [
+        "roms/games/tetris.ch8",
+        "roms/games/flightrunner.ch8",
+        "roms/tests/ibm.ch8",
+        "roms/tests/corax.ch8",
+        "roms/tests/flags.ch8",
+        "roms/tests/quirks.ch8",
+        "roms/tests/keypad.ch8",
+        "roms/tests/sound.ch8",
+    ] {
+        let (_, rom) = database
+            .lookup(&fs::read(path).unwrap())
+            .unwrap_or_else(|| panic!("{} should be in the database", path));
+        assert!(rom.behavior().is_some(), "{}", path);
+    }
+
+    let (program, rom) = database
+        .lookup(&fs::read("roms/games/tetris.ch8").unwrap())
+        .unwrap();
+    assert_eq!(program.title, "Tetris");
+    assert_eq!(rom.behavior(), Some(BehaviorConfig::default()));
+    assert_eq!(rom.keys.get("left"), Some(&5));
+
+    assert!(database.lookup(&[0x12, 0x00]).is_none());
+}
+
+/// A trimmed down entry of the community database, with fields we don't read
+const PROGRAMS: &str = r##"[
+  {
+    "title": "Some Game",
+    "origin": { "type": "gamejam", "reference": "Octojam 1" },
+    "roms": {
+      "0123456789ABCDEF0123456789ABCDEF01234567": {
+        "file": "game.ch8",
+        "platforms": ["megachip8", "superchip", "xochip"],
+        "quirkyPlatforms": {
+          "superchip": { "shift": false, "vblank": true }
+        },
+        "tickrate": 30,
+        "keys": { "up": 5, "a": 6 },
+        "colors": { "pixels": ["#000000", "#ff8800"], "buzzer": "#ffffff" },
+        "screenRotation": 0
+      }
+    }
+  }
+]"##;
+
+#[test]
+fn test_parse_community_format() {
+    let database = Database::parse(PROGRAMS).unwrap();
+    let (program, rom) = database
+        .find("0123456789abcdef0123456789abcdef01234567")
+        .unwrap();
+
+    assert_eq!(program.title, "Some Game");
+    assert_eq!(rom.tickrate, Some(30));
+    assert_eq!(rom.keys.get("a"), Some(&6));
+    assert_eq!(rom.colors.as_ref().unwrap().pixels.len(), 2);
+
+    // megachip8 isn't supported, so superchip with its quirk overridden
+    assert_eq!(
+        rom.behavior(),
+        Some(BehaviorConfig {
+            shift: false,
+            ..BehaviorConfig::for_platform("superchip").unwrap()
+        })
+    );
+
+    assert!(Database::parse("{}").is_err());
+}
+
+#[test]
+fn test_quirks_apply() {
+    let mut behavior = BehaviorConfig::default();
+    Quirks {
+        memory_leave_i_unchanged: Some(true),
+        logic: Some(false),
+        ..Quirks::default()
+    }
+    .apply(&mut behavior);
+
+    assert_eq!(
+        behavior,
+        BehaviorConfig {
+            increment_i_on_save_load: false,
+            vf_reset: false,
+            ..BehaviorConfig::default()
+        }
+    );
+}
@@ -0,0 +1,55 @@
+use chip8::frontend::screenshot;
+use std::{env, fs};
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes.try_into().unwrap())
+}
+
+#[test]
+fn test_encode_png() {
+    let (width, height) = (300, 200);
+    let pixels: Vec<u32> = (0..width * height).map(|n| n as u32 * 0x010203).collect();
+    let png = screenshot::encode_png(&pixels, width, height);
+
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+
+    // Walk the chunks
+    let mut chunks = vec![];
+    let mut at = 8;
+    while at < png.len() {
+        let len = be_u32(&png[at..at + 4]) as usize;
+        let kind = String::from_utf8(png[at + 4..at + 8].to_vec()).unwrap();
+        chunks.push((kind, png[at + 8..at + 8 + len].to_vec()));
+        at += 12 + len;
+    }
+    assert_eq!(at, png.len());
+
+    let kinds: Vec<_> = chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+    assert_eq!(kinds, ["IHDR", "IDAT", "IEND"]);
+    assert_eq!(be_u32(&chunks[0].1[0..4]), width as u32);
+    assert_eq!(be_u32(&chunks[0].1[4..8]), height as u32);
+
+    // Stored blocks hold every row, each with a filter byte: header, 3 bytes
+    // per pixel, 5 bytes per block of at most 65535, and the checksum
+    let raw = (width * 3 + 1) * height;
+    let blocks = raw.div_ceil(0xFFFF);
+    assert_eq!(chunks[1].1.len(), 2 + raw + 5 * blocks + 4);
+}
+
+#[test]
+fn test_crc() {
+    // The IEND chunk is always the same, CRC included
+    let png = screenshot::encode_png(&[0], 1, 1);
+    assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xAE\x42\x60\x82");
+}
+
+#[test]
+fn test_save() {
+    let dir = env::temp_dir();
+    let path = screenshot::save(&[0xFFFFFF; 4], 2, 2, &dir).unwrap();
+    let saved = fs::read(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(path.starts_with(&dir));
+    assert_eq!(saved.unwrap(), screenshot::encode_png(&[0xFFFFFF; 4], 2, 2));
+}
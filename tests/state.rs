@@ -0,0 +1,76 @@
+use chip8::{
+    state::STATE_SIZE,
+    structs::{BehaviorConfig, InvalidState, OutOfBounds},
+    Chip,
+};
+use std::fs;
+
+#[test]
+fn test_state_inspection() {
+    // 6A2A   V(A) = 0x2A
+    // A300   I = 0x300
+    // 2206   Call 0x206
+    // 00EE   Return (never reached before the asserts)
+    let mut chip = Chip::new(
+        vec![0x6A, 0x2A, 0xA3, 0x00, 0x22, 0x06, 0x00, 0xEE],
+        BehaviorConfig::default(),
+    );
+
+    assert_eq!(chip.pc(), 0x200);
+    assert_eq!(chip.memory(0x200..0x202), Some(&[0x6A, 0x2A][..]));
+
+    for _ in 0..3 {
+        chip.process_instruction();
+    }
+
+    assert_eq!(chip.registers().va, 0x2A);
+    assert_eq!(chip.i_reg(), 0x300);
+    assert_eq!(chip.pc(), 0x206);
+    assert_eq!(chip.stack(), &[0x206]);
+    assert_eq!(chip.delay_timer(), 0);
+    assert_eq!(chip.sound_timer(), 0);
+    assert_eq!(chip.waiting_for_key(), None);
+}
+
+#[test]
+fn test_state_mutation() {
+    let mut chip = Chip::new(vec![], BehaviorConfig::default());
+
+    // F00A   Wait for a key into V(0)
+    chip.write_memory(0x300, &[0xF0, 0x0A]).unwrap();
+    chip.set_pc(0x300).unwrap();
+    chip.process_instruction();
+    assert_eq!(chip.waiting_for_key(), Some(0));
+
+    chip.registers_mut().set(0x3, 7);
+    assert_eq!(chip.registers().get(0x3), 7);
+
+    assert_eq!(chip.memory(4000..5000), None);
+    assert_eq!(chip.write_memory(0xFFE, &[1, 2, 3]), Err(OutOfBounds));
+    assert_eq!(chip.memory(0xFFE..0x1000), Some(&[0, 0][..]));
+    assert_eq!(chip.set_pc(0xFFF), Err(OutOfBounds));
+    assert_eq!(chip.set_i_reg(0x1000), Err(OutOfBounds));
+}
+
+#[test]
+fn test_save_and_load_state() {
+    let mut chip = Chip::new(
+        fs::read("roms/tests/ibm.ch8").unwrap(),
+        BehaviorConfig::default(),
+    );
+    for _ in 0..20 {
+        chip.process_instruction();
+    }
+
+    let mut saved = [0; STATE_SIZE];
+    chip.save_state(&mut saved);
+
+    let mut other = Chip::new(vec![0x00, 0xE0], BehaviorConfig::default());
+    other.load_state(&saved).unwrap();
+    assert_eq!(other.display(), chip.display());
+    assert_eq!(other.pc(), chip.pc());
+    assert_eq!(other.i_reg(), chip.i_reg());
+    assert_eq!(other.memory(0..4096), chip.memory(0..4096));
+
+    assert_eq!(other.load_state(&saved[1..]), Err(InvalidState));
+}
@@ -0,0 +1,64 @@
+use chip8::frontend::text::{self, Canvas, ADVANCE, GLYPH_HEIGHT};
+
+#[test]
+fn test_glyph() {
+    assert_eq!(text::glyph('1'), [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]);
+    assert_eq!(text::glyph(' '), [0; GLYPH_HEIGHT]);
+    // Not ASCII
+    assert_eq!(text::glyph('é'), text::glyph('?'));
+    assert_eq!(text::glyph('\n'), text::glyph('?'));
+}
+
+#[test]
+fn test_text_width() {
+    assert_eq!(text::text_width("", 2), 0);
+    assert_eq!(text::text_width("A", 1), 5);
+    assert_eq!(text::text_width("AB", 2), (ADVANCE + 5) * 2);
+}
+
+#[test]
+fn test_draw_text() {
+    let mut buffer = vec![0; 12 * 8];
+    let mut canvas = Canvas {
+        buffer: &mut buffer,
+        width: 12,
+    };
+    canvas.draw_text(0, 0, 1, "1!", 1);
+
+    let rows: Vec<String> = buffer
+        .chunks(12)
+        .map(|row| {
+            row.iter()
+                .map(|p| if *p == 1 { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            "..#.....#...",
+            ".##.....#...",
+            "..#.....#...",
+            "..#.....#...",
+            "..#.....#...",
+            "..#.........",
+            ".###....#...",
+            "............",
+        ]
+    );
+}
+
+#[test]
+fn test_clipping() {
+    let mut buffer = vec![0; 4 * 4];
+    let mut canvas = Canvas {
+        buffer: &mut buffer,
+        width: 4,
+    };
+    canvas.draw_text(2, 2, 3, "WWW", 1);
+    canvas.fill_rect(3, 3, 10, 10, 2);
+
+    assert_eq!(buffer[2 * 4 + 2], 1);
+    assert_eq!(buffer[15], 2);
+    assert_eq!(buffer[..8], [0; 8]);
+}
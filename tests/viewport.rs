@@ -0,0 +1,99 @@
+use chip8::frontend::viewport::{Grid, Viewport, LETTERBOX, MAX_SCALE};
+
+const DISPLAY: (usize, usize) = (64, 32);
+
+#[test]
+fn test_parse_grid() {
+    assert_eq!(Grid::parse("off"), Ok(Grid::Off));
+    assert_eq!(Grid::parse(" scanlines "), Ok(Grid::Scanlines));
+    assert_eq!(Grid::parse("grid"), Ok(Grid::Grid));
+    assert!(Grid::parse("dots").is_err());
+}
+
+#[test]
+fn test_fit() {
+    // Exactly the display's aspect ratio
+    let viewport = Viewport::fit(DISPLAY, 1024, 512);
+    assert_eq!(viewport, Viewport::exact(DISPLAY, 16));
+
+    // Too tall: bars above and below
+    let viewport = Viewport::fit(DISPLAY, 1000, 800);
+    assert_eq!(viewport.scale, 15);
+    assert_eq!((viewport.x, viewport.y), (20, 160));
+
+    // Too wide: bars on the sides
+    let viewport = Viewport::fit(DISPLAY, 1920, 1080);
+    assert_eq!(viewport.scale, 30);
+    assert_eq!((viewport.x, viewport.y), (0, 60));
+
+    // Never below 1 or above the maximum
+    assert_eq!(Viewport::fit(DISPLAY, 10, 10).scale, 1);
+    assert_eq!(Viewport::fit(DISPLAY, 10_000, 5_000).scale, MAX_SCALE);
+
+    // Other resolutions are laid out the same way
+    let viewport = Viewport::fit((128, 64), 1024, 600);
+    assert_eq!((viewport.scale, viewport.x, viewport.y), (8, 0, 44));
+}
+
+#[test]
+fn test_render_letterbox() {
+    let display = (2, 1);
+    let source = [0xFFFF_FFFF, 0xFF11_2233];
+    let viewport = Viewport::fit(display, 6, 4);
+    assert_eq!((viewport.scale, viewport.x, viewport.y), (3, 0, 0));
+
+    let mut output = vec![0; 6 * 4];
+    viewport.render(&source, display, Grid::Off, &mut output);
+    let white = 0xFFFF_FFFF;
+    let other = 0xFF11_2233;
+    for row in output.chunks(6).take(3) {
+        assert_eq!(row, [white, white, white, other, other, other]);
+    }
+    assert_eq!(output[18..], [LETTERBOX; 6]);
+}
+
+#[test]
+fn test_render_lines() {
+    let display = (1, 1);
+    let source = [0xFFFF_FFFF];
+    let dim = 0xFF7F_7F7F;
+    let viewport = Viewport::exact(display, 3);
+    let mut output = vec![0; 9];
+
+    viewport.render(&source, display, Grid::Scanlines, &mut output);
+    assert_eq!(output[..6], [0xFFFF_FFFF; 6]);
+    assert_eq!(output[6..], [dim; 3]);
+
+    viewport.render(&source, display, Grid::Grid, &mut output);
+    assert_eq!(
+        output,
+        [
+            0xFFFF_FFFF,
+            0xFFFF_FFFF,
+            dim,
+            0xFFFF_FFFF,
+            0xFFFF_FFFF,
+            dim,
+            dim,
+            dim,
+            dim
+        ]
+    );
+
+    // Too small for lines
+    let viewport = Viewport::exact(display, 2);
+    let mut output = vec![0; 4];
+    viewport.render(&source, display, Grid::Grid, &mut output);
+    assert_eq!(output, [0xFFFF_FFFF; 4]);
+}
+
+#[test]
+fn test_render_cut_off() {
+    // A window smaller than the display shows its top left corner
+    let display = (4, 2);
+    let source: Vec<u32> = (0..8).collect();
+    let viewport = Viewport::fit(display, 3, 1);
+    let mut output = vec![0; 3];
+    viewport.render(&source, display, Grid::Off, &mut output);
+    assert_eq!(output, [0, 1, 2]);
+}
@@ -0,0 +1,154 @@
+use chip8::{
+    frontend::{
+        text::Canvas,
+        virtual_keypad::{Rect, VirtualKeypad, LAYOUT},
+        Status,
+    },
+    palette::Palette,
+    structs::BehaviorConfig,
+};
+
+const WIDTH: usize = 1024;
+const HEIGHT: usize = 512;
+
+fn status(held_keys: u16, waiting_for_key: Option<u8>) -> Status {
+    Status {
+        frames_run: 1,
+        instructions_per_frame: 11,
+        speed: 100,
+        paused: false,
+        behavior: BehaviorConfig::default(),
+        held_keys,
+        waiting_for_key,
+    }
+}
+
+#[test]
+fn test_layout() {
+    // 1 is top left, F bottom right, in the corner of the canvas
+    let one = VirtualKeypad::key_rect(0x1, WIDTH, HEIGHT, 2).unwrap();
+    let f = VirtualKeypad::key_rect(0xF, WIDTH, HEIGHT, 2).unwrap();
+    assert!(one.x < f.x && one.y < f.y);
+    assert_eq!(f.x + f.width, WIDTH - 4);
+    assert_eq!(f.y + f.height, HEIGHT - 4);
+    assert_eq!(VirtualKeypad::key_rect(0x10, WIDTH, HEIGHT, 2), None);
+
+    // Keys of a row line up
+    for row in LAYOUT {
+        let rects: Vec<Rect> = row
+            .iter()
+            .map(|key| VirtualKeypad::key_rect(*key, WIDTH, HEIGHT, 2).unwrap())
+            .collect();
+        assert!(rects.iter().all(|rect| rect.y == rects[0].y));
+        assert!(rects.windows(2).all(|pair| pair[0].x < pair[1].x));
+    }
+}
+
+#[test]
+fn test_key_at() {
+    let mut keypad = VirtualKeypad::new(false);
+    let rect = VirtualKeypad::key_rect(0xA, WIDTH, HEIGHT, 2).unwrap();
+    let (x, y) = (rect.x + rect.width / 2, rect.y + rect.height / 2);
+
+    // Nothing to click while hidden
+    assert_eq!(keypad.key_at(x, y, WIDTH, HEIGHT, 2), None);
+
+    keypad.toggle();
+    for key in 0..=0xF {
+        let rect = VirtualKeypad::key_rect(key, WIDTH, HEIGHT, 2).unwrap();
+        assert_eq!(keypad.key_at(rect.x, rect.y, WIDTH, HEIGHT, 2), Some(key));
+        let (right, bottom) = (rect.x + rect.width - 1, rect.y + rect.height - 1);
+        assert_eq!(keypad.key_at(right, bottom, WIDTH, HEIGHT, 2), Some(key));
+    }
+    // Between keys, and away from the keypad
+    assert_eq!(
+        keypad.key_at(rect.x + rect.width, y, WIDTH, HEIGHT, 2),
+        None
+    );
+    assert_eq!(keypad.key_at(10, 10, WIDTH, HEIGHT, 2), None);
+}
+
+#[test]
+fn test_caption() {
+    let mut keypad = VirtualKeypad::new(true);
+    keypad.status(&status(0, None));
+    assert_eq!(keypad.caption(), None);
+
+    keypad.status(&status(0, Some(0x3)));
+    assert_eq!(keypad.caption().unwrap(), "Fx0A: any key -> V3");
+
+    keypad.status(&status(1 << 0xB, Some(0xA)));
+    assert!(keypad.is_held(0xB));
+    assert!(!keypad.is_held(0x0));
+    assert_eq!(keypad.caption().unwrap(), "Fx0A: release B -> VA");
+}
+
+#[test]
+fn test_needs_redraw() {
+    let mut keypad = VirtualKeypad::new(false);
+    assert!(!keypad.needs_redraw());
+
+    // Keys changing only matter while shown
+    keypad.status(&status(1, None));
+    assert!(!keypad.needs_redraw());
+
+    keypad.toggle();
+    assert!(keypad.needs_redraw());
+    assert!(!keypad.needs_redraw());
+
+    keypad.status(&status(1, None));
+    assert!(!keypad.needs_redraw());
+    keypad.status(&status(1, Some(0)));
+    assert!(keypad.needs_redraw());
+}
+
+#[test]
+fn test_render() {
+    let palette = Palette::new(0x000000, 0xFFFFFF);
+    let mut buffer = vec![0; WIDTH * HEIGHT];
+    let center = |buffer: &[u32], key: u8| {
+        let rect = VirtualKeypad::key_rect(key, WIDTH, HEIGHT, 2).unwrap();
+        // Left of the label, inside the outline
+        buffer[(rect.y + rect.height / 2) * WIDTH + rect.x + 4]
+    };
+
+    let mut keypad = VirtualKeypad::new(false);
+    keypad.status(&status(1 << 0x5, None));
+    keypad.render(
+        &mut Canvas {
+            buffer: &mut buffer,
+            width: WIDTH,
+        },
+        &palette,
+        2,
+    );
+    assert!(buffer.iter().all(|pixel| *pixel == 0));
+
+    // Held keys are filled in
+    keypad.toggle();
+    keypad.render(
+        &mut Canvas {
+            buffer: &mut buffer,
+            width: WIDTH,
+        },
+        &palette,
+        2,
+    );
+    assert_eq!(center(&buffer, 0x5), 0xFFFFFF);
+    assert_eq!(center(&buffer, 0x6), 0x000000);
+
+    // The caption goes above the keypad
+    let one = VirtualKeypad::key_rect(0x1, WIDTH, HEIGHT, 2).unwrap();
+    let above = |buffer: &[u32]| buffer[..one.y * WIDTH].contains(&0xFFFFFF);
+    assert!(!above(&buffer));
+    keypad.status(&status(0, Some(0)));
+    keypad.render(
+        &mut Canvas {
+            buffer: &mut buffer,
+            width: WIDTH,
+        },
+        &palette,
+        2,
+    );
+    assert!(above(&buffer));
+}